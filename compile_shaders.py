@@ -10,8 +10,11 @@ if shutil.which("glslc"):
     os.mkdir(spirv_dir)
     for fname in os.listdir(source_dir):
         source_file = os.path.join(source_dir, fname)
-        output_file = os.path.join(
-            spirv_dir, f"{os.path.splitext(fname)[1][1:]}.spv")
+        stem, ext = os.path.splitext(fname)
+        # The main pipeline shaders keep their stage only names, every other
+        # shader is named after its source so stages can't collide
+        spirv_name = ext[1:] if stem == "shader" else f"{stem}_{ext[1:]}"
+        output_file = os.path.join(spirv_dir, f"{spirv_name}.spv")
         out = subprocess.run(f"glslc {source_file} -o {output_file}",
                              shell=True, capture_output=True)
         if out.stdout:
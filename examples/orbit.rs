@@ -0,0 +1,34 @@
+use rust_gamephysics::{
+    math::types::Vector3,
+    physics::{RigidBodyBuilder, Shape},
+};
+use std::f32::consts::PI;
+
+const ATTRACTOR_STRENGTH: f32 = 1.0;
+const ORBIT_RADIUS: f32 = 1.0;
+const STEPS_PER_PERIOD: usize = 1000;
+const PERIODS: usize = 100;
+
+fn main() {
+    let speed = f32::sqrt(ATTRACTOR_STRENGTH / ORBIT_RADIUS);
+    let mut body = RigidBodyBuilder::new()
+        .with_position(Vector3::new(ORBIT_RADIUS, 0.0, 0.0))
+        .with_linear_velocity(Vector3::new(0.0, speed, 0.0))
+        .build(Shape::new_sphere(0.1));
+
+    let period = 2.0 * PI * ORBIT_RADIUS / speed;
+    let dt = period / STEPS_PER_PERIOD as f32;
+    let (mut min_radius, mut max_radius) = (ORBIT_RADIUS, ORBIT_RADIUS);
+    for _ in 0..PERIODS * STEPS_PER_PERIOD {
+        let radius = body.position.mag();
+        body.apply_force(-body.position * (ATTRACTOR_STRENGTH / (radius * radius * radius)));
+        body.integrate(dt);
+        min_radius = f32::min(min_radius, body.position.mag());
+        max_radius = f32::max(max_radius, body.position.mag());
+    }
+
+    println!(
+        "Orbit radius after {} periods stayed within [{:.4}, {:.4}]",
+        PERIODS, min_radius, max_radius
+    );
+}
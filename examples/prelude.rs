@@ -0,0 +1,20 @@
+use rust_gamephysics::prelude::*;
+
+fn main() {
+    let mut scene = SceneBuilder::new();
+    let sphere = scene.add_shape(Shape::new_sphere(1.0));
+    scene.add_instance(sphere, Vector3::ZERO);
+    scene.set_camera(Vector3::new(5.0, 5.0, 5.0), Vector3::ZERO);
+    let scene: Scene = scene.build(45.0, 1.0, 0.1, 100.0).unwrap();
+
+    let mut world = PhysicsWorld::new();
+    let body: BodyHandle =
+        world.add_body(RigidBody::new(Shape::new_sphere(1.0), Vector3::ZERO, 1.0));
+    world.step(1.0 / 60.0);
+
+    println!(
+        "{} objects, body at {:?}",
+        scene.objects().count(),
+        world.body(body).map(|body| body.position)
+    );
+}
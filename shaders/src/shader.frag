@@ -1,6 +1,9 @@
 #version 460 core
 #define VULKAN 100
 
+#define COLOR_MODE_VERTEX 0
+#define COLOR_MODE_MATERIAL 1
+
 layout(location=0) in VS_OUT {
     vec3 norm;
     vec4 tang;
@@ -8,8 +11,19 @@ layout(location=0) in VS_OUT {
     vec2 tex;
 } fs_in;
 
+layout(set=0, binding=0) uniform Material {
+    vec4 albedo;
+    uint color_mode;
+    float alpha;
+} material;
+
 layout(location=0) out vec4 frag_color;
 
 void main() {
-    frag_color = fs_in.color;
+    if (material.color_mode == COLOR_MODE_MATERIAL) {
+        frag_color = material.albedo;
+    } else {
+        frag_color = fs_in.color;
+    }
+    frag_color.a *= material.alpha;
 }
\ No newline at end of file
@@ -0,0 +1,18 @@
+#version 460 core
+#define VULKAN 100
+
+layout(location=0) in VS_OUT {
+    vec4 color;
+    vec2 tex;
+} fs_in;
+
+layout(set=0, binding=0) uniform sampler2D atlas;
+
+layout(location=0) out vec4 frag_color;
+
+void main() {
+    float distance = texture(atlas, fs_in.tex).r;
+    float width = fwidth(distance);
+    float coverage = smoothstep(0.5 - width, 0.5 + width, distance);
+    frag_color = vec4(fs_in.color.rgb, fs_in.color.a * coverage);
+}
@@ -0,0 +1,24 @@
+#version 460 core
+#define VULKAN 100
+
+layout(location=0) in vec3 pos;
+layout(location=1) in vec4 color;
+layout(location=2) in vec2 tex;
+
+layout(push_constant) uniform Screen {
+    vec2 extent;
+} screen;
+
+layout(location=0) out VS_OUT {
+    vec4 color;
+    vec2 tex;
+} vs_out;
+
+void main() {
+    vs_out.color = color;
+    vs_out.tex = tex;
+    // Window pixels with the origin at the top left corner, the viewport is
+    // flipped so clip space y points up
+    vec2 ndc = pos.xy / screen.extent * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
@@ -0,0 +1,87 @@
+#version 460 core
+#define VULKAN 100
+
+// Ray marches a signed distance volume loaded with Device::load_texture_3d.
+// The volume spans the unit cube [-0.5, 0.5]^3 in mesh space, draw it on a
+// cube mesh with front face culling so fragments exist inside the volume too.
+//
+// No pipeline uses this shader yet, it expects the material uniform of the
+// main pipeline, a second descriptor set for the volume sampler and a vertex
+// stage forwarding mesh space position. The push constants are the ones of the
+// main pipeline, the eye position is recovered from them.
+
+#define MAX_STEPS 128
+#define SURFACE_EPSILON 0.001
+#define NORMAL_EPSILON 0.01
+
+layout(location=0) in VS_OUT {
+    vec3 norm;
+    vec4 tang;
+    vec4 color;
+    vec2 tex;
+    vec3 pos;
+} fs_in;
+
+layout(push_constant) uniform Transforms {
+    mat4 camera;
+    mat4 world;
+} transforms;
+
+layout(set=0, binding=0) uniform Material {
+    vec4 albedo;
+    uint color_mode;
+    float alpha;
+} material;
+
+layout(set=1, binding=0) uniform sampler3D volume;
+
+layout(location=0) out vec4 frag_color;
+
+float distance_at(vec3 pos) {
+    return texture(volume, pos + 0.5).r;
+}
+
+vec3 normal_at(vec3 pos) {
+    vec2 e = vec2(NORMAL_EPSILON, 0.0);
+    return normalize(vec3(
+        distance_at(pos + e.xyy) - distance_at(pos - e.xyy),
+        distance_at(pos + e.yxy) - distance_at(pos - e.yxy),
+        distance_at(pos + e.yyx) - distance_at(pos - e.yyx)
+    ));
+}
+
+// Entry and exit distances of the ray through the unit cube
+vec2 intersect_volume(vec3 origin, vec3 dir) {
+    vec3 t0 = (vec3(-0.5) - origin) / dir;
+    vec3 t1 = (vec3(0.5) - origin) / dir;
+    vec3 near = min(t0, t1);
+    vec3 far = max(t0, t1);
+    return vec2(max(max(near.x, near.y), max(near.z, 0.0)), min(min(far.x, far.y), far.z));
+}
+
+// A perspective projection maps the eye to a clip space point at infinity,
+// (0, 0, z, 0), mapping that back gives the eye in mesh space.
+vec3 mesh_eye() {
+    vec4 eye = inverse(transforms.camera * transforms.world) * vec4(0.0, 0.0, 1.0, 0.0);
+    return eye.xyz / eye.w;
+}
+
+void main() {
+    vec3 eye = mesh_eye();
+    vec3 dir = normalize(fs_in.pos - eye);
+    vec2 range = intersect_volume(eye, dir);
+
+    float t = range.x;
+    for (int step = 0; step < MAX_STEPS && t < range.y; step++) {
+        vec3 pos = eye + dir * t;
+        float dist = distance_at(pos);
+        if (dist < SURFACE_EPSILON) {
+            vec3 norm = normal_at(pos);
+            float light = max(dot(norm, -dir), 0.0);
+            frag_color = vec4(material.albedo.rgb * (0.2 + 0.8 * light), 1.0);
+            return;
+        }
+        t += dist;
+    }
+    discard;
+}
@@ -1,12 +1,19 @@
+use std::{fmt, path::PathBuf};
 use winit::{
     dpi::PhysicalSize,
-    event::{Event, WindowEvent},
+    event::{Event, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     window::{Window, WindowBuilder},
 };
 
+#[cfg(feature = "profile")]
+use crate::utils::profiler;
 use crate::{
+    ecs, log,
+    math::types::{Matrix4, Vector2, Vector4},
+    physics::PhysicsWorld,
     renderer,
+    replay::{RecordedEvent, ReplayPlayer, ReplayRecorder},
     scene::{Scene, SceneBuilder},
     utils::StaticResult,
 };
@@ -15,12 +22,45 @@ const DEFAULT_WINDOW_HEIGHT: u32 = 728;
 const DEFAULT_WINDOW_WIDTH: u32 = 1024;
 const DEFAULT_APPLICATION_TITLE: &'static str = "RustGamephysics";
 const DEFAULT_RENDERER_BACKEND: renderer::Backend = renderer::Backend::Vulkan;
+const FRAME_STATS_INTERVAL: u64 = 300;
 
+#[derive(Debug)]
 pub struct ApplicationBuilder {
     title: &'static str,
     extent: (u32, u32),
+    resizable: bool,
     backend: renderer::Backend,
+    renderer_config: renderer::Config,
     scene_builder: Option<SceneBuilder>,
+    entities: ecs::World,
+    world: Option<PhysicsWorld>,
+    replay: ReplayMode,
+    handlers: EventHandlers,
+    broadphase_debug: bool,
+    clear_color: Option<(Vector4, renderer::ColorSpace)>,
+}
+
+/// Rendering pauses while the window is minimized, which leaves its surface
+/// with a zero extent no swapchain can be created for, or out of focus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WindowState {
+    extent: (u32, u32),
+    focused: bool,
+}
+
+#[derive(Debug)]
+enum ReplayMode {
+    Disabled,
+    Record(PathBuf),
+    Playback(PathBuf),
+}
+
+#[derive(Default)]
+struct EventHandlers {
+    key_down: Vec<Box<dyn FnMut(VirtualKeyCode)>>,
+    key_up: Vec<Box<dyn FnMut(VirtualKeyCode)>>,
+    mouse_move: Vec<Box<dyn FnMut(Vector2)>>,
+    mouse_button: Vec<Box<dyn FnMut(MouseButton, bool)>>,
 }
 
 pub struct Application {
@@ -28,6 +68,12 @@ pub struct Application {
     event_loop: EventLoop<()>,
     renderer: Box<dyn renderer::Renderer>,
     scene: Scene,
+    entities: ecs::World,
+    world: Option<PhysicsWorld>,
+    recorder: Option<(ReplayRecorder, PathBuf)>,
+    player: Option<ReplayPlayer>,
+    handlers: EventHandlers,
+    broadphase_debug: bool,
 }
 
 impl ApplicationBuilder {
@@ -35,8 +81,16 @@ impl ApplicationBuilder {
         Self {
             title: DEFAULT_APPLICATION_TITLE,
             extent: (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT),
+            resizable: true,
             backend: DEFAULT_RENDERER_BACKEND,
+            renderer_config: renderer::Config::default(),
             scene_builder: None,
+            entities: ecs::World::new(),
+            world: None,
+            replay: ReplayMode::Disabled,
+            handlers: EventHandlers::default(),
+            broadphase_debug: false,
+            clear_color: None,
         }
     }
 
@@ -51,10 +105,90 @@ impl ApplicationBuilder {
         }
     }
 
+    pub fn with_resizable(self, resizable: bool) -> Self {
+        Self { resizable, ..self }
+    }
+
     pub fn with_backend(self, backend: renderer::Backend) -> Self {
         Self { backend, ..self }
     }
 
+    pub fn with_validation(self, validation: bool) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                validation,
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_linear_colors(self, linear_colors: bool) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                linear_colors,
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_shadow_depth_bias(self, constant: f32, slope: f32) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                shadow_depth_bias: Some(renderer::DepthBias { constant, slope }),
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_stencil_buffer(self, stencil: bool) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                stencil,
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_msaa(self, msaa_samples: u32) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                msaa_samples,
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    /// Replaces the built-in vertex and fragment shaders, each given as a
+    /// SPIR-V file path or as SPIR-V code.
+    pub fn with_shaders(
+        self,
+        vertex: impl Into<renderer::ShaderSource>,
+        fragment: impl Into<renderer::ShaderSource>,
+    ) -> Self {
+        Self {
+            renderer_config: renderer::Config {
+                shaders: Some(renderer::Shaders {
+                    vertex: vertex.into(),
+                    fragment: fragment.into(),
+                }),
+                ..self.renderer_config
+            },
+            ..self
+        }
+    }
+
+    pub fn with_clear_color(self, color: Vector4, space: renderer::ColorSpace) -> Self {
+        Self {
+            clear_color: Some((color, space)),
+            ..self
+        }
+    }
+
     pub fn with_scene(self, scene: SceneBuilder) -> Self {
         Self {
             scene_builder: Some(scene),
@@ -62,15 +196,82 @@ impl ApplicationBuilder {
         }
     }
 
+    pub fn with_entities(self, entities: ecs::World) -> Self {
+        Self { entities, ..self }
+    }
+
+    pub fn with_world(self, world: PhysicsWorld) -> Self {
+        Self {
+            world: Some(world),
+            ..self
+        }
+    }
+
+    /// Draws a line between the AABB centers of every broadphase pair, see
+    /// `renderer::broadphase_lines` for the colors.
+    pub fn with_broadphase_debug(self, broadphase_debug: bool) -> Self {
+        Self {
+            broadphase_debug,
+            ..self
+        }
+    }
+
+    pub fn with_replay_record(self, output_path: impl Into<PathBuf>) -> Self {
+        Self {
+            replay: ReplayMode::Record(output_path.into()),
+            ..self
+        }
+    }
+
+    pub fn with_replay_playback(self, input_path: impl Into<PathBuf>) -> Self {
+        Self {
+            replay: ReplayMode::Playback(input_path.into()),
+            ..self
+        }
+    }
+
+    pub fn on_key_down(mut self, callback: impl FnMut(VirtualKeyCode) + 'static) -> Self {
+        self.handlers.key_down.push(Box::new(callback));
+        self
+    }
+
+    pub fn on_key_up(mut self, callback: impl FnMut(VirtualKeyCode) + 'static) -> Self {
+        self.handlers.key_up.push(Box::new(callback));
+        self
+    }
+
+    pub fn on_mouse_move(mut self, callback: impl FnMut(Vector2) + 'static) -> Self {
+        self.handlers.mouse_move.push(Box::new(callback));
+        self
+    }
+
+    pub fn on_mouse_button(mut self, callback: impl FnMut(MouseButton, bool) + 'static) -> Self {
+        self.handlers.mouse_button.push(Box::new(callback));
+        self
+    }
+
     pub fn build(self) -> StaticResult<Application> {
         let event_loop = EventLoop::new();
         let window = WindowBuilder::new()
             .with_title(self.title)
             .with_inner_size(PhysicalSize::new(self.extent.0, self.extent.1))
-            .with_resizable(false)
+            .with_resizable(self.resizable)
             .build(&event_loop)?;
         let scene_builder = self.scene_builder.ok_or(format!("Scene not provided"))?;
-        let renderer = renderer::create(self.backend, &window, &scene_builder.meshes)?;
+        let (recorder, player) = match self.replay {
+            ReplayMode::Disabled => (None, None),
+            ReplayMode::Record(path) => (Some((ReplayRecorder::new(), path)), None),
+            ReplayMode::Playback(path) => (None, Some(ReplayPlayer::load(&path)?)),
+        };
+        let mut renderer = renderer::create(
+            self.backend,
+            &window,
+            &scene_builder.meshes,
+            &self.renderer_config,
+        )?;
+        if let Some((color, space)) = self.clear_color {
+            renderer.set_clear_color(color, space);
+        }
         let scene = scene_builder.build(
             60.0,
             (self.extent.0 as f32) / (self.extent.1 as f32),
@@ -82,20 +283,120 @@ impl ApplicationBuilder {
             event_loop,
             renderer,
             scene,
+            entities: self.entities,
+            world: self.world,
+            recorder,
+            player,
+            handlers: self.handlers,
+            broadphase_debug: self.broadphase_debug,
         })
     }
 }
 
+impl Default for ApplicationBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WindowState {
+    fn new(extent: (u32, u32)) -> Self {
+        Self {
+            extent,
+            focused: true,
+        }
+    }
+
+    fn should_render(&self) -> bool {
+        self.focused && self.extent.0 > 0 && self.extent.1 > 0
+    }
+
+    /// Returns whether rendering resumes, a minimized window stays paused
+    /// after regaining focus.
+    fn set_focused(&mut self, focused: bool) -> bool {
+        let paused = !self.should_render();
+        self.focused = focused;
+        paused && self.should_render()
+    }
+
+    /// Follows the new window extent with the camera aspect, a minimized
+    /// window keeps the previous one. Returns whether rendering resumes.
+    fn resize(&mut self, extent: (u32, u32), camera: &mut renderer::Camera) -> bool {
+        let paused = !self.should_render();
+        self.extent = extent;
+        if extent.0 > 0 && extent.1 > 0 {
+            camera.set_aspect(extent.0 as f32 / extent.1 as f32);
+        }
+        paused && self.should_render()
+    }
+}
+
+impl EventHandlers {
+    fn dispatch(&mut self, event: &RecordedEvent) {
+        match *event {
+            RecordedEvent::KeyDown(key) => {
+                for callback in &mut self.key_down {
+                    callback(key);
+                }
+            }
+            RecordedEvent::KeyUp(key) => {
+                for callback in &mut self.key_up {
+                    callback(key);
+                }
+            }
+            RecordedEvent::MouseMove(position) => {
+                for callback in &mut self.mouse_move {
+                    callback(position);
+                }
+            }
+            RecordedEvent::MouseButton(button, pressed) => {
+                for callback in &mut self.mouse_button {
+                    callback(button, pressed);
+                }
+            }
+            RecordedEvent::Step(_) => {}
+        }
+    }
+}
+
+impl fmt::Debug for EventHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventHandlers")
+            .field("key_down", &self.key_down.len())
+            .field("key_up", &self.key_up.len())
+            .field("mouse_move", &self.mouse_move.len())
+            .field("mouse_button", &self.mouse_button.len())
+            .finish()
+    }
+}
+
 impl Application {
     pub fn run(self) {
         let Application {
             window,
             event_loop,
             mut renderer,
-            scene,
+            mut scene,
+            entities,
+            mut world,
+            mut recorder,
+            mut player,
+            mut handlers,
+            broadphase_debug,
         } = self;
+        let mut frame_time = 0.0;
+        let mut frame_index = 0u64;
+        let size = window.inner_size();
+        let mut window_state = WindowState::new((size.width, size.height));
+        // The first frame after a pause measures the whole pause, don't step
+        // the world over it
+        let mut resumed = false;
         event_loop.run(move |event, _, control_flow| {
-            *control_flow = ControlFlow::Poll;
+            *control_flow = if window_state.should_render() {
+                ControlFlow::Poll
+            } else {
+                ControlFlow::Wait
+            };
             match event {
                 Event::WindowEvent {
                     event: WindowEvent::CloseRequested,
@@ -103,19 +404,186 @@ impl Application {
                 } => {
                     *control_flow = ControlFlow::Exit;
                 }
-                Event::MainEventsCleared => {
+                Event::WindowEvent {
+                    event: WindowEvent::Resized(size),
+                    ..
+                } => {
+                    log::verbose(&format!("Window resized to {}x{}", size.width, size.height));
+                    resumed |= window_state.resize((size.width, size.height), &mut scene.camera);
+                    if let Err(err) = renderer.resize(size.width, size.height) {
+                        log::error(&format!("Failed to resize renderer: {}", err));
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+                Event::WindowEvent {
+                    event: WindowEvent::Focused(focused),
+                    ..
+                } => {
+                    log::verbose(if focused {
+                        "Window focused, resuming rendering"
+                    } else {
+                        "Window lost focus, pausing rendering"
+                    });
+                    resumed |= window_state.set_focused(focused);
+                }
+                Event::WindowEvent { event, .. } => {
+                    if player.is_some() {
+                        return;
+                    }
+                    if let Some(event) = RecordedEvent::from_window_event(&event) {
+                        if let Some((recorder, _)) = &mut recorder {
+                            recorder.record(frame_index, event);
+                        }
+                        handlers.dispatch(&event);
+                    }
+                }
+                Event::MainEventsCleared if window_state.should_render() => {
                     window.request_redraw();
                 }
                 Event::RedrawRequested(_) => {
-                    renderer.begin_frame(&scene.camera).unwrap();
-                    for object in &scene.objects {
-                        renderer.draw(object.mesh, &object.world);
+                    if !window_state.should_render() {
+                        return;
+                    }
+                    let frame = renderer.begin_frame(&scene.camera).unwrap();
+                    frame_time += frame.dt_seconds;
+                    if frame.frame_index > 0 && frame.frame_index % FRAME_STATS_INTERVAL == 0 {
+                        log::verbose(&format!(
+                            "Average frame time: {:.2} ms",
+                            1000.0 * frame_time / FRAME_STATS_INTERVAL as f32
+                        ));
+                        frame_time = 0.0;
+                    }
+                    match &mut player {
+                        Some(player) => {
+                            for (_, event) in player.next_events(frame_index) {
+                                match *event {
+                                    RecordedEvent::Step(dt) => {
+                                        if let Some(world) = &mut world {
+                                            world.step(dt);
+                                        }
+                                    }
+                                    event => handlers.dispatch(&event),
+                                }
+                            }
+                        }
+                        None => {
+                            let step = frame.dt_seconds > 0.0 && !resumed;
+                            if let (Some(world), true) = (&mut world, step) {
+                                world.step(frame.dt_seconds);
+                                if let Some((recorder, _)) = &mut recorder {
+                                    recorder
+                                        .record(frame_index, RecordedEvent::Step(frame.dt_seconds));
+                                }
+                            }
+                        }
+                    }
+                    if player.as_ref().is_some_and(ReplayPlayer::is_finished) {
+                        log::info("Replay playback finished, resuming live input");
+                        player = None;
+                    }
+                    if let Some(world) = &world {
+                        scene.sync_with_world(world);
+                        if broadphase_debug {
+                            for line in renderer::broadphase_lines(world.broadphase_pairs()) {
+                                renderer.draw_line(&line);
+                            }
+                        }
+                    }
+                    for object in scene.objects() {
+                        renderer.draw(object.mesh_handle(), object.world_transform());
+                    }
+                    for (entity, &mesh) in entities.query::<renderer::MeshHandle>() {
+                        let world = entities
+                            .get_component::<Matrix4>(entity)
+                            .unwrap_or(&Matrix4::IDENTITY);
+                        renderer.draw(mesh, world);
                     }
                     renderer.end_frame().unwrap();
+                    frame_index += 1;
+                    resumed = false;
+                    #[cfg(feature = "profile")]
+                    profiler::Profiler::next_frame();
+                }
+                Event::LoopDestroyed => {
+                    if let Some((recorder, path)) = &recorder {
+                        match recorder.save(path) {
+                            Ok(()) => log::info(&format!(
+                                "Saved {} replay events to {}",
+                                recorder.events.len(),
+                                path.display()
+                            )),
+                            Err(err) => log::error(&format!("Failed to save replay: {}", err)),
+                        }
+                    }
                 }
-                Event::LoopDestroyed => {}
                 _ => {}
             }
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::types::Vector3, renderer::CameraBuilder};
+
+    #[test]
+    fn resize_updates_camera_aspect() {
+        let mut camera = CameraBuilder::new(Vector3::new(0.0, -5.0, 0.0), Vector3::ZERO)
+            .build(60.0, 1.0, 0.1, 100.0);
+        let mut state = WindowState::new((800, 800));
+        assert!(!state.resize((1920, 1080), &mut camera));
+        assert_eq!(camera.aspect(), 1920.0 / 1080.0);
+
+        // Minimizing pauses rendering and keeps the last aspect
+        assert!(!state.resize((0, 0), &mut camera));
+        assert!(!state.should_render());
+        assert_eq!(camera.aspect(), 1920.0 / 1080.0);
+
+        assert!(state.resize((1024, 512), &mut camera));
+        assert_eq!(camera.aspect(), 2.0);
+        assert!(state.should_render());
+    }
+
+    #[test]
+    fn focus_and_minimize_pause_rendering() {
+        let mut camera = CameraBuilder::new(Vector3::new(0.0, -5.0, 0.0), Vector3::ZERO)
+            .build(60.0, 1.0, 0.1, 100.0);
+        let mut state = WindowState::new((800, 600));
+        assert!(!state.set_focused(false));
+        assert!(!state.should_render());
+        assert!(state.set_focused(true));
+        assert!(state.should_render());
+
+        // Both conditions have to clear before rendering resumes
+        assert!(!state.set_focused(false));
+        assert!(!state.resize((0, 0), &mut camera));
+        assert!(!state.set_focused(true));
+        assert!(!state.should_render());
+        assert!(!state.set_focused(false));
+        assert!(!state.resize((800, 600), &mut camera));
+        assert!(state.set_focused(true));
+        assert!(!state.set_focused(true));
+    }
+
+    #[test]
+    fn builder_debug_summarizes_configuration() {
+        let builder = ApplicationBuilder::new()
+            .with_title("Debug")
+            .with_window_size(640, 480)
+            .with_world(PhysicsWorld::new())
+            .on_key_down(|_| {})
+            .on_key_down(|_| {});
+        let debug = format!("{:?}", builder);
+        for expected in [
+            "ApplicationBuilder {",
+            "title: \"Debug\"",
+            "extent: (640, 480)",
+            "backend: Vulkan",
+            "world: Some(",
+            "EventHandlers { key_down: 2, key_up: 0, mouse_move: 0, mouse_button: 0 }",
+        ] {
+            assert!(debug.contains(expected), "{}", debug);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    math::types::Matrix4, physics::RigidBody, renderer::MeshHandle, utils::static_assert_send_sync,
+};
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt,
+};
+
+pub trait Component: Any + Send + Sync {}
+
+impl Component for RigidBody {}
+impl Component for MeshHandle {}
+impl Component for Matrix4 {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: usize,
+    generation: u32,
+}
+
+trait Storage: Send + Sync {
+    fn remove(&mut self, index: usize);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+struct ComponentStorage<C> {
+    components: Vec<Option<C>>,
+}
+
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<usize>,
+    storages: HashMap<TypeId, Box<dyn Storage>>,
+}
+
+static_assert_send_sync!(World, Entity);
+
+impl Entity {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<C: Component> Storage for ComponentStorage<C> {
+    fn remove(&mut self, index: usize) {
+        if let Some(component) = self.components.get_mut(index) {
+            *component = None;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            generations: vec![],
+            alive: vec![],
+            free: vec![],
+            storages: HashMap::new(),
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index] = true;
+                Entity {
+                    index,
+                    generation: self.generations[index],
+                }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity {
+                    index: self.alive.len() - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove(entity.index);
+        }
+        self.alive[entity.index] = false;
+        self.generations[entity.index] = self.generations[entity.index].wrapping_add(1);
+        self.free.push(entity.index);
+        true
+    }
+
+    #[inline]
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity.index).copied().unwrap_or(false)
+            && self.generations[entity.index] == entity.generation
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.alive.len() - self.free.len()
+    }
+
+    pub fn add_component<C: Component>(&mut self, entity: Entity, component: C) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        let storage = self
+            .storages
+            .entry(TypeId::of::<C>())
+            .or_insert_with(|| Box::new(ComponentStorage::<C> { components: vec![] }))
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<C>>()
+            .unwrap();
+        if storage.components.len() <= entity.index {
+            storage.components.resize_with(entity.index + 1, || None);
+        }
+        storage.components[entity.index] = Some(component);
+        true
+    }
+
+    pub fn remove_component<C: Component>(&mut self, entity: Entity) -> Option<C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<C>()?
+            .components
+            .get_mut(entity.index)?
+            .take()
+    }
+
+    pub fn get_component<C: Component>(&self, entity: Entity) -> Option<&C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage::<C>()?.components.get(entity.index)?.as_ref()
+    }
+
+    pub fn get_component_mut<C: Component>(&mut self, entity: Entity) -> Option<&mut C> {
+        if !self.is_alive(entity) {
+            return None;
+        }
+        self.storage_mut::<C>()?
+            .components
+            .get_mut(entity.index)?
+            .as_mut()
+    }
+
+    pub fn query<C: Component>(&self) -> impl Iterator<Item = (Entity, &C)> {
+        let generations = &self.generations;
+        self.storage::<C>()
+            .map(|storage| storage.components.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, component)| {
+                let entity = Entity {
+                    index,
+                    generation: generations[index],
+                };
+                component.as_ref().map(|component| (entity, component))
+            })
+    }
+
+    fn storage<C: Component>(&self) -> Option<&ComponentStorage<C>> {
+        self.storages
+            .get(&TypeId::of::<C>())?
+            .as_any()
+            .downcast_ref::<ComponentStorage<C>>()
+    }
+
+    fn storage_mut<C: Component>(&mut self) -> Option<&mut ComponentStorage<C>> {
+        self.storages
+            .get_mut(&TypeId::of::<C>())?
+            .as_any_mut()
+            .downcast_mut::<ComponentStorage<C>>()
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("World")
+            .field("entities", &self.entity_count())
+            .field("component_types", &self.storages.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+
+    impl Component for Health {}
+
+    #[test]
+    fn components_are_stored_per_entity_and_type() {
+        let mut world = World::new();
+        let a = world.spawn();
+        let b = world.spawn();
+        assert!(world.add_component(a, Health(10)));
+        assert!(world.add_component(b, Health(20)));
+        assert!(world.add_component(b, Matrix4::IDENTITY));
+        assert_eq!(world.get_component::<Health>(a), Some(&Health(10)));
+        assert!(world.get_component::<Matrix4>(a).is_none());
+
+        world.get_component_mut::<Health>(b).unwrap().0 += 5;
+        let healths = world.query::<Health>().collect::<Vec<_>>();
+        assert_eq!(healths, vec![(a, &Health(10)), (b, &Health(25))]);
+        assert_eq!(world.query::<Matrix4>().count(), 1);
+
+        assert_eq!(world.remove_component::<Health>(a), Some(Health(10)));
+        assert!(world.remove_component::<Health>(a).is_none());
+        assert_eq!(world.query::<Health>().count(), 1);
+    }
+
+    #[test]
+    fn despawned_handles_go_stale() {
+        let mut world = World::new();
+        let old = world.spawn();
+        world.add_component(old, Health(1));
+        assert!(world.despawn(old));
+        assert!(!world.despawn(old));
+        assert_eq!(world.entity_count(), 0);
+
+        // The slot is reused, neither the old handle nor its components carry over
+        let new = world.spawn();
+        assert_eq!(new.index(), old.index());
+        assert_ne!(new, old);
+        assert!(!world.is_alive(old));
+        assert!(world.get_component::<Health>(new).is_none());
+        assert!(!world.add_component(old, Health(2)));
+        assert!(world.get_component::<Health>(new).is_none());
+        assert_eq!(world.query::<Health>().count(), 0);
+    }
+}
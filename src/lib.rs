@@ -1,7 +1,14 @@
 mod utils;
 
 pub mod app;
+pub mod ecs;
+pub mod log;
 pub mod math;
 pub mod physics;
+pub mod prelude;
 pub mod renderer;
+pub mod replay;
 pub mod scene;
+
+#[cfg(feature = "profile")]
+pub use utils::profiler;
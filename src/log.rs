@@ -0,0 +1,108 @@
+use std::sync::{Arc, RwLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warning,
+    Info,
+    Verbose,
+}
+
+type Sink = Arc<dyn Fn(Level, &str) + Send + Sync>;
+
+struct Logger {
+    level: Level,
+    sink: Option<Sink>,
+}
+
+static LOGGER: RwLock<Logger> = RwLock::new(Logger {
+    level: Level::Verbose,
+    sink: None,
+});
+
+pub fn set_level(level: Level) {
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.level = level;
+    }
+}
+
+pub fn set_sink<F>(sink: F)
+where
+    F: Fn(Level, &str) + Send + Sync + 'static,
+{
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.sink = Some(Arc::new(sink));
+    }
+}
+
+pub fn reset_sink() {
+    if let Ok(mut logger) = LOGGER.write() {
+        logger.sink = None;
+    }
+}
+
+pub fn log(level: Level, message: &str) {
+    // The sink runs without the lock held, it may log or replace itself
+    let sink = match LOGGER.read() {
+        Ok(logger) if level <= logger.level => logger.sink.clone(),
+        _ => return,
+    };
+    match sink {
+        Some(sink) => sink(level, message),
+        None => println!("{}", message),
+    }
+}
+
+#[inline]
+pub fn error(message: &str) {
+    log(Level::Error, message)
+}
+
+#[inline]
+pub fn warning(message: &str) {
+    log(Level::Warning, message)
+}
+
+#[inline]
+pub fn info(message: &str) {
+    log(Level::Info, message)
+}
+
+#[inline]
+pub fn verbose(message: &str) {
+    log(Level::Verbose, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn sink_may_use_the_logger() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink_received = received.clone();
+        set_sink(move |level, message| {
+            sink_received
+                .lock()
+                .unwrap()
+                .push((level, message.to_owned()));
+            if level == Level::Error {
+                set_level(Level::Verbose);
+                info("nested");
+            }
+        });
+        error("outer");
+        reset_sink();
+        // Other tests may log through the sink while it is installed
+        let mut received = received.lock().unwrap().clone();
+        received.retain(|(_, message)| message == "outer" || message == "nested");
+        assert_eq!(
+            received,
+            vec![
+                (Level::Error, "outer".to_owned()),
+                (Level::Info, "nested".to_owned())
+            ]
+        );
+    }
+}
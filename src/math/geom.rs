@@ -0,0 +1,413 @@
+use crate::math::types::{Vector2, Vector3};
+use std::collections::HashSet;
+
+pub fn closest_point_segment(point: Vector3, a: Vector3, b: Vector3) -> Vector3 {
+    let ab = b - a;
+    let len_squared = ab.mag_squared();
+    if len_squared <= f32::EPSILON {
+        return a;
+    }
+    a + ab * f32::clamp((point - a) * ab / len_squared, 0.0, 1.0)
+}
+
+pub fn closest_segment_segment(
+    p1: Vector3,
+    q1: Vector3,
+    p2: Vector3,
+    q2: Vector3,
+) -> (Vector3, Vector3, f32) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.mag_squared();
+    let e = d2.mag_squared();
+    let f = d2 * r;
+    if a <= f32::EPSILON && e <= f32::EPSILON {
+        return (p1, p2, (p2 - p1).mag());
+    }
+    let (s, t) = if a <= f32::EPSILON {
+        (0.0, f32::clamp(f / e, 0.0, 1.0))
+    } else {
+        let c = d1 * r;
+        if e <= f32::EPSILON {
+            (f32::clamp(-c / a, 0.0, 1.0), 0.0)
+        } else {
+            let b = d1 * d2;
+            let denom = a * e - b * b;
+            let s = if denom > f32::EPSILON * a * e {
+                f32::clamp((b * f - c * e) / denom, 0.0, 1.0)
+            } else {
+                let s0 = f32::clamp((p2 - p1) * d1 / a, 0.0, 1.0);
+                let s1 = f32::clamp((q2 - p1) * d1 / a, 0.0, 1.0);
+                (s0 + s1) / 2.0
+            };
+            let t = (b * s + f) / e;
+            if t < 0.0 {
+                (f32::clamp(-c / a, 0.0, 1.0), 0.0)
+            } else if t > 1.0 {
+                (f32::clamp((b - c) / a, 0.0, 1.0), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+    let (c1, c2) = (p1 + d1 * s, p2 + d2 * t);
+    (c1, c2, (c2 - c1).mag())
+}
+
+/// Voronoi region of `site` clipped to the diagram bounds, a convex polygon
+/// with counter-clockwise `vertices`. Empty for sites outside of the bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoronoiCell {
+    pub site: Vector2,
+    pub vertices: Vec<Vector2>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Arc {
+    site: usize,
+    circle_event: Option<f32>,
+}
+
+/// Voronoi diagram of `points` within the `[min, max]` rectangle, one cell per
+/// point in input order.
+///
+/// Fortune's sweep runs over the sites with the line moving towards +y and
+/// records every pair of sites whose arcs become adjacent on the beach line,
+/// those are exactly the pairs sharing a Voronoi edge. Each cell is then the
+/// bounds clipped by the bisectors of its neighbours. The beach line is kept
+/// in a `Vec`, which makes the sweep O(n^2) in the worst case; fine for the
+/// tens of sites of a fracture pattern.
+pub fn voronoi_2d(points: &[Vector2], bounds: [Vector2; 2]) -> Vec<VoronoiCell> {
+    let neighbours = voronoi_neighbours(points);
+    let [min, max] = bounds;
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, &site)| {
+            let mut vertices = vec![
+                min,
+                Vector2::new(max.x, min.y),
+                max,
+                Vector2::new(min.x, max.y),
+            ];
+            for &neighbour in &neighbours[index] {
+                let normal = points[neighbour] - site;
+                if normal.mag_squared() <= f32::EPSILON * f32::EPSILON {
+                    continue;
+                }
+                let offset = normal * ((points[neighbour] + site) / 2.0);
+                vertices = clip_polygon(&vertices, normal, offset);
+            }
+            VoronoiCell { site, vertices }
+        })
+        .collect()
+}
+
+fn voronoi_neighbours(points: &[Vector2]) -> Vec<HashSet<usize>> {
+    let mut neighbours = vec![HashSet::new(); points.len()];
+    let mut connect = |a: usize, b: usize| {
+        neighbours[a].insert(b);
+        neighbours[b].insert(a);
+    };
+    let mut sites = (0..points.len()).collect::<Vec<_>>();
+    sites.sort_by(|&a, &b| {
+        let (a, b) = (points[a], points[b]);
+        a.y.partial_cmp(&b.y)
+            .unwrap()
+            .then(a.x.partial_cmp(&b.x).unwrap())
+    });
+    let mut sites = sites.into_iter().peekable();
+    let mut beach_line: Vec<Arc> = Vec::with_capacity(2 * points.len());
+    loop {
+        let circle_event = beach_line
+            .iter()
+            .enumerate()
+            .filter_map(|(index, arc)| arc.circle_event.map(|y| (index, y)))
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        match (sites.peek(), circle_event) {
+            (None, None) => break,
+            (Some(&site), event) if event.is_none_or(|(_, y)| points[site].y < y) => {
+                sites.next();
+                let sweep = points[site].y;
+                let Vector2 { x, .. } = points[site];
+                let index = (0..beach_line.len())
+                    .find(|&index| {
+                        index + 1 == beach_line.len()
+                            || x < breakpoint(
+                                points[beach_line[index].site],
+                                points[beach_line[index + 1].site],
+                                sweep,
+                            )
+                    })
+                    .unwrap_or(0);
+                let arc = Arc {
+                    site,
+                    circle_event: None,
+                };
+                match beach_line.get(index).copied() {
+                    None => beach_line.push(arc),
+                    // Sites on the first row have degenerate vertical arcs
+                    Some(other) if points[other.site].y >= sweep => {
+                        connect(other.site, site);
+                        beach_line.insert(index + 1, arc);
+                        update_circle_events(&mut beach_line, points, index, sweep);
+                    }
+                    Some(other) => {
+                        connect(other.site, site);
+                        beach_line.splice(index + 1..index + 1, [arc, other].iter().copied());
+                        update_circle_events(&mut beach_line, points, index, sweep);
+                        update_circle_events(&mut beach_line, points, index + 2, sweep);
+                    }
+                }
+            }
+            (_, Some((index, sweep))) => {
+                beach_line.remove(index);
+                connect(beach_line[index - 1].site, beach_line[index].site);
+                update_circle_events(&mut beach_line, points, index - 1, sweep);
+                update_circle_events(&mut beach_line, points, index, sweep);
+            }
+            (Some(_), None) => unreachable!(),
+        }
+    }
+    neighbours
+}
+
+/// Recomputes the circle event of the arc at `index`, the sweep position at
+/// which the breakpoints on both of its sides meet and squeeze it out.
+fn update_circle_events(beach_line: &mut [Arc], points: &[Vector2], index: usize, sweep: f32) {
+    if index == 0 || index + 1 >= beach_line.len() {
+        if let Some(arc) = beach_line.get_mut(index) {
+            arc.circle_event = None;
+        }
+        return;
+    }
+    let (a, b, c) = (
+        beach_line[index - 1].site,
+        beach_line[index].site,
+        beach_line[index + 1].site,
+    );
+    let (pa, pb, pc) = (points[a], points[b], points[c]);
+    let (ab, bc) = (pb - pa, pc - pb);
+    let turn = ab.x * bc.y - ab.y * bc.x;
+    beach_line[index].circle_event = if a != c && turn > 0.0 {
+        // Circumcircle of the three sites, the event fires at its far edge
+        let (ac, d) = (pc - pa, 2.0 * turn);
+        let (ab2, ac2) = (ab.mag_squared(), ac.mag_squared());
+        let center = Vector2::new(
+            pa.x + (ac.y * ab2 - ab.y * ac2) / d,
+            pa.y + (ab.x * ac2 - ac.x * ab2) / d,
+        );
+        Some(f32::max(center.y + (pa - center).mag(), sweep))
+    } else {
+        None
+    };
+}
+
+/// Breakpoint between the arc of `left` and the arc of `right` to its right
+/// for the sweep line at `sweep`, where the two parabolas cross with the left
+/// one descending below the right one.
+fn breakpoint(left: Vector2, right: Vector2, sweep: f32) -> f32 {
+    let (dl, dr) = (2.0 * (left.y - sweep), 2.0 * (right.y - sweep));
+    match (dl == 0.0, dr == 0.0) {
+        (true, true) => return (left.x + right.x) / 2.0,
+        (true, false) => return left.x,
+        (false, true) => return right.x,
+        (false, false) => (),
+    }
+    let a = 1.0 / dl - 1.0 / dr;
+    let b = -2.0 * (left.x / dl - right.x / dr);
+    let c = (left.mag_squared() - sweep * sweep) / dl - (right.mag_squared() - sweep * sweep) / dr;
+    if a.abs() <= f32::EPSILON * (1.0 / dl.abs() + 1.0 / dr.abs()) {
+        return -c / b;
+    }
+    (-b - f32::sqrt(f32::max(b * b - 4.0 * a * c, 0.0))) / (2.0 * a)
+}
+
+/// Sutherland-Hodgman clip of a convex polygon to the half plane
+/// `point * normal <= offset`.
+fn clip_polygon(vertices: &[Vector2], normal: Vector2, offset: f32) -> Vec<Vector2> {
+    let mut clipped = Vec::with_capacity(vertices.len() + 1);
+    for (index, &end) in vertices.iter().enumerate() {
+        let start = vertices[(index + vertices.len() - 1) % vertices.len()];
+        let (ds, de) = (start * normal - offset, end * normal - offset);
+        if (ds > 0.0 && de < 0.0) || (ds < 0.0 && de > 0.0) {
+            clipped.push(start + (end - start) * (ds / (ds - de)));
+        }
+        if de <= 0.0 {
+            clipped.push(end);
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (Vector3, Vector3, f32), expected: (Vector3, Vector3, f32)) {
+        let (c1, c2, distance) = actual;
+        assert!((c1 - expected.0).mag() < 1e-5, "{:?}", actual);
+        assert!((c2 - expected.1).mag() < 1e-5, "{:?}", actual);
+        assert!(f32::abs(distance - expected.2) < 1e-5, "{:?}", actual);
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let closest = closest_segment_segment(
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.5, -1.0, 2.0),
+            Vector3::new(0.5, 1.0, 2.0),
+        );
+        assert_close(
+            closest,
+            (
+                Vector3::new(0.5, 0.0, 0.0),
+                Vector3::new(0.5, 0.0, 2.0),
+                2.0,
+            ),
+        );
+    }
+
+    #[test]
+    fn segments_closest_at_endpoints() {
+        let closest = closest_segment_segment(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(3.0, 1.0, 0.0),
+            Vector3::new(3.0, 5.0, 0.0),
+        );
+        assert_close(
+            closest,
+            (
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(3.0, 1.0, 0.0),
+                f32::sqrt(5.0),
+            ),
+        );
+    }
+
+    #[test]
+    fn parallel_segments() {
+        // Overlapping along x, the closest points share the middle of the overlap
+        let closest = closest_segment_segment(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(3.0, 1.0, 0.0),
+        );
+        assert_close(
+            closest,
+            (
+                Vector3::new(1.5, 0.0, 0.0),
+                Vector3::new(1.5, 1.0, 0.0),
+                1.0,
+            ),
+        );
+        // Disjoint along x, joined by the nearest endpoints
+        let closest = closest_segment_segment(
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(4.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+        );
+        assert_close(
+            closest,
+            (
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+                1.0,
+            ),
+        );
+    }
+
+    #[test]
+    fn point_segments() {
+        let point = Vector3::new(1.0, 2.0, 0.0);
+        let closest = closest_segment_segment(
+            point,
+            point,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(4.0, 0.0, 0.0),
+        );
+        assert_close(closest, (point, Vector3::new(1.0, 0.0, 0.0), 2.0));
+        let closest = closest_segment_segment(
+            Vector3::new(-2.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            point,
+            point,
+        );
+        assert_close(closest, (Vector3::new(1.0, 0.0, 0.0), point, 2.0));
+        let other = Vector3::new(4.0, 6.0, 0.0);
+        let closest = closest_segment_segment(point, point, other, other);
+        assert_close(closest, (point, other, 5.0));
+    }
+
+    fn polygon_area(vertices: &[Vector2]) -> f32 {
+        let mut area = 0.0;
+        for (index, &a) in vertices.iter().enumerate() {
+            let b = vertices[(index + 1) % vertices.len()];
+            area += a.x * b.y - a.y * b.x;
+        }
+        area / 2.0
+    }
+
+    fn assert_valid_diagram(points: &[Vector2], bounds: [Vector2; 2]) {
+        let cells = voronoi_2d(points, bounds);
+        assert_eq!(cells.len(), points.len());
+        let extent = bounds[1] - bounds[0];
+        let total = cells
+            .iter()
+            .map(|cell| polygon_area(&cell.vertices))
+            .sum::<f32>();
+        // Counter-clockwise cells tile the bounds without gaps or overlaps
+        assert!(f32::abs(total - extent.x * extent.y) < 1e-3 * extent.x * extent.y);
+        for cell in &cells {
+            assert!(polygon_area(&cell.vertices) > 0.0);
+            for &vertex in &cell.vertices {
+                let own = (vertex - cell.site).mag();
+                for &other in points {
+                    assert!(own <= (vertex - other).mag() + 1e-3, "{:?}", cell);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_sites_get_square_cells() {
+        let points = (0..3)
+            .flat_map(|y| (0..3).map(move |x| Vector2::new(x as f32 + 0.5, y as f32 + 0.5)))
+            .collect::<Vec<_>>();
+        let bounds = [Vector2::ZERO, Vector2::new(3.0, 3.0)];
+        for cell in voronoi_2d(&points, bounds) {
+            assert!(f32::abs(polygon_area(&cell.vertices) - 1.0) < 1e-4);
+        }
+        assert_valid_diagram(&points, bounds);
+    }
+
+    #[test]
+    fn random_sites_partition_the_bounds() {
+        let mut seed = 0x1234_5678u32;
+        let mut random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed >> 8) as f32 / (1u32 << 24) as f32
+        };
+        for count in [2, 5, 20, 50] {
+            let points = (0..count)
+                .map(|_| Vector2::new(10.0 * random(), 5.0 * random()))
+                .collect::<Vec<_>>();
+            assert_valid_diagram(&points, [Vector2::ZERO, Vector2::new(10.0, 5.0)]);
+        }
+    }
+
+    #[test]
+    fn site_outside_bounds_gets_empty_cell() {
+        let points = [Vector2::new(0.5, 0.5), Vector2::new(5.0, 0.5)];
+        let cells = voronoi_2d(&points, [Vector2::ZERO, Vector2::new(1.0, 1.0)]);
+        assert!(f32::abs(polygon_area(&cells[0].vertices) - 1.0) < 1e-5);
+        assert!(cells[1].vertices.is_empty());
+    }
+}
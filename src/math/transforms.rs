@@ -3,3 +3,147 @@ mod proj;
 
 pub use affine::*;
 pub use proj::*;
+
+/// Handedness of the view space produced by `look_at_handed` and expected by
+/// `perspective_handed`. With `Left`, +z points into the screen; with `Right`,
+/// the camera looks down -z. Both keep +x right and +y up on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Handedness {
+    Left,
+    Right,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::types::{Matrix4, Quaternion, Vector3, Vector4};
+
+    const NEAR: f32 = 1.0;
+    const FAR: f32 = 11.0;
+
+    // Camera 5 units down -y looking at the origin with +z up, as the
+    // default scene camera does
+    fn ndc(point: Vector3, handedness: Handedness) -> Vector3 {
+        let eye = Vector3::new(0.0, -5.0, 0.0);
+        let view = look_at_handed(eye, Vector3::ZERO, Vector3::Z, handedness);
+        let proj = perspective_handed(90.0, 2.0, NEAR, FAR, handedness);
+        let clip = proj * view * Vector4::hom_point(point);
+        assert!(clip.w > 0.0, "{:?} is behind the camera", point);
+        clip.xyz() / clip.w
+    }
+
+    #[test]
+    fn points_in_front_map_to_expected_ndc() {
+        for &handedness in &[Handedness::Left, Handedness::Right] {
+            let center = ndc(Vector3::ZERO, handedness);
+            assert!(f32::abs(center.x) < 1e-6 && f32::abs(center.y) < 1e-6);
+            // World +x is screen right, world +z (the up vector) is screen up
+            let right = ndc(Vector3::new(1.0, 0.0, 0.0), handedness);
+            assert!(f32::abs(right.x - 0.1) < 1e-6 && f32::abs(right.y) < 1e-6);
+            let up = ndc(Vector3::new(0.0, 0.0, 1.0), handedness);
+            assert!(f32::abs(up.y - 0.2) < 1e-6 && f32::abs(up.x) < 1e-6);
+            // Depth grows into the screen, from 0 at the near plane to 1 at the far one
+            let near = ndc(Vector3::new(0.0, NEAR - 5.0, 0.0), handedness);
+            let far = ndc(Vector3::new(0.0, FAR - 5.0, 0.0), handedness);
+            assert!(f32::abs(near.z) < 1e-6 && f32::abs(far.z - 1.0) < 1e-5);
+            assert!(center.z > near.z && center.z < far.z);
+        }
+    }
+
+    #[test]
+    fn handedness_only_changes_view_space() {
+        let eye = Vector3::new(2.0, -4.0, 1.0);
+        let left = look_at_handed(eye, Vector3::ZERO, Vector3::Z, Handedness::Left);
+        let right = look_at_handed(eye, Vector3::ZERO, Vector3::Z, Handedness::Right);
+        let point = Vector4::hom_point(Vector3::new(0.5, 1.0, -0.5));
+        let (left, right) = (left * point, right * point);
+        assert_eq!((left.x, left.y), (right.x, right.y));
+        assert!(left.z > 0.0 && f32::abs(left.z + right.z) < 1e-6);
+    }
+
+    #[test]
+    fn billboard_faces_the_camera() {
+        let camera = Vector3::new(3.0, -4.0, 2.0);
+        let object = Vector3::new(1.0, 1.0, 2.0);
+        for &up in &[Vector3::Z, Vector3::Y, (camera - object).normalized()] {
+            let rotation = billboard(camera, object, up);
+            let (x, y, z) = (
+                rotation.transform_direction(Vector3::X),
+                rotation.transform_direction(Vector3::Y),
+                rotation.transform_direction(Vector3::Z),
+            );
+            assert!((z - (camera - object).normalized()).mag() < 1e-5);
+            // Right handed orthonormal basis, so quads keep their winding
+            assert!((x.cross(y) - z).mag() < 1e-5);
+            assert!(f32::abs(x.mag() - 1.0) < 1e-5 && f32::abs(y.mag() - 1.0) < 1e-5);
+            assert_eq!(rotation.l, Vector4::new(0.0, 0.0, 0.0, 1.0));
+        }
+        // Object +y stays as close to the requested up as the facing allows
+        let rotation = billboard(camera, object, Vector3::Z);
+        assert!(f32::abs(rotation.transform_direction(Vector3::Y).z - 1.0) < 1e-5);
+    }
+
+    #[test]
+    fn quaternion_translation_matches_composed_matrix() {
+        let t = Vector3::new(1.5, -2.0, 0.25);
+        for (axis, angle) in [
+            (Vector3::X, 0.3),
+            (Vector3::new(1.0, 2.0, 3.0).normalized(), 2.0),
+            (Vector3::new(-0.5, 0.1, 1.0).normalized(), -4.0),
+        ] {
+            let q = Quaternion::vec_angle(axis, angle);
+            let rotation = Matrix4 {
+                i: Vector4::hom_vec(q.rotate_point(Vector3::X)),
+                j: Vector4::hom_vec(q.rotate_point(Vector3::Y)),
+                k: Vector4::hom_vec(q.rotate_point(Vector3::Z)),
+                l: Vector4::W,
+            };
+            let composed = translate(t) * rotation;
+            let direct = from_quaternion_translation(q, t);
+            for c in 0..4 {
+                for r in 0..4 {
+                    assert!(
+                        f32::abs(direct[c][r] - composed[c][r]) < 1e-7,
+                        "{:?}",
+                        direct
+                    );
+                }
+            }
+        }
+        // Same handedness as the axis rotations
+        let q = Quaternion::vec_angle(Vector3::X, 0.3);
+        let direct = from_quaternion_translation(q, Vector3::ZERO);
+        let expected = rot_x(0.3);
+        for c in 0..4 {
+            assert!((direct[c] - expected[c]).mag() < 1e-6);
+        }
+    }
+
+    fn assert_orthonormal_view(view: Matrix4) {
+        assert!(
+            view.to_array().iter().all(|value| value.is_finite()),
+            "{:?}",
+            view
+        );
+        let rows = [0, 1, 2].map(|r| Vector3::new(view.i[r], view.j[r], view.k[r]));
+        for a in 0..3 {
+            for b in 0..3 {
+                let expected = if a == b { 1.0 } else { 0.0 };
+                assert!(f32::abs(rows[a] * rows[b] - expected) < 1e-5, "{:?}", view);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_look_at_stays_orthonormal() {
+        let eye = Vector3::new(1.0, 2.0, 3.0);
+        assert_orthonormal_view(look_at(eye, eye, Vector3::Z));
+        // Looking straight along the up vector, in both directions
+        assert_orthonormal_view(look_at(eye, eye + Vector3::Z * 4.0, Vector3::Z));
+        assert_orthonormal_view(look_at(eye, eye - Vector3::Z * 4.0, Vector3::Z));
+        assert_orthonormal_view(look_at(eye, Vector3::ZERO, Vector3::ZERO));
+        // Forward is still towards the center when up is useless
+        let view = look_at(eye, eye + Vector3::Z, Vector3::Z);
+        assert!((view.transform_point(eye + Vector3::Z) - Vector3::Z).mag() < 1e-5);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::math::types::{Matrix4, Vector3, Vector4};
+use super::Handedness;
+use crate::math::types::{Matrix4, Quaternion, Vector3, Vector4};
+
+const DEFAULT_LOOK_DIRECTION: Vector3 = Vector3::Y;
 
 #[inline]
 pub fn translate(point: Vector3) -> Matrix4 {
@@ -10,6 +13,20 @@ pub fn translate(point: Vector3) -> Matrix4 {
     }
 }
 
+#[inline]
+pub fn from_quaternion_translation(q: Quaternion, t: Vector3) -> Matrix4 {
+    let Quaternion { r, i, j, k } = q;
+    let (ii, jj, kk) = (i * i, j * j, k * k);
+    let (ij, ik, jk) = (i * j, i * k, j * k);
+    let (ri, rj, rk) = (r * i, r * j, r * k);
+    Matrix4 {
+        i: Vector4::new(1.0 - 2.0 * (jj + kk), 2.0 * (ij + rk), 2.0 * (ik - rj), 0.0),
+        j: Vector4::new(2.0 * (ij - rk), 1.0 - 2.0 * (ii + kk), 2.0 * (jk + ri), 0.0),
+        k: Vector4::new(2.0 * (ik + rj), 2.0 * (jk - ri), 1.0 - 2.0 * (ii + jj), 0.0),
+        l: Vector4::new(t.x, t.y, t.z, 1.0),
+    }
+}
+
 #[inline]
 pub fn rot_x(rad: f32) -> Matrix4 {
     Matrix4 {
@@ -65,11 +82,37 @@ pub fn align_x_axis(axis: Vector3) -> Matrix4 {
     .transpose()
 }
 
+/// View transform with +x to the right, +y up and +z towards `center`, which
+/// makes view space left-handed for a right-handed world.
 #[inline]
 pub fn look_at(eye: Vector3, center: Vector3, up: Vector3) -> Matrix4 {
-    let front = (center - eye).normalized();
-    let right = front.cross(up).normalized();
+    look_at_handed(eye, center, up, Handedness::Left)
+}
+
+#[inline]
+pub fn look_at_handed(
+    eye: Vector3,
+    center: Vector3,
+    up: Vector3,
+    handedness: Handedness,
+) -> Matrix4 {
+    let front = center - eye;
+    let front = if front.mag_squared() > f32::EPSILON * f32::EPSILON {
+        front.normalized()
+    } else {
+        DEFAULT_LOOK_DIRECTION
+    };
+    let right = front.cross(up);
+    let right = if right.mag_squared() > f32::EPSILON * f32::EPSILON {
+        right.normalized()
+    } else {
+        front.ortho().1
+    };
     let up = right.cross(front).normalized();
+    let front = match handedness {
+        Handedness::Left => front,
+        Handedness::Right => -front,
+    };
     Matrix4 {
         i: Vector4::new(right.x, up.x, front.x, 0.0),
         j: Vector4::new(right.y, up.y, front.y, 0.0),
@@ -78,6 +121,32 @@ pub fn look_at(eye: Vector3, center: Vector3, up: Vector3) -> Matrix4 {
     }
 }
 
+/// Rotation turning the object +z axis towards `camera_pos`, with `up`
+/// constraining roll. Translation is left out, multiply by
+/// `translate(object_pos)` to place the object.
+#[inline]
+pub fn billboard(camera_pos: Vector3, object_pos: Vector3, up: Vector3) -> Matrix4 {
+    let front = camera_pos - object_pos;
+    let front = if front.mag_squared() > f32::EPSILON * f32::EPSILON {
+        front.normalized()
+    } else {
+        Vector3::Z
+    };
+    let right = up.cross(front);
+    let right = if right.mag_squared() > f32::EPSILON * f32::EPSILON {
+        right.normalized()
+    } else {
+        front.ortho().1
+    };
+    let up = front.cross(right).normalized();
+    Matrix4 {
+        i: Vector4::hom_vec(right),
+        j: Vector4::hom_vec(up),
+        k: Vector4::hom_vec(front),
+        l: Vector4::new(0.0, 0.0, 0.0, 1.0),
+    }
+}
+
 #[inline]
 pub fn scale(s: f32) -> Matrix4 {
     Matrix4 {
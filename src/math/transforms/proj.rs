@@ -1,16 +1,35 @@
+use super::Handedness;
 use crate::math::types::{Matrix4, Vector4};
 
+/// Maps view depth `near..far` to `0..1` with `fovy_deg` spanning the
+/// vertical extent and `aspect` being width over height. +y in clip space is
+/// up, the renderer flips the viewport to match Vulkan's y-down framebuffer.
 #[inline]
 pub fn perspective(fovy_deg: f32, aspect: f32, near: f32, far: f32) -> Matrix4 {
+    perspective_handed(fovy_deg, aspect, near, far, Handedness::Left)
+}
+
+#[inline]
+pub fn perspective_handed(
+    fovy_deg: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+    handedness: Handedness,
+) -> Matrix4 {
     let fovy = f32::to_radians(fovy_deg);
-    let xscale = 1.0 / f32::tan(fovy * 0.5);
-    let yscale = xscale / aspect;
+    let yscale = 1.0 / f32::tan(fovy * 0.5);
+    let xscale = yscale / aspect;
     let zscale = 0.5 * (far + near) / (near - far) - 0.5;
     let zpos = (far * near) / (near - far);
+    let k = match handedness {
+        Handedness::Left => Vector4::new(0.0, 0.0, -zscale, 1.0),
+        Handedness::Right => Vector4::new(0.0, 0.0, zscale, -1.0),
+    };
     Matrix4 {
         i: Vector4::new(xscale, 0.0, 0.0, 0.0),
         j: Vector4::new(0.0, yscale, 0.0, 0.0),
-        k: Vector4::new(0.0, 0.0, -zscale, 1.0),
+        k,
         l: Vector4::new(0.0, 0.0, zpos, 0.0),
     }
 }
@@ -1,5 +1,8 @@
 use bytemuck::{Pod, Zeroable};
-use std::ops::{Add, Index, IndexMut, Mul, Sub};
+use std::{
+    mem::size_of,
+    ops::{Add, Index, IndexMut, Mul, Sub},
+};
 
 use super::{Quaternion, Vector2, Vector3, Vector4};
 
@@ -37,6 +40,9 @@ unsafe impl Zeroable for Matrix4 {}
 unsafe impl Pod for Matrix4 {}
 
 impl Matrix2 {
+    pub const IDENTITY: Matrix2 = Matrix2::new(Vector2::X, Vector2::Y);
+    pub const ZERO: Matrix2 = Matrix2::new(Vector2::ZERO, Vector2::ZERO);
+
     #[inline]
     pub const fn new(i: Vector2, j: Vector2) -> Self {
         Self { i, j }
@@ -44,10 +50,7 @@ impl Matrix2 {
 
     #[inline]
     pub fn iden() -> Self {
-        Self {
-            i: Vector2::new(1.0, 0.0),
-            j: Vector2::new(0.0, 1.0),
-        }
+        Self::IDENTITY
     }
 
     #[inline]
@@ -130,6 +133,9 @@ impl IndexMut<usize> for Matrix2 {
 }
 
 impl Matrix3 {
+    pub const IDENTITY: Matrix3 = Matrix3::new(Vector3::X, Vector3::Y, Vector3::Z);
+    pub const ZERO: Matrix3 = Matrix3::new(Vector3::ZERO, Vector3::ZERO, Vector3::ZERO);
+
     #[inline]
     pub const fn new(i: Vector3, j: Vector3, k: Vector3) -> Self {
         Self { i, j, k }
@@ -137,10 +143,26 @@ impl Matrix3 {
 
     #[inline]
     pub fn iden() -> Self {
+        Self::IDENTITY
+    }
+
+    /// Outer product `a * b^T`, column `n` is `a` scaled by `b[n]`.
+    #[inline]
+    pub fn from_outer(a: Vector3, b: Vector3) -> Self {
+        Self {
+            i: a * b.x,
+            j: a * b.y,
+            k: a * b.z,
+        }
+    }
+
+    /// Cross product matrix, `skew(v) * x == v.cross(x)`.
+    #[inline]
+    pub fn skew(v: Vector3) -> Self {
         Self {
-            i: Vector3::new(1.0, 0.0, 0.0),
-            j: Vector3::new(0.0, 1.0, 0.0),
-            k: Vector3::new(0.0, 0.0, 1.0),
+            i: Vector3::new(0.0, v.z, -v.y),
+            j: Vector3::new(-v.z, 0.0, v.x),
+            k: Vector3::new(v.y, -v.x, 0.0),
         }
     }
 
@@ -166,7 +188,7 @@ impl Matrix3 {
         l = 0;
         let mut y = [0; 2];
         for n in 0..3 {
-            if n == i {
+            if n == j {
                 continue;
             }
             y[l] = n;
@@ -299,6 +321,10 @@ impl From<Quaternion> for Matrix3 {
 }
 
 impl Matrix4 {
+    pub const IDENTITY: Matrix4 = Matrix4::new(Vector4::X, Vector4::Y, Vector4::Z, Vector4::W);
+    pub const ZERO: Matrix4 =
+        Matrix4::new(Vector4::ZERO, Vector4::ZERO, Vector4::ZERO, Vector4::ZERO);
+
     #[inline]
     pub const fn new(i: Vector4, j: Vector4, k: Vector4, l: Vector4) -> Self {
         Self { i, j, k, l }
@@ -306,12 +332,34 @@ impl Matrix4 {
 
     #[inline]
     pub fn iden() -> Self {
-        Self {
-            i: Vector4::new(1.0, 0.0, 0.0, 0.0),
-            j: Vector4::new(0.0, 1.0, 0.0, 0.0),
-            k: Vector4::new(0.0, 0.0, 1.0, 0.0),
-            l: Vector4::new(0.0, 0.0, 0.0, 1.0),
+        Self::IDENTITY
+    }
+
+    /// Flattens the matrix in column-major order: `i`, `j`, `k`, `l`, each as `x, y, z, w`.
+    #[inline]
+    pub fn to_array(&self) -> [f32; 16] {
+        bytemuck::cast(*self)
+    }
+
+    /// Inverse of [`Matrix4::to_array`], reading four consecutive columns.
+    #[inline]
+    pub fn from_array(array: &[f32; 16]) -> Self {
+        bytemuck::cast(*array)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        bytemuck::bytes_of(self)
+    }
+
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != size_of::<Self>() {
+            return None;
         }
+        let mut matrix = Self::default();
+        bytemuck::bytes_of_mut(&mut matrix).copy_from_slice(bytes);
+        Some(matrix)
     }
 
     #[inline]
@@ -340,9 +388,9 @@ impl Matrix4 {
             l += 1;
         }
         Matrix3 {
-            i: Vector3::new(self[x[0]][y[0]], self[x[1]][x[0]], self[x[2]][x[0]]),
-            j: Vector3::new(self[x[0]][y[1]], self[x[1]][x[1]], self[x[2]][x[1]]),
-            k: Vector3::new(self[x[0]][y[2]], self[x[1]][x[2]], self[x[2]][x[2]]),
+            i: Vector3::new(self[x[0]][y[0]], self[x[1]][y[0]], self[x[2]][y[0]]),
+            j: Vector3::new(self[x[0]][y[1]], self[x[1]][y[1]], self[x[2]][y[1]]),
+            k: Vector3::new(self[x[0]][y[2]], self[x[1]][y[2]], self[x[2]][y[2]]),
         }
     }
 
@@ -376,6 +424,70 @@ impl Matrix4 {
         }
         cof * (1f32 / self.det())
     }
+
+    #[inline]
+    pub fn transform_direction(&self, v: Vector3) -> Vector3 {
+        let v = *self * Vector4::hom_vec(v);
+        Vector3::new(v.x, v.y, v.z)
+    }
+
+    #[inline]
+    pub fn transform_point(&self, p: Vector3) -> Vector3 {
+        let p = *self * Vector4::hom_point(p);
+        Vector3::new(p.x, p.y, p.z)
+    }
+
+    /// Translation `t` after rotation `r` after the per axis scale `s`.
+    pub fn from_trs(t: Vector3, r: Quaternion, s: Vector3) -> Self {
+        Matrix4 {
+            i: Vector4::hom_vec(r.rotate_point(Vector3::X) * s.x),
+            j: Vector4::hom_vec(r.rotate_point(Vector3::Y) * s.y),
+            k: Vector4::hom_vec(r.rotate_point(Vector3::Z) * s.z),
+            l: Vector4::hom_point(t),
+        }
+    }
+
+    /// Splits the upper 3x3 block into the nearest orthonormal matrix and a
+    /// uniform scale, iterating `Q = (Q + Q^-T) / 2` until it settles. The
+    /// translation is dropped and a mirroring block gives a mirroring `Q`.
+    /// The scale is the one best fitting the remaining stretch `Q^T M`, so
+    /// for non-uniform scaling it is the mean of the per axis factors.
+    pub fn polar_decompose(self) -> (Matrix4, f32) {
+        const MAX_ITERATIONS: usize = 32;
+        const TOLERANCE: f32 = 1e-6;
+        let block = [self.i.xyz(), self.j.xyz(), self.k.xyz()];
+        let mut q = block;
+        for _ in 0..MAX_ITERATIONS {
+            // Columns of the inverse transpose are the cross products of the
+            // other two columns over the determinant
+            let det = q[0] * q[1].cross(q[2]);
+            if det.abs() <= f32::EPSILON {
+                break;
+            }
+            let inv_t = [
+                q[1].cross(q[2]) / det,
+                q[2].cross(q[0]) / det,
+                q[0].cross(q[1]) / det,
+            ];
+            let mut change = 0.0f32;
+            for (column, inv_t) in q.iter_mut().zip(&inv_t) {
+                let next = (*column + *inv_t) * 0.5;
+                change = change.max((next - *column).mag());
+                *column = next;
+            }
+            if change <= TOLERANCE {
+                break;
+            }
+        }
+        let scale = q.iter().zip(&block).map(|(&q, &m)| q * m).sum::<f32>() / 3.0;
+        let rotation = Matrix4 {
+            i: Vector4::hom_vec(q[0]),
+            j: Vector4::hom_vec(q[1]),
+            k: Vector4::hom_vec(q[2]),
+            l: Vector4::W,
+        };
+        (rotation, scale)
+    }
 }
 
 impl Add for Matrix4 {
@@ -425,7 +537,7 @@ impl Mul<Vector4> for Matrix4 {
         let z = self.k * rhs.z;
         let l = self.l * rhs.w;
         Vector4 {
-            x: x.x + y.x + z.x + l.w,
+            x: x.x + y.x + z.x + l.x,
             y: x.y + y.y + z.y + l.y,
             z: x.z + y.z + z.z + l.z,
             w: x.w + y.w + z.w + l.w,
@@ -433,6 +545,15 @@ impl Mul<Vector4> for Matrix4 {
     }
 }
 
+impl Mul<Vector3> for Matrix4 {
+    type Output = Vector3;
+    #[inline]
+    fn mul(self, rhs: Vector3) -> Self::Output {
+        let p = self * Vector4::hom_point(rhs);
+        Vector3::new(p.x, p.y, p.z) / p.w
+    }
+}
+
 impl Mul<f32> for Matrix4 {
     type Output = Self;
     #[inline]
@@ -462,3 +583,223 @@ impl IndexMut<usize> for Matrix4 {
         unsafe { &mut *(&mut self.i as *mut Vector4).offset(index as isize) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_identity4(m: Matrix4) {
+        let iden = Matrix4::iden();
+        for c in 0..4 {
+            for r in 0..4 {
+                assert!(f32::abs(m[c][r] - iden[c][r]) < 1e-5, "{:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn matrix3_inverse() {
+        let m = Matrix3::new(
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(1.0, 3.0, 0.0),
+            Vector3::new(0.0, 1.0, 4.0),
+        );
+        let product = m * m.inv();
+        let iden = Matrix3::iden();
+        for c in 0..3 {
+            for r in 0..3 {
+                assert!(f32::abs(product[c][r] - iden[c][r]) < 1e-5, "{:?}", product);
+            }
+        }
+    }
+
+    #[test]
+    fn matrix4_inverse() {
+        let m = Matrix4::from_trs(
+            Vector3::new(1.0, -2.0, 3.0),
+            Quaternion::vec_angle(Vector3::new(1.0, 1.0, 0.0).normalized(), 0.7),
+            Vector3::new(2.0, 0.5, 1.5),
+        );
+        assert!(f32::abs(m.det() - 1.5) < 1e-5);
+        assert_identity4(m * m.inv());
+        assert_identity4(m.inv() * m);
+        // Projections have no affine bottom row
+        let p = Matrix4::new(
+            Vector4::new(1.5, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 2.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, 1.1, 1.0),
+            Vector4::new(0.0, 0.0, -0.2, 0.0),
+        );
+        assert_identity4(p * p.inv());
+    }
+
+    #[test]
+    fn skew_matches_cross_product() {
+        let v = Vector3::new(1.0, -2.0, 0.5);
+        let skew = Matrix3::skew(v);
+        for x in [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(3.0, 4.0, -5.0),
+        ] {
+            assert!((skew * x - v.cross(x)).mag() < 1e-6);
+        }
+        // Skew symmetric, and `v` lies in its null space
+        let sum = skew + skew.transpose();
+        for c in 0..3 {
+            assert_eq!(sum[c], Vector3::ZERO);
+        }
+        assert_eq!(skew * v, Vector3::ZERO);
+    }
+
+    #[test]
+    fn outer_product_applies_dot_product() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(-1.0, 0.5, 2.0);
+        let outer = Matrix3::from_outer(a, b);
+        for c in 0..3 {
+            for r in 0..3 {
+                assert_eq!(outer[c][r], a[r] * b[c]);
+            }
+        }
+        let x = Vector3::new(0.5, -3.0, 1.0);
+        assert!((outer * x - a * (b * x)).mag() < 1e-6);
+        let transposed = Matrix3::from_outer(b, a);
+        for c in 0..3 {
+            assert_eq!(transposed[c], outer.transpose()[c]);
+        }
+    }
+
+    fn assert_matrix4_eq(a: Matrix4, b: Matrix4) {
+        for c in 0..4 {
+            assert!((a[c] - b[c]).mag() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn from_trs_scales_then_rotates_then_translates() {
+        let (t, s) = (Vector3::new(1.0, 2.0, 3.0), Vector3::new(2.0, 0.5, 3.0));
+        let r = Quaternion::vec_angle(Vector3::new(0.3, 1.0, -0.4).normalized(), 1.2);
+        let m = Matrix4::from_trs(t, r, s);
+        let p = Vector3::new(-1.0, 4.0, 0.5);
+        let expected = t + r.rotate_point(Vector3::new(p.x * s.x, p.y * s.y, p.z * s.z));
+        assert!((m.transform_point(p) - expected).mag() < 1e-5);
+    }
+
+    #[test]
+    fn polar_decompose_recovers_rotation_and_scale() {
+        let r = Quaternion::vec_angle(Vector3::new(1.0, -2.0, 0.5).normalized(), 2.3);
+        let rotation = Matrix4::from_trs(Vector3::ZERO, r, Vector3::new(1.0, 1.0, 1.0));
+        let m = Matrix4::from_trs(Vector3::new(5.0, 6.0, 7.0), r, Vector3::new(2.5, 2.5, 2.5));
+        let (q, scale) = m.polar_decompose();
+        assert_matrix4_eq(q, rotation);
+        assert!(f32::abs(scale - 2.5) < 1e-5);
+
+        // Non-uniform scaling keeps the rotation and averages the factors
+        let m = Matrix4::from_trs(Vector3::ZERO, r, Vector3::new(1.0, 2.0, 3.0));
+        let (q, scale) = m.polar_decompose();
+        assert_matrix4_eq(q, rotation);
+        assert!(f32::abs(scale - 2.0) < 1e-5);
+        assert_identity4(q * q.transpose());
+    }
+
+    #[test]
+    fn polar_decompose_keeps_mirroring() {
+        let r = Quaternion::vec_angle(Vector3::Z, 0.8);
+        let m = Matrix4::from_trs(Vector3::ZERO, r, Vector3::new(-2.0, 2.0, 2.0));
+        let (q, scale) = m.polar_decompose();
+        assert!(f32::abs(q.det() + 1.0) < 1e-5);
+        assert!(f32::abs(scale - 2.0) < 1e-5);
+        assert_matrix4_eq(
+            q * scale,
+            Matrix4 {
+                l: Vector4::W * scale,
+                ..m
+            },
+        );
+    }
+
+    fn numbered() -> Matrix4 {
+        Matrix4::new(
+            Vector4::new(1.0, 2.0, 3.0, 4.0),
+            Vector4::new(5.0, 6.0, 7.0, 8.0),
+            Vector4::new(9.0, 10.0, 11.0, 12.0),
+            Vector4::new(13.0, 14.0, 15.0, 16.0),
+        )
+    }
+
+    #[test]
+    fn array_is_column_major() {
+        let m = numbered();
+        let array = m.to_array();
+        assert_eq!(array, std::array::from_fn(|index| index as f32 + 1.0));
+        // Translation sits in the last four elements
+        let t = crate::math::transforms::translate(Vector3::new(7.0, 8.0, 9.0)).to_array();
+        assert_eq!(t[12..], [7.0, 8.0, 9.0, 1.0]);
+        let back = Matrix4::from_array(&array);
+        for c in 0..4 {
+            assert_eq!(back[c], m[c]);
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let m = numbered();
+        let bytes = m.as_bytes();
+        assert_eq!(bytes.len(), 64);
+        assert_eq!(bytes[..4], 1.0f32.to_ne_bytes());
+        let back = Matrix4::from_bytes(bytes).unwrap();
+        assert_eq!(back.to_array(), m.to_array());
+        assert!(Matrix4::from_bytes(&bytes[..60]).is_none());
+        assert!(Matrix4::from_bytes(&[0; 68]).is_none());
+    }
+
+    // Usable in const contexts, unlike `iden()`
+    crate::utils::const_assert!(
+        Matrix4::IDENTITY.i.x == 1.0
+            && Matrix4::IDENTITY.j.y == 1.0
+            && Matrix4::IDENTITY.k.z == 1.0
+            && Matrix4::IDENTITY.l.w == 1.0
+            && Matrix4::IDENTITY.l.x == 0.0
+            && Matrix4::ZERO.l.w == 0.0
+    );
+
+    #[test]
+    fn identity_constants_match_iden() {
+        use bytemuck::bytes_of;
+        assert_eq!(bytes_of(&Matrix2::IDENTITY), bytes_of(&Matrix2::iden()));
+        assert_eq!(bytes_of(&Matrix3::IDENTITY), bytes_of(&Matrix3::iden()));
+        assert_eq!(bytes_of(&Matrix4::IDENTITY), bytes_of(&Matrix4::iden()));
+        let v = Vector4::new(1.0, -2.0, 3.0, 0.5);
+        assert_eq!(Matrix4::IDENTITY * v, v);
+        assert_eq!(Matrix3::IDENTITY * v.xyz(), v.xyz());
+        assert_eq!(
+            Matrix2::IDENTITY * Vector2::new(4.0, 5.0),
+            Vector2::new(4.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn zero_constants_annihilate() {
+        let v = Vector4::new(1.0, -2.0, 3.0, 0.5);
+        assert_eq!(Matrix4::ZERO * v, Vector4::ZERO);
+        assert_eq!(Matrix3::ZERO * v.xyz(), Vector3::ZERO);
+        assert_eq!(Matrix2::ZERO * Vector2::ONE, Vector2::ZERO);
+        assert!(bytemuck::bytes_of(&Matrix4::ZERO)
+            .iter()
+            .all(|&byte| byte == 0));
+        assert_eq!(Matrix4::ZERO.to_array(), Matrix4::default().to_array());
+    }
+
+    #[test]
+    fn translation_moves_points_but_not_directions() {
+        let t = Vector3::new(1.0, -2.0, 3.0);
+        let m = Matrix4::from_trs(t, Quaternion::default(), Vector3::ONE);
+        let v = Vector3::new(0.5, 4.0, -1.0);
+        assert!((m * v - (v + t)).mag() < 1e-6);
+        assert!((m.transform_point(v) - (v + t)).mag() < 1e-6);
+        assert!((m.transform_direction(v) - v).mag() < 1e-6);
+        // Scaling w out of the matrix is undone by the perspective divide
+        assert!(((m * 2.0) * v - (v + t)).mag() < 1e-6);
+    }
+}
@@ -132,7 +132,7 @@ impl Mul for Quaternion {
             r: self.r * rhs.r - self.i * rhs.i - self.j * rhs.j - self.k * rhs.k,
             i: self.i * rhs.r + self.r * rhs.i + self.j * rhs.k - self.k * rhs.j,
             j: self.j * rhs.r + self.r * rhs.j + self.k * rhs.i - self.i * rhs.k,
-            k: self.k * rhs.r + self.r * rhs.i + self.i * rhs.j - self.j * rhs.i,
+            k: self.k * rhs.r + self.r * rhs.k + self.i * rhs.j - self.j * rhs.i,
         }
     }
 }
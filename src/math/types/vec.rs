@@ -35,6 +35,11 @@ unsafe impl Zeroable for Vector4 {}
 unsafe impl Pod for Vector4 {}
 
 impl Vector2 {
+    pub const ZERO: Vector2 = Vector2::new(0.0, 0.0);
+    pub const ONE: Vector2 = Vector2::new(1.0, 1.0);
+    pub const X: Vector2 = Vector2::new(1.0, 0.0);
+    pub const Y: Vector2 = Vector2::new(0.0, 1.0);
+
     #[inline]
     pub const fn new(x: f32, y: f32) -> Self {
         Self { x, y }
@@ -136,6 +141,15 @@ impl IndexMut<usize> for Vector2 {
 }
 
 impl Vector3 {
+    pub const ZERO: Vector3 = Vector3::new(0.0, 0.0, 0.0);
+    pub const ONE: Vector3 = Vector3::new(1.0, 1.0, 1.0);
+    pub const X: Vector3 = Vector3::new(1.0, 0.0, 0.0);
+    pub const Y: Vector3 = Vector3::new(0.0, 1.0, 0.0);
+    pub const Z: Vector3 = Vector3::new(0.0, 0.0, 1.0);
+    pub const NEG_X: Vector3 = Vector3::new(-1.0, 0.0, 0.0);
+    pub const NEG_Y: Vector3 = Vector3::new(0.0, -1.0, 0.0);
+    pub const NEG_Z: Vector3 = Vector3::new(0.0, 0.0, -1.0);
+
     #[inline]
     pub const fn new(x: f32, y: f32, z: f32) -> Self {
         Self { x, y, z }
@@ -263,6 +277,13 @@ impl IndexMut<usize> for Vector3 {
 }
 
 impl Vector4 {
+    pub const ZERO: Vector4 = Vector4::new(0.0, 0.0, 0.0, 0.0);
+    pub const ONE: Vector4 = Vector4::new(1.0, 1.0, 1.0, 1.0);
+    pub const X: Vector4 = Vector4::new(1.0, 0.0, 0.0, 0.0);
+    pub const Y: Vector4 = Vector4::new(0.0, 1.0, 0.0, 0.0);
+    pub const Z: Vector4 = Vector4::new(0.0, 0.0, 1.0, 0.0);
+    pub const W: Vector4 = Vector4::new(0.0, 0.0, 0.0, 1.0);
+
     #[inline]
     pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
         Self { x, y, z, w }
@@ -288,6 +309,23 @@ impl Vector4 {
         }
     }
 
+    #[inline]
+    pub fn xyz(self) -> Vector3 {
+        Vector3::new(self.x, self.y, self.z)
+    }
+
+    /// Dot product of the xyz parts, w is ignored.
+    #[inline]
+    pub fn dot3(self, rhs: Self) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
+
+    /// Cross product of the xyz parts, returned as a direction with w = 0.
+    #[inline]
+    pub fn cross3(self, rhs: Self) -> Self {
+        Self::hom_vec(self.xyz().cross(rhs.xyz()))
+    }
+
     #[inline]
     pub fn mag_squared(self) -> f32 {
         self * self
@@ -388,3 +426,48 @@ impl IndexMut<usize> for Vector4 {
         unsafe { &mut *(&mut self.x as *mut f32).offset(index as isize) }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dot3_ignores_w() {
+        let a = Vector4::hom_point(Vector3::new(1.0, 2.0, 3.0));
+        let b = Vector4::hom_point(Vector3::new(-2.0, 0.5, 1.0));
+        assert_eq!(a.dot3(b), 2.0);
+        // The full dot product picks up the homogeneous coordinates
+        assert_eq!(a * b, 3.0);
+        assert_eq!(a.dot3(b), a.xyz() * b.xyz());
+    }
+
+    #[test]
+    fn cross3_is_a_direction() {
+        let a = Vector4::hom_point(Vector3::new(1.0, 0.0, 0.0));
+        let b = Vector4::new(0.0, 1.0, 0.0, 5.0);
+        let cross = a.cross3(b);
+        assert_eq!(cross, Vector4::new(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(b.cross3(a), -cross);
+        assert_eq!(cross.dot3(a), 0.0);
+        assert_eq!(cross.dot3(b), 0.0);
+    }
+
+    #[test]
+    fn axis_constants() {
+        assert_eq!(Vector3::X.cross(Vector3::Y), Vector3::Z);
+        assert_eq!(Vector3::Y.cross(Vector3::Z), Vector3::X);
+        assert_eq!(Vector3::Z.cross(Vector3::X), Vector3::Y);
+        assert_eq!(Vector3::ZERO.mag(), 0.0);
+        assert_eq!(Vector3::X + Vector3::Y + Vector3::Z, Vector3::ONE);
+        assert_eq!(-Vector3::X, Vector3::NEG_X);
+        assert_eq!(-Vector3::Y, Vector3::NEG_Y);
+        assert_eq!(-Vector3::Z, Vector3::NEG_Z);
+        assert_eq!(Vector2::X + Vector2::Y, Vector2::ONE);
+        assert_eq!(Vector2::ZERO.mag(), 0.0);
+        assert_eq!(
+            Vector4::X + Vector4::Y + Vector4::Z + Vector4::W,
+            Vector4::ONE
+        );
+        assert_eq!(Vector4::ZERO.mag(), 0.0);
+    }
+}
@@ -1,3 +1,22 @@
+mod aabb;
+mod body;
+pub mod collision;
+mod material;
+mod minkowski;
+mod ray;
 mod shape;
+mod spatial_hash;
+mod thread;
+mod tree;
+mod world;
 
+pub use aabb::*;
+pub use body::*;
+pub use material::*;
+pub use minkowski::*;
+pub use ray::*;
 pub use shape::*;
+pub use spatial_hash::*;
+pub use thread::*;
+pub use tree::*;
+pub use world::*;
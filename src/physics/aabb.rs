@@ -0,0 +1,153 @@
+use super::{Ray, RigidBody, Shape};
+use crate::math::types::{Matrix4, Vector3};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vector3,
+    pub max: Vector3,
+}
+
+impl Aabb {
+    #[inline]
+    pub fn new(min: Vector3, max: Vector3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_points(points: &[Vector3]) -> Self {
+        let init = Self::new(
+            Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+            -Vector3::new(f32::MAX, f32::MAX, f32::MAX),
+        );
+        points.iter().fold(init, |aabb, &point| aabb.expand(point))
+    }
+
+    pub fn from_shape(shape: &Shape, world: &Matrix4) -> Self {
+        shape.aabb().transform(world)
+    }
+
+    pub fn from_body(body: &RigidBody) -> Self {
+        body.shape.aabb().transform(&body.world_transform())
+    }
+
+    pub fn transform(&self, matrix: &Matrix4) -> Self {
+        let center = *matrix * self.center();
+        let half = self.extent() / 2.0;
+        let (mut min, mut max) = (center, center);
+        for (column, &half) in [matrix.i, matrix.j, matrix.k]
+            .iter()
+            .zip(&[half.x, half.y, half.z])
+        {
+            for i in 0..3 {
+                let offset = f32::abs(column[i]) * half;
+                min[i] -= offset;
+                max[i] += offset;
+            }
+        }
+        Self { min, max }
+    }
+
+    pub fn corners(&self) -> [Vector3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vector3::new(min.x, min.y, min.z),
+            Vector3::new(max.x, min.y, min.z),
+            Vector3::new(min.x, max.y, min.z),
+            Vector3::new(max.x, max.y, min.z),
+            Vector3::new(min.x, min.y, max.z),
+            Vector3::new(max.x, min.y, max.z),
+            Vector3::new(min.x, max.y, max.z),
+            Vector3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vector3 {
+        (self.min + self.max) / 2.0
+    }
+
+    #[inline]
+    pub fn extent(&self) -> Vector3 {
+        self.max - self.min
+    }
+
+    pub fn expand(&self, point: Vector3) -> Self {
+        let (mut min, mut max) = (self.min, self.max);
+        for i in 0..3 {
+            min[i] = f32::min(min[i], point[i]);
+            max[i] = f32::max(max[i], point[i]);
+        }
+        Self { min, max }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        self.expand(other.min).expand(other.max)
+    }
+
+    pub fn closest_point(&self, point: Vector3) -> Vector3 {
+        let mut closest = point;
+        for i in 0..3 {
+            closest[i] = f32::clamp(point[i], self.min[i], self.max[i]);
+        }
+        closest
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        (0..3).all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::MAX;
+        for i in 0..3 {
+            if f32::abs(ray.dir[i]) < f32::EPSILON {
+                if ray.origin[i] < self.min[i] || ray.origin[i] > self.max[i] {
+                    return None;
+                }
+                continue;
+            }
+            let inv = 1.0 / ray.dir[i];
+            let t0 = (self.min[i] - ray.origin[i]) * inv;
+            let t1 = (self.max[i] - ray.origin[i]) * inv;
+            t_min = f32::max(t_min, f32::min(t0, t1));
+            t_max = f32::min(t_max, f32::max(t0, t1));
+            if t_min > t_max {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::transforms;
+
+    #[test]
+    fn rotated_box_matches_transformed_corners() {
+        let unit = Aabb::new(-Vector3::ONE / 2.0, Vector3::ONE / 2.0);
+        let rotation = transforms::rot_z(std::f32::consts::FRAC_PI_4);
+        let matrix = transforms::translate(Vector3::new(1.0, 2.0, 3.0)) * rotation;
+        let transformed = unit.transform(&matrix);
+        let corners = unit
+            .corners()
+            .iter()
+            .map(|&corner| matrix.transform_point(corner))
+            .collect::<Vec<_>>();
+        let tight = Aabb::from_points(&corners);
+        assert!(
+            (transformed.min - tight.min).mag() < 1e-5,
+            "{:?}",
+            transformed
+        );
+        assert!(
+            (transformed.max - tight.max).mag() < 1e-5,
+            "{:?}",
+            transformed
+        );
+        // The diagonal of the unit square is the new width
+        let half_diagonal = std::f32::consts::SQRT_2 / 2.0;
+        assert!(f32::abs(transformed.max.x - 1.0 - half_diagonal) < 1e-5);
+        assert!(f32::abs(transformed.max.z - 3.5) < 1e-5);
+    }
+}
@@ -0,0 +1,435 @@
+use super::{MaterialTable, Shape, DEFAULT_MATERIAL_ID};
+use crate::{
+    log,
+    math::{
+        transforms,
+        types::{Matrix3, Matrix4, Quaternion, Vector3},
+    },
+};
+
+const ORIENTATION_DRIFT_TOLERANCE: f32 = 1e-6;
+const DEFAULT_BODY_MASS: f32 = 1.0;
+const DEFAULT_MAX_LINEAR_VELOCITY: f32 = 1000.0;
+const DEFAULT_MAX_ANGULAR_VELOCITY: f32 = 100.0;
+
+#[derive(Debug, Clone)]
+pub struct RigidBody {
+    pub shape: Shape,
+    pub position: Vector3,
+    pub orientation: Quaternion,
+    pub linear_velocity: Vector3,
+    pub angular_velocity: Vector3,
+    pub force: Vector3,
+    pub inv_mass: f32,
+    pub material_id: u32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    pub gravity_scale: f32,
+    pub max_linear_velocity: f32,
+    pub max_angular_velocity: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RigidBodyBuilder {
+    position: Vector3,
+    orientation: Quaternion,
+    mass: f32,
+    linear_velocity: Vector3,
+    angular_velocity: Vector3,
+    material_id: u32,
+    linear_damping: f32,
+    angular_damping: f32,
+    gravity_scale: f32,
+    max_linear_velocity: f32,
+    max_angular_velocity: f32,
+}
+
+impl RigidBody {
+    pub fn new(shape: Shape, position: Vector3, mass: f32) -> Self {
+        Self {
+            shape,
+            position,
+            orientation: Quaternion::default(),
+            linear_velocity: Vector3::default(),
+            angular_velocity: Vector3::default(),
+            force: Vector3::default(),
+            inv_mass: if mass > 0.0 { 1.0 / mass } else { 0.0 },
+            material_id: DEFAULT_MATERIAL_ID,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            max_linear_velocity: DEFAULT_MAX_LINEAR_VELOCITY,
+            max_angular_velocity: DEFAULT_MAX_ANGULAR_VELOCITY,
+        }
+    }
+
+    /// Creates a body at the origin, zero volume shapes such as planes produce a static body.
+    pub fn from_shape_and_density(shape: Shape, density: f32) -> Self {
+        let mass = shape.volume() * density;
+        Self::new(shape, Vector3::ZERO, mass)
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f32 {
+        if self.inv_mass > 0.0 {
+            1.0 / self.inv_mass
+        } else {
+            0.0
+        }
+    }
+
+    #[inline]
+    pub fn apply_force(&mut self, force: Vector3) {
+        self.force = self.force + force;
+    }
+
+    /// Inverse inertia tensor rotated into world space, zero for static
+    /// bodies and shapes without rotational inertia.
+    pub fn world_inv_inertia(&self) -> Matrix3 {
+        let Matrix3 { i, j, k } = self.shape.inertia_tensor(self.mass());
+        let det = i * j.cross(k);
+        if self.inv_mass <= 0.0 || det.abs() <= f32::EPSILON {
+            return Matrix3::ZERO;
+        }
+        // Rows of the inverse are the cross products of the other two columns
+        let inv_local = Matrix3::new(j.cross(k), k.cross(i), i.cross(j)).transpose() * (1.0 / det);
+        let rotate = |axis: Vector3| {
+            let local = self.orientation.inverse().rotate_point(axis);
+            self.orientation.rotate_point(inv_local * local)
+        };
+        Matrix3::new(rotate(Vector3::X), rotate(Vector3::Y), rotate(Vector3::Z))
+    }
+
+    /// Changes only the spin of the body, unlike an impulse applied off the
+    /// center of mass there is no linear component.
+    pub fn apply_angular_impulse(&mut self, impulse: Vector3) {
+        self.angular_velocity = self.angular_velocity + self.world_inv_inertia() * impulse;
+    }
+
+    pub fn integrate(&mut self, dt: f32) {
+        self.integrate_velocity(dt);
+        self.integrate_position(dt);
+    }
+
+    pub fn integrate_velocity(&mut self, dt: f32) {
+        self.linear_velocity = self.linear_velocity + self.force * (self.inv_mass * dt);
+        self.linear_velocity = self.linear_velocity * f32::max(1.0 - self.linear_damping * dt, 0.0);
+        self.angular_velocity =
+            self.angular_velocity * f32::max(1.0 - self.angular_damping * dt, 0.0);
+        self.force = Vector3::default();
+        self.limit_velocities();
+    }
+
+    pub fn limit_velocities(&mut self) {
+        if !self.linear_velocity.is_valid() || !self.angular_velocity.is_valid() {
+            log::warning(&format!(
+                "Non-finite rigid body velocity (linear: {:?}, angular: {:?}), resetting to zero",
+                self.linear_velocity, self.angular_velocity
+            ));
+            self.linear_velocity = Vector3::ZERO;
+            self.angular_velocity = Vector3::ZERO;
+            return;
+        }
+        let clamp = |velocity: Vector3, max: f32| {
+            let speed = velocity.mag();
+            if speed > max {
+                velocity * (max / speed)
+            } else {
+                velocity
+            }
+        };
+        self.linear_velocity = clamp(self.linear_velocity, self.max_linear_velocity);
+        self.angular_velocity = clamp(self.angular_velocity, self.max_angular_velocity);
+    }
+
+    pub fn integrate_position(&mut self, dt: f32) {
+        self.position = self.position + self.linear_velocity * dt;
+        let angle = self.angular_velocity.mag() * dt;
+        if angle > 0.0 {
+            self.orientation =
+                Quaternion::vec_angle(self.angular_velocity, angle) * self.orientation;
+        }
+        if f32::abs(self.orientation.mag_squared() - 1.0) > ORIENTATION_DRIFT_TOLERANCE {
+            self.orientation = self.orientation.normalized();
+        }
+    }
+
+    #[inline]
+    pub fn world_transform(&self) -> Matrix4 {
+        transforms::from_quaternion_translation(self.orientation, self.position)
+    }
+
+    #[inline]
+    pub fn local_to_world(&self, point: Vector3) -> Vector3 {
+        self.position + self.orientation.rotate_point(point)
+    }
+
+    #[inline]
+    pub fn world_to_local(&self, point: Vector3) -> Vector3 {
+        self.orientation
+            .inverse()
+            .rotate_point(point - self.position)
+    }
+}
+
+impl RigidBodyBuilder {
+    pub fn new() -> Self {
+        Self {
+            position: Vector3::ZERO,
+            orientation: Quaternion::default(),
+            mass: DEFAULT_BODY_MASS,
+            linear_velocity: Vector3::ZERO,
+            angular_velocity: Vector3::ZERO,
+            material_id: DEFAULT_MATERIAL_ID,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            gravity_scale: 1.0,
+            max_linear_velocity: DEFAULT_MAX_LINEAR_VELOCITY,
+            max_angular_velocity: DEFAULT_MAX_ANGULAR_VELOCITY,
+        }
+    }
+
+    pub fn with_position(self, position: Vector3) -> Self {
+        Self { position, ..self }
+    }
+
+    pub fn with_orientation(self, orientation: Quaternion) -> Self {
+        Self {
+            orientation,
+            ..self
+        }
+    }
+
+    pub fn with_mass(self, mass: f32) -> Self {
+        Self { mass, ..self }
+    }
+
+    pub fn with_linear_velocity(self, linear_velocity: Vector3) -> Self {
+        Self {
+            linear_velocity,
+            ..self
+        }
+    }
+
+    pub fn with_angular_velocity(self, angular_velocity: Vector3) -> Self {
+        Self {
+            angular_velocity,
+            ..self
+        }
+    }
+
+    pub fn with_material(self, material_id: u32) -> Self {
+        Self {
+            material_id,
+            ..self
+        }
+    }
+
+    /// Assigns a frictionless material with the given restitution, reusing
+    /// a matching material of `materials` or adding one.
+    pub fn with_restitution(self, restitution: f32, materials: &mut MaterialTable) -> Self {
+        self.with_material(materials.find_or_add_restitution(restitution))
+    }
+
+    pub fn with_damping(self, linear_damping: f32, angular_damping: f32) -> Self {
+        Self {
+            linear_damping,
+            angular_damping,
+            ..self
+        }
+    }
+
+    pub fn with_gravity_scale(self, gravity_scale: f32) -> Self {
+        Self {
+            gravity_scale,
+            ..self
+        }
+    }
+
+    pub fn with_max_velocity(self, max_linear_velocity: f32, max_angular_velocity: f32) -> Self {
+        Self {
+            max_linear_velocity,
+            max_angular_velocity,
+            ..self
+        }
+    }
+
+    pub fn build(self, shape: Shape) -> RigidBody {
+        RigidBody {
+            orientation: self.orientation.normalized(),
+            linear_velocity: self.linear_velocity,
+            angular_velocity: self.angular_velocity,
+            material_id: self.material_id,
+            linear_damping: self.linear_damping,
+            angular_damping: self.angular_damping,
+            gravity_scale: self.gravity_scale,
+            max_linear_velocity: self.max_linear_velocity,
+            max_angular_velocity: self.max_angular_velocity,
+            ..RigidBody::new(shape, self.position, self.mass)
+        }
+    }
+}
+
+impl Default for RigidBodyBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angular_impulse_on_principal_axis() {
+        let mut body = RigidBody::new(
+            Shape::new_cuboid(Vector3::new(1.0, 2.0, 3.0)),
+            Vector3::new(4.0, 5.0, 6.0),
+            2.0,
+        );
+        body.orientation = Quaternion::vec_angle(Vector3::new(1.0, 1.0, 1.0).normalized(), 0.9);
+        let inertia = body.shape.inertia_tensor(body.mass());
+        for (axis, moment) in [
+            (Vector3::X, inertia.i.x),
+            (Vector3::Y, inertia.j.y),
+            (Vector3::Z, inertia.k.z),
+        ] {
+            body.angular_velocity = Vector3::ZERO;
+            let world_axis = body.orientation.rotate_point(axis);
+            body.apply_angular_impulse(world_axis * 3.0);
+            // Spin stays on the principal axis, scaled by its moment
+            assert!((body.angular_velocity - world_axis * (3.0 / moment)).mag() < 1e-5);
+            assert_eq!(body.linear_velocity, Vector3::ZERO);
+        }
+    }
+
+    #[test]
+    fn world_inv_inertia_inverts_rotated_tensor() {
+        let mut body = RigidBody::new(
+            Shape::new_cuboid(Vector3::new(0.5, 1.0, 2.0)),
+            Vector3::ZERO,
+            3.0,
+        );
+        body.orientation = Quaternion::vec_angle(Vector3::new(0.2, -1.0, 0.5).normalized(), 2.1);
+        let local = body.shape.inertia_tensor(body.mass());
+        let rotate = |axis: Vector3| {
+            let local_axis = body.orientation.inverse().rotate_point(axis);
+            body.orientation.rotate_point(local * local_axis)
+        };
+        let world = Matrix3::new(rotate(Vector3::X), rotate(Vector3::Y), rotate(Vector3::Z));
+        let product = body.world_inv_inertia() * world;
+        let identity = Matrix3::IDENTITY;
+        for c in 0..3 {
+            assert!((product[c] - identity[c]).mag() < 1e-5, "{:?}", product);
+        }
+    }
+
+    #[test]
+    fn static_body_ignores_angular_impulse() {
+        let mut body = RigidBody::new(Shape::new_sphere(1.0), Vector3::ZERO, 0.0);
+        body.apply_angular_impulse(Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(body.angular_velocity, Vector3::ZERO);
+        assert_eq!(body.world_inv_inertia().i, Vector3::ZERO);
+    }
+
+    #[test]
+    fn orbit_radius_stays_bounded() {
+        // Unit circular orbit around an inverse square attractor at the origin,
+        // semi-implicit Euler keeps the energy error bounded over many periods
+        const STEPS_PER_PERIOD: usize = 1000;
+        let mut body = RigidBodyBuilder::new()
+            .with_position(Vector3::X)
+            .with_linear_velocity(Vector3::Y)
+            .build(Shape::new_sphere(0.1));
+        let dt = 2.0 * std::f32::consts::PI / STEPS_PER_PERIOD as f32;
+        let (mut min_radius, mut max_radius) = (1.0f32, 1.0f32);
+        for _ in 0..100 * STEPS_PER_PERIOD {
+            let radius = body.position.mag();
+            body.apply_force(-body.position / (radius * radius * radius));
+            body.integrate(dt);
+            min_radius = min_radius.min(body.position.mag());
+            max_radius = max_radius.max(body.position.mag());
+        }
+        assert!(
+            min_radius > 0.95 && max_radius < 1.05,
+            "{} {}",
+            min_radius,
+            max_radius
+        );
+    }
+
+    #[test]
+    fn spinning_keeps_orientation_normalized() {
+        let mut body = RigidBody::new(Shape::new_cuboid(Vector3::ONE), Vector3::ZERO, 1.0);
+        body.angular_velocity = Vector3::new(3.0, -7.0, 11.0);
+        for _ in 0..100_000 {
+            body.integrate_position(1.0 / 60.0);
+            assert!(f32::abs(body.orientation.mag() - 1.0) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn builder_sets_every_field() {
+        let body = RigidBodyBuilder::new()
+            .with_position(Vector3::new(1.0, 2.0, 3.0))
+            .with_orientation(Quaternion::new(0.0, 0.0, 0.0, 1.0))
+            .with_mass(4.0)
+            .with_linear_velocity(Vector3::new(-1.0, 0.5, 2.0))
+            .with_angular_velocity(Vector3::new(0.0, 3.0, 0.0))
+            .with_material(7)
+            .with_damping(0.1, 0.2)
+            .with_gravity_scale(0.5)
+            .with_max_velocity(50.0, 5.0)
+            .build(Shape::new_sphere(1.0));
+        assert_eq!(body.position, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(body.orientation, Quaternion::new(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(body.mass(), 4.0);
+        assert_eq!(body.inv_mass, 0.25);
+        assert_eq!(body.linear_velocity, Vector3::new(-1.0, 0.5, 2.0));
+        assert_eq!(body.angular_velocity, Vector3::new(0.0, 3.0, 0.0));
+        assert_eq!(body.force, Vector3::ZERO);
+        assert_eq!(body.material_id, 7);
+        assert_eq!((body.linear_damping, body.angular_damping), (0.1, 0.2));
+        assert_eq!(body.gravity_scale, 0.5);
+        assert_eq!(
+            (body.max_linear_velocity, body.max_angular_velocity),
+            (50.0, 5.0)
+        );
+    }
+
+    #[test]
+    fn builder_defaults_match_new() {
+        let body = RigidBodyBuilder::default().build(Shape::new_sphere(1.0));
+        let expected = RigidBody::new(Shape::new_sphere(1.0), Vector3::ZERO, DEFAULT_BODY_MASS);
+        assert_eq!(format!("{:?}", body), format!("{:?}", expected));
+        assert_eq!(body.orientation, Quaternion::default());
+        assert_eq!(body.mass(), 1.0);
+        assert_eq!(body.linear_velocity, Vector3::ZERO);
+        assert_eq!(body.angular_velocity, Vector3::ZERO);
+        assert_eq!(body.material_id, DEFAULT_MATERIAL_ID);
+        assert_eq!((body.linear_damping, body.angular_damping), (0.0, 0.0));
+        assert_eq!(body.gravity_scale, 1.0);
+        assert_eq!(body.max_linear_velocity, DEFAULT_MAX_LINEAR_VELOCITY);
+        assert_eq!(body.max_angular_velocity, DEFAULT_MAX_ANGULAR_VELOCITY);
+    }
+
+    #[test]
+    fn velocities_are_clamped_and_sanitized() {
+        let mut body = RigidBodyBuilder::new()
+            .with_max_velocity(10.0, 2.0)
+            .build(Shape::new_sphere(1.0));
+        body.linear_velocity = Vector3::new(3e8, 4e8, 0.0);
+        body.angular_velocity = Vector3::new(0.0, 0.0, -1e6);
+        body.limit_velocities();
+        assert!((body.linear_velocity - Vector3::new(6.0, 8.0, 0.0)).mag() < 1e-5);
+        assert!((body.angular_velocity - Vector3::new(0.0, 0.0, -2.0)).mag() < 1e-5);
+
+        body.linear_velocity = Vector3::new(f32::NAN, 1.0, 0.0);
+        body.angular_velocity = Vector3::new(0.0, f32::INFINITY, 0.0);
+        body.integrate(1.0 / 60.0);
+        assert_eq!(body.linear_velocity, Vector3::ZERO);
+        assert_eq!(body.angular_velocity, Vector3::ZERO);
+        assert!(body.position.is_valid());
+        assert_eq!(body.position, Vector3::ZERO);
+    }
+}
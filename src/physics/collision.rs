@@ -0,0 +1,801 @@
+use super::{Capsule, Cuboid, Plane, RigidBody, Shape, Sphere};
+use crate::math::{geom, types::Vector3};
+#[cfg(debug_assertions)]
+use std::cell::RefCell;
+
+const FEATURE_EPSILON: f32 = 1e-5;
+const GJK_MAX_ITERATIONS: usize = 64;
+
+#[cfg(debug_assertions)]
+thread_local! {
+    static GJK_DEBUG_SIMPLEX: RefCell<Vec<Vector3>> = const { RefCell::new(Vec::new()) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Center,
+    Vertex(u8),
+    Edge(u8),
+    Face(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FeatureId {
+    pub a: Feature,
+    pub b: Feature,
+}
+
+/// Contact between bodies `a` and `b` of a narrowphase query. `normal` is a
+/// unit vector pointing from `a` towards `b`, so separating the bodies moves
+/// `b` along it, `depth` is the positive penetration along `normal` and
+/// `point` lies halfway between the two surfaces. The colliding features are
+/// kept as a `FeatureId` pair, `feature_id` packs them into the `u64` key
+/// used for warm start matching.
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub point: Vector3,
+    pub normal: Vector3,
+    pub depth: f32,
+    pub feature: FeatureId,
+}
+
+struct Obb {
+    center: Vector3,
+    axes: [Vector3; 3],
+    half: Vector3,
+}
+
+impl Contact {
+    /// Feature pair packed into a single key, stable across frames while the
+    /// same features stay in contact.
+    #[inline]
+    pub fn feature_id(&self) -> u64 {
+        self.feature.packed()
+    }
+
+    #[inline]
+    pub fn flipped(self) -> Self {
+        Self {
+            normal: -self.normal,
+            feature: FeatureId {
+                a: self.feature.b,
+                b: self.feature.a,
+            },
+            ..self
+        }
+    }
+}
+
+impl Feature {
+    fn packed(self) -> u32 {
+        match self {
+            Feature::Center => 0,
+            Feature::Vertex(index) => 1 << 8 | index as u32,
+            Feature::Edge(index) => 2 << 8 | index as u32,
+            Feature::Face(index) => 3 << 8 | index as u32,
+        }
+    }
+}
+
+impl FeatureId {
+    pub const CENTERS: FeatureId = FeatureId {
+        a: Feature::Center,
+        b: Feature::Center,
+    };
+
+    #[inline]
+    pub fn packed(self) -> u64 {
+        (self.a.packed() as u64) << 32 | self.b.packed() as u64
+    }
+}
+
+impl Obb {
+    fn new(body: &RigidBody, cuboid: &Cuboid) -> Self {
+        let rot = body.orientation;
+        Self {
+            center: body.local_to_world((cuboid.bounds_min + cuboid.bounds_max) / 2.0),
+            axes: [
+                rot.rotate_point(Vector3::X),
+                rot.rotate_point(Vector3::Y),
+                rot.rotate_point(Vector3::Z),
+            ],
+            half: (cuboid.bounds_max - cuboid.bounds_min) / 2.0,
+        }
+    }
+
+    fn radius(&self, axis: Vector3) -> f32 {
+        (0..3).fold(0.0, |r, i| r + self.half[i] * f32::abs(self.axes[i] * axis))
+    }
+
+    fn vertex_feature(&self, dir: Vector3) -> Feature {
+        let index = (0..3).fold(0, |index, i| {
+            index | (((self.axes[i] * dir >= 0.0) as u8) << i)
+        });
+        Feature::Vertex(index)
+    }
+
+    fn support(&self, dir: Vector3) -> Vector3 {
+        (0..3).fold(self.center, |point, i| {
+            let sign = if self.axes[i] * dir >= 0.0 { 1.0 } else { -1.0 };
+            point + self.axes[i] * (self.half[i] * sign)
+        })
+    }
+}
+
+pub fn sphere_sphere(a: &RigidBody, sa: &Sphere, b: &RigidBody, sb: &Sphere) -> Option<Contact> {
+    swept_contact(
+        a.position,
+        sa.radius,
+        b.position,
+        sb.radius,
+        FeatureId::CENTERS,
+    )
+}
+
+pub fn sphere_cuboid(a: &RigidBody, sa: &Sphere, b: &RigidBody, cb: &Cuboid) -> Option<Contact> {
+    let center = b.world_to_local(a.position);
+    let mut closest = center;
+    for i in 0..3 {
+        closest[i] = f32::clamp(center[i], cb.bounds_min[i], cb.bounds_max[i]);
+    }
+    let delta = closest - center;
+    let dist = delta.mag();
+    let (normal, depth, surface, feature) = if dist > f32::EPSILON {
+        (
+            delta / dist,
+            sa.radius - dist,
+            closest,
+            cuboid_feature(cb, closest),
+        )
+    } else {
+        let mut axis = 0;
+        let mut face_dist = f32::MAX;
+        let mut face_sign = 1.0;
+        for i in 0..3 {
+            let to_min = center[i] - cb.bounds_min[i];
+            let to_max = cb.bounds_max[i] - center[i];
+            if to_min < face_dist {
+                axis = i;
+                face_dist = to_min;
+                face_sign = -1.0;
+            }
+            if to_max < face_dist {
+                axis = i;
+                face_dist = to_max;
+                face_sign = 1.0;
+            }
+        }
+        let mut normal = Vector3::default();
+        normal[axis] = -face_sign;
+        let face = 2 * axis as u8 + (face_sign > 0.0) as u8;
+        (
+            normal,
+            sa.radius + face_dist,
+            center - normal * face_dist,
+            Feature::Face(face),
+        )
+    };
+    if depth < 0.0 {
+        return None;
+    }
+    let normal = b.orientation.rotate_point(normal);
+    let surface = b.local_to_world(surface);
+    let sphere_surface = a.position + normal * sa.radius;
+    Some(Contact {
+        point: (surface + sphere_surface) / 2.0,
+        normal,
+        depth,
+        feature: FeatureId {
+            a: Feature::Center,
+            b: feature,
+        },
+    })
+}
+
+pub fn cuboid_cuboid(a: &RigidBody, ca: &Cuboid, b: &RigidBody, cb: &Cuboid) -> Option<Contact> {
+    let obb_a = Obb::new(a, ca);
+    let obb_b = Obb::new(b, cb);
+    let offset = obb_b.center - obb_a.center;
+
+    let mut axes = Vec::with_capacity(15);
+    axes.extend_from_slice(&obb_a.axes);
+    axes.extend_from_slice(&obb_b.axes);
+    for &axis_a in &obb_a.axes {
+        for &axis_b in &obb_b.axes {
+            axes.push(axis_a.cross(axis_b));
+        }
+    }
+
+    let mut depth = f32::MAX;
+    let mut normal = Vector3::Z;
+    let mut best_axis = 0;
+    for (n, axis) in axes.into_iter().enumerate() {
+        if axis.mag_squared() < 1e-6 {
+            continue;
+        }
+        let axis = axis.normalized();
+        let dist = offset * axis;
+        let overlap = obb_a.radius(axis) + obb_b.radius(axis) - f32::abs(dist);
+        if overlap < 0.0 {
+            return None;
+        }
+        let is_face_axis = n < 6;
+        if overlap < depth && (is_face_axis || overlap < depth - 1e-4) {
+            depth = overlap;
+            normal = if dist < 0.0 { -axis } else { axis };
+            best_axis = n;
+        }
+    }
+
+    let face = |obb: &Obb, axis: usize, dir: Vector3| {
+        Feature::Face(2 * axis as u8 + (obb.axes[axis] * dir >= 0.0) as u8)
+    };
+    let edge = |obb: &Obb, axis: usize, dir: Vector3| match obb.vertex_feature(dir) {
+        Feature::Vertex(index) => edge_feature(axis, index),
+        feature => feature,
+    };
+    let feature = match best_axis {
+        n if n < 3 => FeatureId {
+            a: face(&obb_a, n, normal),
+            b: obb_b.vertex_feature(-normal),
+        },
+        n if n < 6 => FeatureId {
+            a: obb_a.vertex_feature(normal),
+            b: face(&obb_b, n - 3, -normal),
+        },
+        n => FeatureId {
+            a: edge(&obb_a, (n - 6) / 3, normal),
+            b: edge(&obb_b, (n - 6) % 3, -normal),
+        },
+    };
+    let deepest = obb_b.support(-normal);
+    Some(Contact {
+        point: deepest + normal * (0.5 * depth),
+        normal,
+        depth,
+        feature,
+    })
+}
+
+fn capsule_segment(body: &RigidBody, capsule: &Capsule) -> (Vector3, Vector3) {
+    let (a, b) = capsule.segment();
+    (body.local_to_world(a), body.local_to_world(b))
+}
+
+/// Edge of a cuboid parallel to `axis`, `corner` holds one bit per axis as
+/// in vertex features and its `axis` bit is ignored. The two remaining bits
+/// select one of the four edges along the axis, giving ids 0..12.
+fn edge_feature(axis: usize, corner: u8) -> Feature {
+    let low = corner & ((1 << axis) - 1);
+    let high = corner >> (axis + 1);
+    Feature::Edge(4 * axis as u8 + (low | high << axis))
+}
+
+fn cuboid_feature(cuboid: &Cuboid, point: Vector3) -> Feature {
+    let mut clamped = 0;
+    let mut index = 0;
+    let mut free_axis = 0;
+    let mut face_axis = 0;
+    for i in 0..3 {
+        let on_min = f32::abs(point[i] - cuboid.bounds_min[i]) <= FEATURE_EPSILON;
+        let on_max = f32::abs(point[i] - cuboid.bounds_max[i]) <= FEATURE_EPSILON;
+        if on_min || on_max {
+            clamped += 1;
+            face_axis = i;
+            index |= (on_max as u8) << i;
+        } else {
+            free_axis = i;
+        }
+    }
+    match clamped {
+        3 => Feature::Vertex(index),
+        2 => edge_feature(free_axis, index),
+        _ => Feature::Face(2 * face_axis as u8 + (index >> face_axis)),
+    }
+}
+
+fn segment_feature(start: Vector3, end: Vector3, point: Vector3) -> Feature {
+    if (point - start).mag_squared() <= FEATURE_EPSILON * FEATURE_EPSILON {
+        Feature::Vertex(0)
+    } else if (point - end).mag_squared() <= FEATURE_EPSILON * FEATURE_EPSILON {
+        Feature::Vertex(1)
+    } else {
+        Feature::Edge(0)
+    }
+}
+
+fn swept_contact(
+    pa: Vector3,
+    ra: f32,
+    pb: Vector3,
+    rb: f32,
+    feature: FeatureId,
+) -> Option<Contact> {
+    let delta = pb - pa;
+    let dist = delta.mag();
+    let depth = ra + rb - dist;
+    if depth < 0.0 {
+        return None;
+    }
+    let normal = if dist > f32::EPSILON {
+        delta / dist
+    } else {
+        Vector3::Z
+    };
+    Some(Contact {
+        point: (pa + normal * ra + pb - normal * rb) / 2.0,
+        normal,
+        depth,
+        feature,
+    })
+}
+
+/// Planes bound the solid half space behind their normal, spheres sinking
+/// below the plane are pushed back out along the plane normal.
+pub fn sphere_plane(a: &RigidBody, sa: &Sphere, b: &RigidBody, pb: &Plane) -> Option<Contact> {
+    let plane_normal = b.orientation.rotate_point(pb.normal);
+    let dist = (a.position - b.position) * plane_normal;
+    let depth = sa.radius - dist;
+    if depth < 0.0 {
+        return None;
+    }
+    let normal = -plane_normal;
+    Some(Contact {
+        point: a.position + normal * (dist + 0.5 * depth),
+        normal,
+        depth,
+        feature: FeatureId {
+            a: Feature::Center,
+            b: Feature::Face(0),
+        },
+    })
+}
+
+pub fn sphere_capsule(a: &RigidBody, sa: &Sphere, b: &RigidBody, cb: &Capsule) -> Option<Contact> {
+    let (start, end) = capsule_segment(b, cb);
+    let closest = geom::closest_point_segment(a.position, start, end);
+    let feature = FeatureId {
+        a: Feature::Center,
+        b: segment_feature(start, end, closest),
+    };
+    swept_contact(a.position, sa.radius, closest, cb.radius, feature)
+}
+
+pub fn capsule_capsule(
+    a: &RigidBody,
+    ca: &Capsule,
+    b: &RigidBody,
+    cb: &Capsule,
+) -> Option<Contact> {
+    let (start_a, end_a) = capsule_segment(a, ca);
+    let (start_b, end_b) = capsule_segment(b, cb);
+    let (closest_a, closest_b, _) = geom::closest_segment_segment(start_a, end_a, start_b, end_b);
+    let feature = FeatureId {
+        a: segment_feature(start_a, end_a, closest_a),
+        b: segment_feature(start_b, end_b, closest_b),
+    };
+    swept_contact(closest_a, ca.radius, closest_b, cb.radius, feature)
+}
+
+fn world_support(body: &RigidBody, dir: Vector3) -> Vector3 {
+    let local = body.orientation.inverse().rotate_point(dir);
+    body.position + body.orientation.rotate_point(body.shape.support(local))
+}
+
+/// Boolean GJK on the Minkowski difference `a - b`, the shapes intersect when
+/// it contains the origin. Touching shapes count as intersecting.
+pub fn gjk_intersect(a: &RigidBody, b: &RigidBody) -> bool {
+    let support = |dir: Vector3| world_support(a, dir) - world_support(b, -dir);
+    let dir = b.position - a.position;
+    let mut dir = if dir.mag_squared() > f32::EPSILON {
+        dir
+    } else {
+        Vector3::X
+    };
+    let mut simplex = vec![support(dir)];
+    dir = -simplex[0];
+    for _ in 0..GJK_MAX_ITERATIONS {
+        #[cfg(debug_assertions)]
+        GJK_DEBUG_SIMPLEX.with(|debug| debug.replace(simplex.clone()));
+        if dir.mag_squared() <= f32::EPSILON * f32::EPSILON {
+            return true;
+        }
+        let point = support(dir);
+        if point * dir < 0.0 {
+            return false;
+        }
+        simplex.push(point);
+        if gjk_update_simplex(&mut simplex, &mut dir) {
+            #[cfg(debug_assertions)]
+            GJK_DEBUG_SIMPLEX.with(|debug| debug.replace(simplex.clone()));
+            return true;
+        }
+    }
+    // No separating direction found, origin sits on the difference boundary
+    true
+}
+
+/// Simplex left by the last `gjk_intersect` call on this thread, containing
+/// the origin when the shapes intersect.
+#[cfg(debug_assertions)]
+pub fn gjk_last_simplex() -> Vec<Vector3> {
+    GJK_DEBUG_SIMPLEX.with(|debug| debug.borrow().clone())
+}
+
+// Reduces the simplex to the feature closest to the origin, newest point
+// last, and points `dir` from that feature towards the origin. Returns true
+// once the simplex encloses the origin.
+fn gjk_update_simplex(simplex: &mut Vec<Vector3>, dir: &mut Vector3) -> bool {
+    let a = simplex[simplex.len() - 1];
+    let ao = -a;
+    match *simplex.as_slice() {
+        [b, _] => {
+            let ab = b - a;
+            if ab * ao > 0.0 {
+                *dir = ab.cross(ao).cross(ab);
+            } else {
+                *simplex = vec![a];
+                *dir = ao;
+            }
+            false
+        }
+        [c, b, _] => {
+            let (ab, ac) = (b - a, c - a);
+            let abc = ab.cross(ac);
+            if abc.cross(ac) * ao > 0.0 {
+                if ac * ao > 0.0 {
+                    *simplex = vec![c, a];
+                    *dir = ac.cross(ao).cross(ac);
+                } else {
+                    *simplex = vec![b, a];
+                    return gjk_update_simplex(simplex, dir);
+                }
+            } else if ab.cross(abc) * ao > 0.0 {
+                *simplex = vec![b, a];
+                return gjk_update_simplex(simplex, dir);
+            } else if abc * ao > 0.0 {
+                *dir = abc;
+            } else {
+                *simplex = vec![b, c, a];
+                *dir = -abc;
+            }
+            false
+        }
+        [d, c, b, _] => {
+            for &(p, q, opposite) in &[(b, c, d), (c, d, b), (d, b, c)] {
+                let normal = (p - a).cross(q - a);
+                let normal = if normal * (opposite - a) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+                if normal * ao > 0.0 {
+                    *simplex = vec![q, p, a];
+                    return gjk_update_simplex(simplex, dir);
+                }
+            }
+            true
+        }
+        _ => unreachable!(),
+    }
+}
+
+pub fn narrowphase(a: &RigidBody, b: &RigidBody) -> Option<Contact> {
+    match (&a.shape, &b.shape) {
+        (Shape::Sphere(sa), Shape::Sphere(sb)) => sphere_sphere(a, sa, b, sb),
+        (Shape::Sphere(sa), Shape::Cuboid(cb)) => sphere_cuboid(a, sa, b, cb),
+        (Shape::Cuboid(ca), Shape::Sphere(sb)) => sphere_cuboid(b, sb, a, ca).map(Contact::flipped),
+        (Shape::Cuboid(ca), Shape::Cuboid(cb)) => cuboid_cuboid(a, ca, b, cb),
+        (Shape::Sphere(sa), Shape::Capsule(cb)) => sphere_capsule(a, sa, b, cb),
+        (Shape::Capsule(ca), Shape::Sphere(sb)) => {
+            sphere_capsule(b, sb, a, ca).map(Contact::flipped)
+        }
+        (Shape::Capsule(ca), Shape::Capsule(cb)) => capsule_capsule(a, ca, b, cb),
+        (Shape::Capsule(_), Shape::Cuboid(_)) | (Shape::Cuboid(_), Shape::Capsule(_)) => None,
+        (Shape::ConvexHull(_), _) | (_, Shape::ConvexHull(_)) => None,
+        (Shape::Cylinder(_), _) | (_, Shape::Cylinder(_)) => None,
+        (Shape::Sphere(sa), Shape::Plane(pb)) => sphere_plane(a, sa, b, pb),
+        (Shape::Plane(pa), Shape::Sphere(sb)) => sphere_plane(b, sb, a, pa).map(Contact::flipped),
+        (Shape::Plane(_), _) | (_, Shape::Plane(_)) => None,
+        (Shape::Torus(_), _) | (_, Shape::Torus(_)) => None,
+    }
+}
+
+pub fn brute_force_contacts(bodies: &[RigidBody]) -> Vec<(usize, usize, Contact)> {
+    let mut contacts = Vec::new();
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            if let Some(contact) = narrowphase(&bodies[i], &bodies[j]) {
+                contacts.push((i, j, contact));
+            }
+        }
+    }
+    contacts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::types::Quaternion,
+        physics::{Aabb, PhysicsWorld, SpatialHash},
+    };
+    use std::collections::HashSet;
+
+    // xorshift32 scene generator, uniform in [min, max)
+    struct Rng(u32);
+
+    impl Rng {
+        fn range(&mut self, min: f32, max: f32) -> f32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            min + (max - min) * (self.0 >> 8) as f32 / (1u32 << 24) as f32
+        }
+
+        fn vector(&mut self, extent: f32) -> Vector3 {
+            Vector3::new(
+                self.range(-extent, extent),
+                self.range(-extent, extent),
+                self.range(-extent, extent),
+            )
+        }
+    }
+
+    fn random_bodies(seed: u32, count: usize, extent: f32) -> Vec<RigidBody> {
+        let mut rng = Rng(seed);
+        (0..count)
+            .map(|index| {
+                let shape = match index % 3 {
+                    0 => Shape::new_sphere(rng.range(0.2, 1.0)),
+                    1 => Shape::new_cuboid(Vector3::new(
+                        rng.range(0.2, 2.0),
+                        rng.range(0.2, 2.0),
+                        rng.range(0.2, 2.0),
+                    )),
+                    _ => Shape::new_capsule(rng.range(0.2, 0.6), rng.range(0.2, 1.0)),
+                };
+                let mut body = RigidBody::new(shape, rng.vector(extent), 1.0);
+                let axis = rng.vector(1.0);
+                if axis.mag_squared() > 1e-3 {
+                    body.orientation = Quaternion::vec_angle(axis, rng.range(0.0, 3.0));
+                }
+                body
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spatial_hash_finds_every_brute_force_contact() {
+        for seed in 1..6 {
+            let mut bodies = random_bodies(seed * 7919, 60, 6.0);
+            // Spans far more cells than the hash tracks individually
+            bodies.push(RigidBody::new(
+                Shape::new_cuboid(Vector3::new(200.0, 1.0, 200.0)),
+                Vector3::ZERO,
+                0.0,
+            ));
+            let expected = brute_force_contacts(&bodies)
+                .into_iter()
+                .map(|(a, b, _)| (a, b))
+                .collect::<Vec<_>>();
+            assert!(!expected.is_empty());
+            let mut hash = SpatialHash::new(2.0);
+            for (index, body) in bodies.iter().enumerate() {
+                hash.insert(index, Aabb::from_body(body));
+            }
+            let found = hash
+                .candidate_pairs()
+                .into_iter()
+                .filter(|&(a, b)| narrowphase(&bodies[a], &bodies[b]).is_some())
+                .collect::<Vec<_>>();
+            assert_eq!(found, expected, "seed {}", seed);
+        }
+    }
+
+    fn signed_volume(a: Vector3, b: Vector3, c: Vector3, d: Vector3) -> f32 {
+        (b - a).cross(c - a) * (d - a)
+    }
+
+    #[test]
+    fn gjk_agrees_with_narrowphase() {
+        let mut compared = 0;
+        for seed in 1..6 {
+            let bodies = random_bodies(seed * 104_729, 40, 3.0);
+            for i in 0..bodies.len() {
+                for j in (i + 1)..bodies.len() {
+                    let (a, b) = (&bodies[i], &bodies[j]);
+                    if matches!(
+                        (&a.shape, &b.shape),
+                        (Shape::Capsule(_), Shape::Cuboid(_))
+                            | (Shape::Cuboid(_), Shape::Capsule(_))
+                    ) {
+                        continue;
+                    }
+                    let contact = narrowphase(a, b);
+                    // Grazing contacts may go either way
+                    if contact
+                        .as_ref()
+                        .map_or(false, |contact| contact.depth < 1e-3)
+                    {
+                        continue;
+                    }
+                    assert_eq!(gjk_intersect(a, b), contact.is_some(), "{:?} {:?}", a, b);
+                    compared += 1;
+                }
+            }
+        }
+        assert!(compared > 1000);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn gjk_simplex_encloses_origin() {
+        let a = RigidBody::new(
+            Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)),
+            Vector3::ZERO,
+            1.0,
+        );
+        let mut b = RigidBody::new(Shape::new_sphere(0.5), Vector3::new(0.6, 0.3, -0.2), 1.0);
+        assert!(gjk_intersect(&a, &b));
+        let simplex = gjk_last_simplex();
+        if let [d, c, b, a] = *simplex.as_slice() {
+            let volumes = [
+                signed_volume(a, b, c, d),
+                signed_volume(Vector3::ZERO, b, c, d),
+                signed_volume(a, Vector3::ZERO, c, d),
+                signed_volume(a, b, Vector3::ZERO, d),
+                signed_volume(a, b, c, Vector3::ZERO),
+            ];
+            assert!(volumes[1..].iter().all(|volume| volume * volumes[0] >= 0.0));
+        } else {
+            panic!("{:?}", simplex);
+        }
+        b.position = Vector3::new(3.0, 0.0, 0.0);
+        assert!(!gjk_intersect(&a, &b));
+        assert!((1..=4).contains(&gjk_last_simplex().len()));
+    }
+
+    fn assert_normal(contact: Option<Contact>, normal: Vector3, depth: f32) {
+        let contact = contact.expect("bodies should touch");
+        assert!((contact.normal - normal).mag() < 1e-5, "{:?}", contact);
+        assert!(f32::abs(contact.depth - depth) < 1e-5, "{:?}", contact);
+    }
+
+    #[test]
+    fn sphere_sphere_normal_points_from_a_to_b() {
+        let a = RigidBody::new(Shape::new_sphere(1.0), Vector3::ZERO, 1.0);
+        let b = RigidBody::new(Shape::new_sphere(0.5), Vector3::new(0.0, 1.2, 0.0), 1.0);
+        assert_normal(narrowphase(&a, &b), Vector3::Y, 0.3);
+        assert_normal(narrowphase(&b, &a), Vector3::NEG_Y, 0.3);
+        let far = RigidBody::new(Shape::new_sphere(0.5), Vector3::new(0.0, 1.6, 0.0), 1.0);
+        assert!(narrowphase(&a, &far).is_none());
+    }
+
+    #[test]
+    fn sphere_plane_normal_points_from_a_to_b() {
+        let plane = RigidBody::new(Shape::new_plane(Vector3::Z), Vector3::ZERO, 0.0);
+        let sphere = RigidBody::new(Shape::new_sphere(1.0), Vector3::new(2.0, 3.0, 0.75), 1.0);
+        // The plane is solid below z = 0, it lies beneath the sphere
+        assert_normal(narrowphase(&sphere, &plane), Vector3::NEG_Z, 0.25);
+        assert_normal(narrowphase(&plane, &sphere), Vector3::Z, 0.25);
+        let above = RigidBody::new(Shape::new_sphere(1.0), Vector3::new(0.0, 0.0, 1.5), 1.0);
+        assert!(narrowphase(&above, &plane).is_none());
+    }
+
+    fn capsule(position: Vector3, along_y: bool) -> RigidBody {
+        let mut body = RigidBody::new(Shape::new_capsule(0.5, 1.0), position, 1.0);
+        if along_y {
+            body.orientation = Quaternion::vec_angle(Vector3::X, std::f32::consts::FRAC_PI_2);
+        }
+        body
+    }
+
+    #[test]
+    fn crossed_capsules_touch_at_segment_closest_points() {
+        let a = capsule(Vector3::ZERO, false);
+        assert_normal(
+            narrowphase(&a, &capsule(Vector3::new(0.8, 0.0, 0.0), true)),
+            Vector3::X,
+            0.2,
+        );
+        assert_normal(
+            narrowphase(&capsule(Vector3::new(0.8, 0.0, 0.0), true), &a),
+            Vector3::NEG_X,
+            0.2,
+        );
+        assert!(narrowphase(&a, &capsule(Vector3::new(1.2, 0.0, 0.0), true)).is_none());
+    }
+
+    #[test]
+    fn parallel_capsules_use_their_gap() {
+        let a = capsule(Vector3::ZERO, false);
+        // Segments overlap over z in [-0.5, 1], the contact lies in that range
+        let b = capsule(Vector3::new(0.0, 0.7, 0.5), false);
+        assert_normal(narrowphase(&a, &b), Vector3::Y, 0.3);
+        let contact = narrowphase(&a, &b).unwrap();
+        assert!((-0.5..=1.0).contains(&contact.point.z));
+        assert!(narrowphase(&a, &capsule(Vector3::new(0.0, 1.1, 0.5), false)).is_none());
+        // End to end along the axis, the caps touch
+        assert_normal(
+            narrowphase(&a, &capsule(Vector3::new(0.0, 0.0, 2.9), false)),
+            Vector3::Z,
+            0.1,
+        );
+        assert!(narrowphase(&a, &capsule(Vector3::new(0.0, 0.0, 3.1), false)).is_none());
+    }
+
+    #[test]
+    fn sphere_capsule_hits_tube_and_caps() {
+        let b = capsule(Vector3::ZERO, false);
+        let sphere = |position| RigidBody::new(Shape::new_sphere(0.5), position, 1.0);
+        assert_normal(
+            narrowphase(&sphere(Vector3::new(0.9, 0.0, 0.3)), &b),
+            Vector3::NEG_X,
+            0.1,
+        );
+        assert_normal(
+            narrowphase(&sphere(Vector3::new(0.0, 0.0, 1.9)), &b),
+            Vector3::NEG_Z,
+            0.1,
+        );
+        assert_normal(
+            narrowphase(&b, &sphere(Vector3::new(0.0, 0.0, 1.9))),
+            Vector3::Z,
+            0.1,
+        );
+        assert!(narrowphase(&sphere(Vector3::new(0.0, 1.1, 0.0)), &b).is_none());
+    }
+
+    #[test]
+    fn edge_ids_are_unique() {
+        let ids: HashSet<_> = (0..3)
+            .flat_map(|axis| {
+                (0..8u8)
+                    .filter(move |corner| corner & (1 << axis) == 0)
+                    .map(move |corner| edge_feature(axis, corner))
+            })
+            .collect();
+        assert_eq!(ids.len(), 12);
+        assert!(ids
+            .iter()
+            .all(|&id| matches!(id, Feature::Edge(index) if index < 12)));
+        // The bit along the edge does not select a different edge
+        assert_eq!(edge_feature(1, 0b111), edge_feature(1, 0b101));
+    }
+
+    #[test]
+    fn resting_box_keeps_feature_ids() {
+        // Cube sitting on top of the ground along the default -z gravity
+        let mut world = PhysicsWorld::new();
+        let ground = world.add_body(RigidBody::new(
+            Shape::new_cuboid(Vector3::new(10.0, 10.0, 1.0)),
+            Vector3::ZERO,
+            0.0,
+        ));
+        let start = Vector3::new(0.0, 0.0, 0.99);
+        let cube = world.add_body(RigidBody::new(
+            Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)),
+            start,
+            1.0,
+        ));
+        let feature = |world: &PhysicsWorld| {
+            narrowphase(world.body(ground).unwrap(), world.body(cube).unwrap())
+                .map(|contact| contact.feature_id())
+        };
+        world.step(1.0 / 60.0);
+        let first = feature(&world);
+        assert!(first.is_some());
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+            let body = world.body(cube).unwrap();
+            assert!(
+                body.linear_velocity.mag() < 0.2,
+                "{:?}",
+                body.linear_velocity
+            );
+            assert!((body.position - start).mag() < 0.01, "{:?}", body.position);
+            assert_eq!(feature(&world), first);
+        }
+    }
+}
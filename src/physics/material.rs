@@ -0,0 +1,154 @@
+pub const DEFAULT_MATERIAL_ID: u32 = 0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhysicsMaterial {
+    pub name: String,
+    pub restitution: f32,
+    pub dynamic_friction: f32,
+    pub static_friction: f32,
+}
+
+/// Material ids index into `materials`, id `DEFAULT_MATERIAL_ID` is always
+/// present and is used for bodies whose id is not in the table.
+#[derive(Debug, Clone)]
+pub struct MaterialTable {
+    materials: Vec<PhysicsMaterial>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CombinedMaterial {
+    pub restitution: f32,
+    pub dynamic_friction: f32,
+    pub static_friction: f32,
+}
+
+impl PhysicsMaterial {
+    pub fn new(name: &str, restitution: f32, dynamic_friction: f32, static_friction: f32) -> Self {
+        Self {
+            name: name.to_owned(),
+            restitution,
+            dynamic_friction,
+            static_friction,
+        }
+    }
+}
+
+impl Default for PhysicsMaterial {
+    fn default() -> Self {
+        Self::new("default", 0.0, 0.0, 0.0)
+    }
+}
+
+impl MaterialTable {
+    pub fn new() -> Self {
+        Self {
+            materials: vec![PhysicsMaterial::default()],
+        }
+    }
+
+    pub fn add(&mut self, material: PhysicsMaterial) -> u32 {
+        self.materials.push(material);
+        (self.materials.len() - 1) as u32
+    }
+
+    #[inline]
+    pub fn get(&self, id: u32) -> Option<&PhysicsMaterial> {
+        self.materials.get(id as usize)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut PhysicsMaterial> {
+        self.materials.get_mut(id as usize)
+    }
+
+    pub fn find(&self, name: &str) -> Option<u32> {
+        self.materials
+            .iter()
+            .position(|material| material.name == name)
+            .map(|id| id as u32)
+    }
+
+    /// Id of a frictionless material with the given restitution, added to
+    /// the table when no such material is present yet.
+    pub fn find_or_add_restitution(&mut self, restitution: f32) -> u32 {
+        let existing = self.materials.iter().position(|material| {
+            material.restitution == restitution
+                && material.dynamic_friction == 0.0
+                && material.static_friction == 0.0
+        });
+        match existing {
+            Some(id) => id as u32,
+            None => self.add(PhysicsMaterial::new(
+                &format!("restitution {}", restitution),
+                restitution,
+                0.0,
+                0.0,
+            )),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Restitution takes the bouncier of the two materials, friction
+    /// coefficients use the geometric mean so a frictionless surface
+    /// stays frictionless against anything.
+    pub fn combine(&self, a: u32, b: u32) -> CombinedMaterial {
+        let default = &self.materials[DEFAULT_MATERIAL_ID as usize];
+        let a = self.get(a).unwrap_or(default);
+        let b = self.get(b).unwrap_or(default);
+        CombinedMaterial {
+            restitution: f32::max(a.restitution, b.restitution),
+            dynamic_friction: f32::sqrt(a.dynamic_friction * b.dynamic_friction),
+            static_friction: f32::sqrt(a.static_friction * b.static_friction),
+        }
+    }
+}
+
+impl Default for MaterialTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_takes_max_restitution_and_mean_friction() {
+        let mut table = MaterialTable::new();
+        let rubber = table.add(PhysicsMaterial::new("rubber", 0.9, 0.8, 1.0));
+        let ice = table.add(PhysicsMaterial::new("ice", 0.1, 0.02, 0.05));
+        let combined = table.combine(rubber, ice);
+        assert_eq!(combined.restitution, 0.9);
+        assert!(f32::abs(combined.dynamic_friction - f32::sqrt(0.8 * 0.02)) < 1e-6);
+        assert!(f32::abs(combined.static_friction - f32::sqrt(0.05)) < 1e-6);
+        // The default material is frictionless against anything
+        assert_eq!(
+            table.combine(rubber, DEFAULT_MATERIAL_ID).static_friction,
+            0.0
+        );
+        // Unknown ids fall back to the default material
+        assert_eq!(
+            table.combine(42, ice),
+            table.combine(DEFAULT_MATERIAL_ID, ice)
+        );
+    }
+
+    #[test]
+    fn restitution_materials_are_shared() {
+        let mut table = MaterialTable::new();
+        assert_eq!(table.find_or_add_restitution(0.0), DEFAULT_MATERIAL_ID);
+        let bouncy = table.find_or_add_restitution(0.75);
+        assert_eq!(table.find_or_add_restitution(0.75), bouncy);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(bouncy).unwrap().restitution, 0.75);
+    }
+}
@@ -0,0 +1,66 @@
+use super::{ConvexHull, Shape};
+use crate::math::types::Vector3;
+use crate::renderer::Mesh;
+use std::f32::consts::PI;
+
+fn sphere_directions(samples: u32) -> impl Iterator<Item = Vector3> {
+    let golden_angle = PI * (3.0 - f32::sqrt(5.0));
+    (0..samples).map(move |i| {
+        let z = 1.0 - 2.0 * (i as f32 + 0.5) / samples as f32;
+        let radius = f32::sqrt(1.0 - z * z);
+        let theta = golden_angle * i as f32;
+        Vector3::new(radius * f32::cos(theta), radius * f32::sin(theta), z)
+    })
+}
+
+// Sums shape_a with the reflection of shape_b, which is the set GJK searches for the origin
+pub fn minkowski_difference(shape_a: &Shape, shape_b: &Shape, samples: u32) -> ConvexHull {
+    let points: Vec<_> = sphere_directions(samples)
+        .map(|dir| shape_a.support(dir) - shape_b.support(-dir))
+        .collect();
+    ConvexHull::new(&points)
+}
+
+/// Boundary of `minkowski_difference` for debug drawing, the configuration
+/// space obstacle contains the origin when the shapes overlap.
+pub fn minkowski_sum_mesh(shape_a: &Shape, shape_b: &Shape, samples: u32) -> Mesh {
+    Mesh::from_convex_hull(&minkowski_difference(shape_a, shape_b, samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn difference_of_spheres_is_a_sphere_of_summed_radius() {
+        let hull = minkowski_difference(&Shape::new_sphere(1.0), &Shape::new_sphere(0.5), 64);
+        assert!(!hull.faces.is_empty());
+        assert!(hull
+            .vertices
+            .iter()
+            .all(|vertex| f32::abs(vertex.mag() - 1.5) < 1e-4));
+    }
+
+    #[test]
+    fn difference_of_boxes_spans_summed_extents() {
+        let a = Shape::new_cuboid(Vector3::new(2.0, 2.0, 2.0));
+        let b = Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0));
+        let hull = minkowski_difference(&a, &b, 64);
+        for i in 0..3 {
+            let max = hull.vertices.iter().map(|v| v[i]).fold(f32::MIN, f32::max);
+            assert!(f32::abs(max - 1.5) < 1e-5);
+        }
+    }
+
+    #[test]
+    fn sum_mesh_of_unit_spheres_has_radius_two() {
+        let sphere = Shape::new_sphere(1.0);
+        let hull = minkowski_difference(&sphere, &sphere, 128);
+        assert!(hull
+            .vertices
+            .iter()
+            .all(|vertex| f32::abs(vertex.mag() - 2.0) < 1e-4));
+        let mesh = minkowski_sum_mesh(&sphere, &sphere, 128);
+        assert_eq!(mesh.triangle_count(), hull.faces.len());
+    }
+}
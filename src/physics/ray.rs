@@ -0,0 +1,22 @@
+use crate::math::types::Vector3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Ray {
+    pub origin: Vector3,
+    pub dir: Vector3,
+}
+
+impl Ray {
+    #[inline]
+    pub fn new(origin: Vector3, dir: Vector3) -> Self {
+        Self {
+            origin,
+            dir: dir.normalized(),
+        }
+    }
+
+    #[inline]
+    pub fn at(&self, t: f32) -> Vector3 {
+        self.origin + self.dir * t
+    }
+}
@@ -1,15 +1,39 @@
-use crate::math::types::Vector3;
+use super::{Aabb, Ray};
+use crate::math::types::{Matrix3, Vector3};
+use std::fmt;
 
+mod capsule;
+mod convex_hull;
 mod cuboid;
+mod cylinder;
+mod plane;
 mod sphere;
+mod torus;
 
+pub use capsule::*;
+pub use convex_hull::*;
 pub use cuboid::*;
+pub use cylinder::*;
+pub use plane::*;
 pub use sphere::*;
+pub use torus::*;
 
-#[derive(Debug, Clone, Copy)]
+// Entry of a ray into a convex interval, clamped to the ray origin
+fn ray_entry(interval: Option<(f32, f32)>) -> Option<f32> {
+    interval
+        .filter(|&(_, exit)| exit >= 0.0)
+        .map(|(entry, _)| f32::max(entry, 0.0))
+}
+
+#[derive(Debug, Clone)]
 pub enum Shape {
     Cuboid(cuboid::Cuboid),
     Sphere(sphere::Sphere),
+    ConvexHull(convex_hull::ConvexHull),
+    Capsule(capsule::Capsule),
+    Cylinder(cylinder::Cylinder),
+    Plane(plane::Plane),
+    Torus(torus::Torus),
 }
 
 impl Shape {
@@ -23,4 +47,261 @@ impl Shape {
     pub fn new_sphere(radius: f32) -> Self {
         Self::Sphere(Sphere { radius })
     }
+
+    pub fn new_capsule(radius: f32, half_height: f32) -> Self {
+        Self::Capsule(Capsule {
+            radius,
+            half_height,
+        })
+    }
+
+    pub fn new_cylinder(radius: f32, half_height: f32) -> Self {
+        Self::Cylinder(Cylinder {
+            radius,
+            half_height,
+        })
+    }
+
+    pub fn new_plane(normal: Vector3) -> Self {
+        Self::Plane(Plane {
+            normal: normal.normalized(),
+        })
+    }
+
+    pub fn new_torus(major_radius: f32, minor_radius: f32) -> Self {
+        Self::Torus(Torus {
+            major_radius,
+            minor_radius,
+        })
+    }
+
+    pub fn new_convex_hull(points: &[Vector3]) -> Self {
+        Self::ConvexHull(ConvexHull::new(points))
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        match self {
+            Shape::Cuboid(cuboid) => Aabb::new(cuboid.bounds_min, cuboid.bounds_max),
+            Shape::Sphere(sphere) => {
+                let extent = Vector3::ONE * sphere.radius;
+                Aabb::new(-extent, extent)
+            }
+            Shape::ConvexHull(hull) => Aabb::from_points(&hull.vertices),
+            Shape::Capsule(capsule) => {
+                let extent = Vector3::new(
+                    capsule.radius,
+                    capsule.radius,
+                    capsule.half_height + capsule.radius,
+                );
+                Aabb::new(-extent, extent)
+            }
+            Shape::Cylinder(cylinder) => {
+                let extent = Vector3::new(cylinder.radius, cylinder.radius, cylinder.half_height);
+                Aabb::new(-extent, extent)
+            }
+            Shape::Plane(plane) => Aabb::from_points(&plane.corners()),
+            Shape::Torus(torus) => {
+                let outer = torus.major_radius + torus.minor_radius;
+                let extent = Vector3::new(outer, outer, torus.minor_radius);
+                Aabb::new(-extent, extent)
+            }
+        }
+    }
+
+    /// Ray parameter where `ray`, given in the local space of the shape,
+    /// first enters it, zero when it starts inside.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        match self {
+            Shape::Cuboid(cuboid) => cuboid.ray_intersection(ray),
+            Shape::Sphere(sphere) => sphere.ray_intersection(ray),
+            Shape::ConvexHull(hull) => hull.ray_intersection(ray),
+            Shape::Capsule(capsule) => capsule.ray_intersection(ray),
+            Shape::Cylinder(cylinder) => cylinder.ray_intersection(ray),
+            Shape::Plane(plane) => plane.ray_intersection(ray),
+            Shape::Torus(torus) => torus.ray_intersection(ray),
+        }
+    }
+
+    pub fn volume(&self) -> f32 {
+        match self {
+            Shape::Cuboid(cuboid) => cuboid.volume(),
+            Shape::Sphere(sphere) => sphere.volume(),
+            Shape::ConvexHull(hull) => hull.volume(),
+            Shape::Capsule(capsule) => capsule.volume(),
+            Shape::Cylinder(cylinder) => cylinder.volume(),
+            Shape::Plane(plane) => plane.volume(),
+            Shape::Torus(torus) => torus.volume(),
+        }
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        match self {
+            Shape::Cuboid(cuboid) => cuboid.surface_area(),
+            Shape::Sphere(sphere) => sphere.surface_area(),
+            Shape::ConvexHull(hull) => hull.surface_area(),
+            Shape::Capsule(capsule) => capsule.surface_area(),
+            Shape::Cylinder(cylinder) => cylinder.surface_area(),
+            Shape::Plane(plane) => plane.surface_area(),
+            Shape::Torus(torus) => torus.surface_area(),
+        }
+    }
+
+    pub fn inertia_tensor(&self, mass: f32) -> Matrix3 {
+        let diagonal = |x: f32, y: f32, z: f32| {
+            Matrix3::new(Vector3::X * x, Vector3::Y * y, Vector3::Z * z) * mass
+        };
+        match self {
+            Shape::Cuboid(cuboid) => {
+                let size = cuboid.bounds_max - cuboid.bounds_min;
+                let (xx, yy, zz) = (size.x * size.x, size.y * size.y, size.z * size.z);
+                diagonal(yy + zz, xx + zz, xx + yy) * (1.0 / 12.0)
+            }
+            Shape::Sphere(sphere) => {
+                let i = 0.4 * sphere.radius * sphere.radius;
+                diagonal(i, i, i)
+            }
+            Shape::ConvexHull(hull) => hull.inertia_tensor(mass),
+            Shape::Capsule(capsule) => {
+                let (r, h) = (capsule.radius, 2.0 * capsule.half_height);
+                let cylinder_volume = h;
+                let caps_volume = 4.0 / 3.0 * r;
+                let cylinder = cylinder_volume / (cylinder_volume + caps_volume);
+                let caps = 1.0 - cylinder;
+                let axial = cylinder * 0.5 * r * r + caps * 0.4 * r * r;
+                let lateral = cylinder * (h * h / 12.0 + r * r / 4.0)
+                    + caps * (0.4 * r * r + h * h / 4.0 + 3.0 * h * r / 8.0);
+                diagonal(lateral, lateral, axial)
+            }
+            Shape::Cylinder(cylinder) => {
+                let (r, h) = (cylinder.radius, 2.0 * cylinder.half_height);
+                let lateral = (3.0 * r * r + h * h) / 12.0;
+                diagonal(lateral, lateral, 0.5 * r * r)
+            }
+            Shape::Plane(_) => Matrix3::ZERO,
+            Shape::Torus(torus) => {
+                let (rr, tt) = (
+                    torus.major_radius * torus.major_radius,
+                    torus.minor_radius * torus.minor_radius,
+                );
+                let lateral = 0.5 * rr + 0.625 * tt;
+                diagonal(lateral, lateral, rr + 0.75 * tt)
+            }
+        }
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        match self {
+            Shape::Cuboid(cuboid) => cuboid.support(dir),
+            Shape::Sphere(sphere) => sphere.support(dir),
+            Shape::ConvexHull(hull) => hull.support(dir),
+            Shape::Capsule(capsule) => capsule.support(dir),
+            Shape::Cylinder(cylinder) => cylinder.support(dir),
+            Shape::Plane(plane) => plane.support(dir),
+            Shape::Torus(torus) => torus.support(dir),
+        }
+    }
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Shape::Cuboid(cuboid) => fmt::Display::fmt(cuboid, f),
+            Shape::Sphere(sphere) => fmt::Display::fmt(sphere, f),
+            Shape::ConvexHull(hull) => fmt::Display::fmt(hull, f),
+            Shape::Capsule(capsule) => fmt::Display::fmt(capsule, f),
+            Shape::Cylinder(cylinder) => fmt::Display::fmt(cylinder, f),
+            Shape::Plane(plane) => fmt::Display::fmt(plane, f),
+            Shape::Torus(torus) => fmt::Display::fmt(torus, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::RigidBody;
+    use std::f32::consts::PI;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            f32::abs(actual - expected) <= 1e-4 * expected,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn sphere_and_box_measures() {
+        let r = 1.5;
+        let sphere = Shape::new_sphere(r);
+        assert_close(sphere.volume(), 4.0 * PI * r * r * r / 3.0);
+        assert_close(sphere.surface_area(), 4.0 * PI * r * r);
+        let cuboid = Shape::new_cuboid(Vector3::new(1.0, 2.0, 3.0));
+        assert_close(cuboid.volume(), 6.0);
+        assert_close(cuboid.surface_area(), 22.0);
+    }
+
+    #[test]
+    fn round_shape_measures() {
+        let (r, h) = (0.5, 2.0);
+        let cylinder = Shape::new_cylinder(r, h / 2.0);
+        assert_close(cylinder.volume(), PI * r * r * h);
+        assert_close(cylinder.surface_area(), 2.0 * PI * r * (r + h));
+        let capsule = Shape::new_capsule(r, h / 2.0);
+        assert_close(
+            capsule.volume(),
+            PI * r * r * h + 4.0 * PI * r * r * r / 3.0,
+        );
+        assert_close(capsule.surface_area(), 2.0 * PI * r * h + 4.0 * PI * r * r);
+        let torus = Shape::new_torus(2.0, r);
+        assert_close(torus.volume(), 2.0 * PI * PI * 2.0 * r * r);
+        assert_close(torus.surface_area(), 4.0 * PI * PI * 2.0 * r);
+    }
+
+    #[test]
+    fn hull_of_box_corners_measures_like_the_box() {
+        let corners = (0..8)
+            .map(|corner| {
+                Vector3::new(
+                    (corner & 1) as f32,
+                    2.0 * (corner >> 1 & 1) as f32,
+                    3.0 * (corner >> 2 & 1) as f32,
+                )
+            })
+            .collect::<Vec<_>>();
+        let hull = Shape::new_convex_hull(&corners);
+        assert_close(hull.volume(), 6.0);
+        assert_close(hull.surface_area(), 22.0);
+    }
+
+    #[test]
+    fn density_gives_mass_from_volume() {
+        let body = RigidBody::from_shape_and_density(
+            Shape::new_cuboid(Vector3::new(1.0, 2.0, 3.0)),
+            500.0,
+        );
+        assert_close(body.mass(), 3000.0);
+        let plane = RigidBody::from_shape_and_density(Shape::new_plane(Vector3::Z), 500.0);
+        assert_eq!(plane.inv_mass, 0.0);
+    }
+
+    #[test]
+    fn display_is_compact() {
+        let hull = Shape::new_convex_hull(&[Vector3::ZERO, Vector3::X, Vector3::Y, Vector3::Z]);
+        for (shape, expected) in [
+            (Shape::new_sphere(1.0), "Sphere(r=1.00)"),
+            (
+                Shape::new_cuboid(Vector3::new(2.0, 1.0, 3.0)),
+                "Cuboid(2.00×1.00×3.00)",
+            ),
+            (Shape::new_capsule(0.5, 1.0), "Capsule(r=0.50, h=2.00)"),
+            (Shape::new_cylinder(0.25, 0.75), "Cylinder(r=0.25, h=1.50)"),
+            (Shape::new_plane(Vector3::Z), "Plane(n=(0.00, 0.00, 1.00))"),
+            (Shape::new_torus(2.0, 0.5), "Torus(R=2.00, r=0.50)"),
+            (hull, "ConvexHull(vertices=4, faces=4)"),
+        ] {
+            assert_eq!(shape.to_string(), expected);
+        }
+    }
 }
@@ -0,0 +1,75 @@
+use super::{Cylinder, Sphere};
+use crate::{math::types::Vector3, physics::Ray};
+use std::{f32::consts::PI, fmt};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Capsule {
+    pub radius: f32,
+    pub half_height: f32,
+}
+
+impl Capsule {
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        let r = self.radius;
+        PI * r * r * (2.0 * self.half_height + 4.0 / 3.0 * r)
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        2.0 * PI * self.radius * (2.0 * self.half_height + 2.0 * self.radius)
+    }
+
+    #[inline]
+    pub fn segment(&self) -> (Vector3, Vector3) {
+        (
+            Vector3::NEG_Z * self.half_height,
+            Vector3::Z * self.half_height,
+        )
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        let tip = if dir.z >= 0.0 {
+            Vector3::Z * self.half_height
+        } else {
+            Vector3::NEG_Z * self.half_height
+        };
+        if dir.mag_squared() > 0.0 {
+            tip + dir.normalized() * self.radius
+        } else {
+            tip
+        }
+    }
+
+    /// The capsule is the convex union of its tube and end caps, so the ray
+    /// enters it where it first enters any of them.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        let (a, b) = self.segment();
+        let cap = Sphere {
+            radius: self.radius,
+        };
+        let tube = Cylinder {
+            radius: self.radius,
+            half_height: self.half_height,
+        };
+        [
+            tube.ray_interval(ray),
+            cap.ray_interval(a, ray),
+            cap.ray_interval(b, ray),
+        ]
+        .iter()
+        .filter_map(|&interval| super::ray_entry(interval))
+        .min_by(|a, b| a.partial_cmp(b).unwrap())
+    }
+}
+
+impl fmt::Display for Capsule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Capsule(r={:.2}, h={:.2})",
+            self.radius,
+            2.0 * self.half_height
+        )
+    }
+}
@@ -0,0 +1,327 @@
+use crate::{
+    math::types::{Matrix3, Vector3},
+    physics::Ray,
+};
+use std::{
+    collections::{HashSet, VecDeque},
+    f32::consts::{FRAC_PI_2, PI},
+    fmt,
+};
+
+const HULL_EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone)]
+pub struct ConvexHull {
+    pub vertices: Vec<Vector3>,
+    pub faces: Vec<[u32; 3]>,
+}
+
+impl ConvexHull {
+    /// Hull of `points` by gift wrapping, it has no faces when they are
+    /// coplanar, fewer than four or not all finite.
+    pub fn new(points: &[Vector3]) -> Self {
+        let mut unique: Vec<Vector3> = Vec::with_capacity(points.len());
+        for &point in points {
+            if !unique.contains(&point) {
+                unique.push(point);
+            }
+        }
+        match ConvexHull::gift_wrap(&unique) {
+            Some(faces) => ConvexHull::compact(&unique, &faces),
+            None => Self {
+                vertices: unique,
+                faces: vec![],
+            },
+        }
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        self.vertices
+            .iter()
+            .copied()
+            .fold((f32::MIN, Vector3::default()), |best, vertex| {
+                let dist = vertex * dir;
+                if dist > best.0 {
+                    (dist, vertex)
+                } else {
+                    best
+                }
+            })
+            .1
+    }
+
+    fn face_vertices(&self, face: &[u32; 3]) -> [Vector3; 3] {
+        [
+            self.vertices[face[0] as usize],
+            self.vertices[face[1] as usize],
+            self.vertices[face[2] as usize],
+        ]
+    }
+
+    /// Clips the ray against the plane of every face, normals are oriented
+    /// away from the vertex centroid so the face winding does not matter.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        if self.faces.is_empty() {
+            return None;
+        }
+        let centroid = self
+            .vertices
+            .iter()
+            .fold(Vector3::ZERO, |sum, &vertex| sum + vertex)
+            / self.vertices.len() as f32;
+        let (mut entry, mut exit) = (0.0f32, f32::MAX);
+        for face in &self.faces {
+            let [a, b, c] = self.face_vertices(face);
+            let mut normal = (b - a).cross(c - a);
+            if normal * (a - centroid) < 0.0 {
+                normal = -normal;
+            }
+            let denom = normal * ray.dir;
+            let dist = normal * (a - ray.origin);
+            if f32::abs(denom) < HULL_EPSILON {
+                if dist < 0.0 {
+                    return None;
+                }
+                continue;
+            }
+            let t = dist / denom;
+            if denom < 0.0 {
+                entry = f32::max(entry, t);
+            } else {
+                exit = f32::min(exit, t);
+            }
+            if entry > exit {
+                return None;
+            }
+        }
+        Some(entry)
+    }
+
+    pub fn volume(&self) -> f32 {
+        let volume = self.faces.iter().fold(0.0, |volume, face| {
+            let [a, b, c] = self.face_vertices(face);
+            volume + a * b.cross(c) / 6.0
+        });
+        f32::abs(volume)
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        self.faces.iter().fold(0.0, |area, face| {
+            let [a, b, c] = self.face_vertices(face);
+            area + 0.5 * (b - a).cross(c - a).mag()
+        })
+    }
+
+    pub fn inertia_tensor(&self, mass: f32) -> Matrix3 {
+        let outer = |v: Vector3| Matrix3::new(v * v.x, v * v.y, v * v.z);
+        let (volume, covariance) =
+            self.faces
+                .iter()
+                .fold((0.0, Matrix3::ZERO), |(volume, covariance), face| {
+                    let [a, b, c] = self.face_vertices(face);
+                    let det = a * b.cross(c);
+                    let products = outer(a) + outer(b) + outer(c) + outer(a + b + c);
+                    (volume + det / 6.0, covariance + products * (det / 120.0))
+                });
+        if f32::abs(volume) < HULL_EPSILON {
+            return Matrix3::ZERO;
+        }
+        (Matrix3::IDENTITY * covariance.trace() - covariance) * (mass / volume)
+    }
+
+    fn pivot(
+        points: &[Vector3],
+        origin: usize,
+        edge: Vector3,
+        start: Vector3,
+        inward: Vector3,
+    ) -> Option<usize> {
+        let mut best: Option<(usize, f32, f32)> = None;
+        for (index, &point) in points.iter().enumerate() {
+            let offset = point - points[origin];
+            let perp = offset - edge * (offset * edge);
+            let dist = perp.mag();
+            if dist < HULL_EPSILON {
+                continue;
+            }
+            let angle = match f32::atan2(perp * inward, perp * start) {
+                angle if angle < -FRAC_PI_2 => angle + 2.0 * PI,
+                angle => angle,
+            };
+            best = match best {
+                Some((_, best_angle, best_dist))
+                    if angle > best_angle + HULL_EPSILON
+                        || (f32::abs(angle - best_angle) <= HULL_EPSILON && dist <= best_dist) =>
+                {
+                    best
+                }
+                _ => Some((index, angle, dist)),
+            };
+        }
+        best.map(|(index, _, _)| index)
+    }
+
+    fn gift_wrap(points: &[Vector3]) -> Option<Vec<[usize; 3]>> {
+        if points.len() < 4 || !points.iter().all(|point| point.is_valid()) {
+            return None;
+        }
+        let centroid = points
+            .iter()
+            .fold(Vector3::default(), |sum, &point| sum + point)
+            / points.len() as f32;
+
+        let a = (0..points.len()).min_by(|&i, &j| {
+            let (p, q) = (points[i], points[j]);
+            (p.x, p.y, p.z).partial_cmp(&(q.x, q.y, q.z)).unwrap()
+        })?;
+        let b = ConvexHull::pivot(points, a, Vector3::Z, Vector3::Y, Vector3::X)?;
+        let edge = (points[b] - points[a]).normalized();
+        let inward = {
+            let normal = edge.cross(Vector3::Z);
+            if normal.mag_squared() > HULL_EPSILON && normal * (centroid - points[a]) < 0.0 {
+                -normal.normalized()
+            } else if normal.mag_squared() > HULL_EPSILON {
+                normal.normalized()
+            } else {
+                Vector3::X
+            }
+        };
+        let c = ConvexHull::pivot(points, a, edge, edge.cross(inward), inward)?;
+
+        let normal = (points[b] - points[a]).cross(points[c] - points[a]);
+        let is_flat = points
+            .iter()
+            .all(|&point| f32::abs((point - points[a]) * normal.normalized()) < HULL_EPSILON);
+        if normal.mag_squared() < HULL_EPSILON || is_flat {
+            return None;
+        }
+        let first = if normal * (centroid - points[a]) > 0.0 {
+            [a, c, b]
+        } else {
+            [a, b, c]
+        };
+
+        let mut faces = vec![first];
+        let mut edges = HashSet::new();
+        let mut open = VecDeque::new();
+        for i in 0..3 {
+            let edge = (first[i], first[(i + 1) % 3], first[(i + 2) % 3]);
+            edges.insert((edge.0, edge.1));
+            open.push_back(edge);
+        }
+
+        let max_faces = 2 * points.len() * points.len();
+        while let Some((u, v, w)) = open.pop_front() {
+            if edges.contains(&(v, u)) {
+                continue;
+            }
+            if faces.len() > max_faces {
+                return None;
+            }
+            let edge = (points[v] - points[u]).normalized();
+            let outward = edge.cross(points[w] - points[u]).normalized();
+            let toward_face = points[w] - points[u];
+            let start = -(toward_face - edge * (toward_face * edge)).normalized();
+            let p = ConvexHull::pivot(points, u, edge, start, -outward)?;
+
+            let face = [v, u, p];
+            if edges.contains(&(u, p)) || edges.contains(&(p, v)) {
+                continue;
+            }
+            for i in 0..3 {
+                let edge = (face[i], face[(i + 1) % 3], face[(i + 2) % 3]);
+                edges.insert((edge.0, edge.1));
+                open.push_back(edge);
+            }
+            faces.push(face);
+        }
+        Some(faces)
+    }
+
+    fn compact(points: &[Vector3], faces: &[[usize; 3]]) -> Self {
+        let mut remap = vec![None; points.len()];
+        let mut vertices = Vec::new();
+        let faces = faces
+            .iter()
+            .map(|face| {
+                let mut mapped = [0u32; 3];
+                for (dst, &src) in mapped.iter_mut().zip(face.iter()) {
+                    *dst = *remap[src].get_or_insert_with(|| {
+                        vertices.push(points[src]);
+                        (vertices.len() - 1) as u32
+                    });
+                }
+                mapped
+            })
+            .collect();
+        Self { vertices, faces }
+    }
+}
+
+impl fmt::Display for ConvexHull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ConvexHull(vertices={}, faces={})",
+            self.vertices.len(),
+            self.faces.len()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cube_with_interior() -> (ConvexHull, Vec<Vector3>) {
+        let corners = (0..8)
+            .map(|corner| {
+                let coord = |bit: u32| if corner & bit == 0 { -1.0 } else { 1.0 };
+                Vector3::new(coord(1), coord(2), coord(4))
+            })
+            .collect::<Vec<_>>();
+        let mut points = corners.clone();
+        points.extend([
+            Vector3::ZERO,
+            Vector3::new(0.5, -0.5, 0.9),
+            Vector3::new(1.0, 0.0, 0.0),
+        ]);
+        (ConvexHull::new(&points), corners)
+    }
+
+    #[test]
+    fn hull_drops_interior_points() {
+        let (hull, corners) = cube_with_interior();
+        assert_eq!(hull.vertices.len(), 8);
+        assert!(corners.iter().all(|corner| hull.vertices.contains(corner)));
+        assert!(f32::abs(hull.volume() - 8.0) < 1e-4);
+    }
+
+    #[test]
+    fn support_returns_extremal_vertex() {
+        let (hull, _) = cube_with_interior();
+        for dir in [
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(-1.0, 2.0, 0.5),
+            Vector3::new(0.3, -0.2, -4.0),
+            Vector3::new(-1.0, -1.0, -1.0),
+        ] {
+            let expected = Vector3::new(dir.x.signum(), dir.y.signum(), dir.z.signum());
+            assert_eq!(hull.support(dir), expected, "{:?}", dir);
+            assert!(hull
+                .vertices
+                .iter()
+                .all(|&vertex| vertex * dir <= hull.support(dir) * dir));
+        }
+    }
+
+    #[test]
+    fn non_finite_points_give_no_faces() {
+        let (_, mut points) = cube_with_interior();
+        points.push(Vector3::new(f32::NAN, 0.0, 0.0));
+        assert!(ConvexHull::new(&points).faces.is_empty());
+        points.pop();
+        points.push(Vector3::new(0.0, f32::INFINITY, 0.0));
+        assert!(ConvexHull::new(&points).faces.is_empty());
+    }
+}
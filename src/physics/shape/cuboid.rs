@@ -1,7 +1,47 @@
-use crate::math::types::Vector3;
+use crate::{
+    math::types::Vector3,
+    physics::{Aabb, Ray},
+};
+use std::fmt;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Cuboid {
     pub bounds_min: Vector3,
     pub bounds_max: Vector3,
 }
+
+impl Cuboid {
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        let size = self.bounds_max - self.bounds_min;
+        size.x * size.y * size.z
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        let size = self.bounds_max - self.bounds_min;
+        2.0 * (size.x * size.y + size.y * size.z + size.z * size.x)
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        let mut point = self.bounds_min;
+        for i in 0..3 {
+            if dir[i] > 0.0 {
+                point[i] = self.bounds_max[i];
+            }
+        }
+        point
+    }
+
+    #[inline]
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        Aabb::new(self.bounds_min, self.bounds_max).ray_intersection(ray)
+    }
+}
+
+impl fmt::Display for Cuboid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = self.bounds_max - self.bounds_min;
+        write!(f, "Cuboid({:.2}×{:.2}×{:.2})", size.x, size.y, size.z)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{math::types::Vector3, physics::Ray};
+use std::{f32::consts::PI, fmt};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Cylinder {
+    pub radius: f32,
+    pub half_height: f32,
+}
+
+impl Cylinder {
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        PI * self.radius * self.radius * 2.0 * self.half_height
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        2.0 * PI * self.radius * (self.radius + 2.0 * self.half_height)
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        let cap = if dir.z >= 0.0 {
+            Vector3::Z * self.half_height
+        } else {
+            Vector3::NEG_Z * self.half_height
+        };
+        let radial = Vector3::new(dir.x, dir.y, 0.0);
+        if radial.mag_squared() > 0.0 {
+            cap + radial.normalized() * self.radius
+        } else {
+            cap
+        }
+    }
+
+    /// Entry and exit parameters of the line through `ray` with the capped
+    /// cylinder, the entry may lie behind the ray origin.
+    pub fn ray_interval(&self, ray: &Ray) -> Option<(f32, f32)> {
+        let (mut entry, mut exit) = (f32::MIN, f32::MAX);
+        // Slab between the caps
+        if f32::abs(ray.dir.z) < f32::EPSILON {
+            if f32::abs(ray.origin.z) > self.half_height {
+                return None;
+            }
+        } else {
+            let t0 = (-self.half_height - ray.origin.z) / ray.dir.z;
+            let t1 = (self.half_height - ray.origin.z) / ray.dir.z;
+            entry = f32::min(t0, t1);
+            exit = f32::max(t0, t1);
+        }
+        // Infinite tube around the Z axis
+        let origin = Vector3::new(ray.origin.x, ray.origin.y, 0.0);
+        let dir = Vector3::new(ray.dir.x, ray.dir.y, 0.0);
+        let c = origin * origin - self.radius * self.radius;
+        let a = dir * dir;
+        if a < f32::EPSILON {
+            if c > 0.0 {
+                return None;
+            }
+        } else {
+            let b = origin * dir;
+            let discriminant = b * b - a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let root = discriminant.sqrt();
+            entry = f32::max(entry, (-b - root) / a);
+            exit = f32::min(exit, (-b + root) / a);
+        }
+        if entry > exit {
+            return None;
+        }
+        Some((entry, exit))
+    }
+
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        super::ray_entry(self.ray_interval(ray))
+    }
+}
+
+impl fmt::Display for Cylinder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cylinder(r={:.2}, h={:.2})",
+            self.radius,
+            2.0 * self.half_height
+        )
+    }
+}
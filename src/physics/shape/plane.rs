@@ -0,0 +1,59 @@
+use crate::{math::types::Vector3, physics::Ray};
+use std::fmt;
+
+pub const PLANE_HALF_EXTENT: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Plane {
+    pub normal: Vector3,
+}
+
+impl Plane {
+    /// Planes are infinitely thin, so they have no volume and are treated as static
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        0.0
+    }
+
+    /// Area of the finite quad spanned by [`Plane::corners`]
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        (2.0 * PLANE_HALF_EXTENT).powi(2)
+    }
+
+    pub fn corners(&self) -> [Vector3; 4] {
+        let (_, u, v) = self.normal.ortho();
+        let (u, v) = (u * PLANE_HALF_EXTENT, v * PLANE_HALF_EXTENT);
+        [-u - v, u - v, u + v, v - u]
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        let (_, u, v) = self.normal.ortho();
+        let sign = |axis: Vector3| if axis * dir >= 0.0 { 1.0 } else { -1.0 };
+        (u * sign(u) + v * sign(v)) * PLANE_HALF_EXTENT
+    }
+
+    /// Hit with the finite quad spanned by [`Plane::corners`], from either side.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        let denom = self.normal * ray.dir;
+        if f32::abs(denom) < f32::EPSILON {
+            return None;
+        }
+        let t = -(self.normal * ray.origin) / denom;
+        let (_, u, v) = self.normal.ortho();
+        let point = ray.at(t);
+        let inside =
+            f32::abs(point * u) <= PLANE_HALF_EXTENT && f32::abs(point * v) <= PLANE_HALF_EXTENT;
+        Some(t).filter(|&t| t >= 0.0 && inside)
+    }
+}
+
+impl fmt::Display for Plane {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Plane(n=({:.2}, {:.2}, {:.2}))",
+            self.normal.x, self.normal.y, self.normal.z
+        )
+    }
+}
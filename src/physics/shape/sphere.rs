@@ -1,4 +1,52 @@
+use crate::{math::types::Vector3, physics::Ray};
+use std::{f32::consts::PI, fmt};
+
 #[derive(Debug, Clone, Copy)]
 pub struct Sphere {
     pub radius: f32,
 }
+
+impl Sphere {
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        4.0 / 3.0 * PI * self.radius.powi(3)
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        if dir.mag_squared() > 0.0 {
+            dir.normalized() * self.radius
+        } else {
+            Vector3::default()
+        }
+    }
+
+    /// Entry and exit parameters of the line through `ray` with the sphere
+    /// centered at `center`, the entry may lie behind the ray origin.
+    pub fn ray_interval(&self, center: Vector3, ray: &Ray) -> Option<(f32, f32)> {
+        let offset = ray.origin - center;
+        let a = ray.dir * ray.dir;
+        let b = offset * ray.dir;
+        let c = offset * offset - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+        if a <= 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        Some(((-b - root) / a, (-b + root) / a))
+    }
+
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        super::ray_entry(self.ray_interval(Vector3::ZERO, ray))
+    }
+}
+
+impl fmt::Display for Sphere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sphere(r={:.2})", self.radius)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    math::types::{Vector2, Vector3},
+    physics::{Aabb, Ray},
+};
+use std::{f32::consts::PI, fmt};
+
+const TRACE_MAX_STEPS: usize = 128;
+const TRACE_EPSILON: f32 = 1e-4;
+
+/// Ring around the local Z axis, `major_radius` is measured from the origin
+/// to the center of the tube and `minor_radius` is the tube radius.
+#[derive(Debug, Clone, Copy)]
+pub struct Torus {
+    pub major_radius: f32,
+    pub minor_radius: f32,
+}
+
+impl Torus {
+    #[inline]
+    pub fn volume(&self) -> f32 {
+        2.0 * PI * PI * self.major_radius * self.minor_radius * self.minor_radius
+    }
+
+    #[inline]
+    pub fn surface_area(&self) -> f32 {
+        4.0 * PI * PI * self.major_radius * self.minor_radius
+    }
+
+    /// Support point of the torus convex hull, collision queries treat the
+    /// hole as filled.
+    pub fn support(&self, dir: Vector3) -> Vector3 {
+        let radial = Vector3::new(dir.x, dir.y, 0.0);
+        let ring = if radial.mag_squared() > 0.0 {
+            radial.normalized() * self.major_radius
+        } else {
+            Vector3::ZERO
+        };
+        if dir.mag_squared() > 0.0 {
+            ring + dir.normalized() * self.minor_radius
+        } else {
+            ring
+        }
+    }
+
+    fn distance(&self, point: Vector3) -> f32 {
+        let ring = Vector2::new(point.x, point.y).mag() - self.major_radius;
+        Vector2::new(ring, point.z).mag() - self.minor_radius
+    }
+
+    /// Sphere traces the signed distance of the tube within its bounding box,
+    /// unlike collision queries the hole stays open.
+    pub fn ray_intersection(&self, ray: &Ray) -> Option<f32> {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vector3::new(outer, outer, self.minor_radius);
+        let mut t = Aabb::new(-extent, extent).ray_intersection(ray)?;
+        let speed = ray.dir.mag();
+        if speed <= 0.0 {
+            return None;
+        }
+        let epsilon = TRACE_EPSILON * self.minor_radius;
+        for _ in 0..TRACE_MAX_STEPS {
+            let point = ray.at(t);
+            if (0..3).any(|i| f32::abs(point[i]) > extent[i] + epsilon) {
+                return None;
+            }
+            let distance = self.distance(point);
+            if distance < epsilon {
+                return Some(t);
+            }
+            t += distance / speed;
+        }
+        None
+    }
+}
+
+impl fmt::Display for Torus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Torus(R={:.2}, r={:.2})",
+            self.major_radius, self.minor_radius
+        )
+    }
+}
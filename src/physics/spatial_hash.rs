@@ -0,0 +1,120 @@
+use super::Aabb;
+use std::collections::HashMap;
+
+const MAX_CELLS_PER_AABB: f64 = 1024.0;
+
+#[derive(Debug, Clone)]
+pub struct SpatialHash {
+    cell_size: f32,
+    table: HashMap<(i32, i32, i32), Vec<usize>>,
+    bodies: Vec<usize>,
+    oversized: Vec<usize>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            table: HashMap::new(),
+            bodies: vec![],
+            oversized: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    fn cell(&self, coord: f32) -> i32 {
+        f32::floor(coord / self.cell_size) as i32
+    }
+
+    fn is_oversized(&self, aabb: &Aabb) -> bool {
+        let cells: f64 = (0..3)
+            .map(|i| f64::from(self.cell(aabb.max[i])) - f64::from(self.cell(aabb.min[i])) + 1.0)
+            .product();
+        cells > MAX_CELLS_PER_AABB
+    }
+
+    fn cells(&self, aabb: &Aabb) -> impl Iterator<Item = (i32, i32, i32)> {
+        let (min_x, min_y, min_z) = (
+            self.cell(aabb.min.x),
+            self.cell(aabb.min.y),
+            self.cell(aabb.min.z),
+        );
+        let (max_x, max_y, max_z) = (
+            self.cell(aabb.max.x),
+            self.cell(aabb.max.y),
+            self.cell(aabb.max.z),
+        );
+        (min_x..=max_x).flat_map(move |x| {
+            (min_y..=max_y).flat_map(move |y| (min_z..=max_z).map(move |z| (x, y, z)))
+        })
+    }
+
+    pub fn insert(&mut self, body_index: usize, aabb: Aabb) {
+        self.bodies.push(body_index);
+        if self.is_oversized(&aabb) {
+            self.oversized.push(body_index);
+            return;
+        }
+        for cell in self.cells(&aabb).collect::<Vec<_>>() {
+            self.table.entry(cell).or_default().push(body_index);
+        }
+    }
+
+    pub fn remove(&mut self, body_index: usize) {
+        self.bodies.retain(|&index| index != body_index);
+        self.oversized.retain(|&index| index != body_index);
+        self.table.retain(|_, bodies| {
+            bodies.retain(|&index| index != body_index);
+            !bodies.is_empty()
+        });
+    }
+
+    pub fn query(&self, aabb: &Aabb) -> Vec<usize> {
+        let mut bodies: Vec<_> = if self.is_oversized(aabb) {
+            self.bodies.clone()
+        } else {
+            self.cells(aabb)
+                .filter_map(|cell| self.table.get(&cell))
+                .flatten()
+                .chain(&self.oversized)
+                .copied()
+                .collect()
+        };
+        bodies.sort_unstable();
+        bodies.dedup();
+        bodies
+    }
+
+    pub fn candidate_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for bodies in self.table.values() {
+            for (n, &a) in bodies.iter().enumerate() {
+                for &b in &bodies[n + 1..] {
+                    if a != b {
+                        pairs.push((usize::min(a, b), usize::max(a, b)));
+                    }
+                }
+            }
+        }
+        for &a in &self.oversized {
+            for &b in &self.bodies {
+                if a != b {
+                    pairs.push((usize::min(a, b), usize::max(a, b)));
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    pub fn clear(&mut self) {
+        self.table.clear();
+        self.bodies.clear();
+        self.oversized.clear();
+    }
+}
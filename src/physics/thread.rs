@@ -0,0 +1,152 @@
+use super::PhysicsWorld;
+use crate::{log, utils::StaticResult};
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const MAX_STEP_LAG: Duration = Duration::from_millis(250);
+
+pub struct PhysicsThread {
+    world: Arc<Mutex<PhysicsWorld>>,
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    fixed_dt: f32,
+}
+
+impl PhysicsThread {
+    /// Fails for a `fixed_dt` that is not a positive, finite number of seconds.
+    pub fn spawn(world: PhysicsWorld, fixed_dt: f32) -> StaticResult<PhysicsThread> {
+        if !(fixed_dt.is_finite() && fixed_dt > 0.0) {
+            Err(format!(
+                "Physics thread time step of {} seconds is not positive and finite",
+                fixed_dt
+            ))?;
+        }
+        let world = Arc::new(Mutex::new(world));
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = {
+            let world = Arc::clone(&world);
+            let running = Arc::clone(&running);
+            thread::spawn(move || PhysicsThread::run(&world, &running, fixed_dt))
+        };
+        Ok(PhysicsThread {
+            world,
+            running,
+            handle: Some(handle),
+            fixed_dt,
+        })
+    }
+
+    fn run(world: &Mutex<PhysicsWorld>, running: &AtomicBool, fixed_dt: f32) {
+        let step = Duration::from_secs_f32(fixed_dt);
+        let mut next_step = Instant::now();
+        while running.load(Ordering::Acquire) {
+            match world.lock() {
+                Ok(mut world) => world.step(fixed_dt),
+                Err(_) => {
+                    log::error("Physics world lock poisoned, stopping physics thread");
+                    break;
+                }
+            }
+            next_step += step;
+            let now = Instant::now();
+            if next_step > now {
+                thread::sleep(next_step - now);
+            } else if now - next_step > MAX_STEP_LAG {
+                log::warning("Physics thread running behind wall-clock time, skipping ahead");
+                next_step = now;
+            }
+        }
+    }
+
+    #[inline]
+    pub fn fixed_dt(&self) -> f32 {
+        self.fixed_dt
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some() && self.running.load(Ordering::Acquire)
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, PhysicsWorld> {
+        self.world.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn stop(mut self) -> PhysicsWorld {
+        self.join();
+        let world = Arc::clone(&self.world);
+        drop(self);
+        match Arc::try_unwrap(world) {
+            Ok(world) => world.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(_) => unreachable!("Physics thread joined while world is still shared"),
+        }
+    }
+
+    fn join(&mut self) {
+        self.running.store(false, Ordering::Release);
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                log::error("Physics thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for PhysicsThread {
+    fn drop(&mut self) {
+        self.join();
+    }
+}
+
+impl fmt::Debug for PhysicsThread {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhysicsThread")
+            .field("fixed_dt", &self.fixed_dt)
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::types::Vector3,
+        physics::{RigidBody, Shape},
+    };
+
+    #[test]
+    fn invalid_time_steps_are_rejected() {
+        for fixed_dt in [0.0, -1.0 / 60.0, f32::NAN, f32::INFINITY] {
+            assert!(PhysicsThread::spawn(PhysicsWorld::new(), fixed_dt).is_err());
+        }
+    }
+
+    #[test]
+    fn world_steps_while_main_thread_sleeps() {
+        let mut world = PhysicsWorld::new();
+        let sphere = world.add_body(RigidBody::new(
+            Shape::new_sphere(0.5),
+            Vector3::new(0.0, 0.0, 10.0),
+            1.0,
+        ));
+        let physics = PhysicsThread::spawn(world, 1.0 / 240.0).unwrap();
+        assert!(physics.is_running());
+        thread::sleep(Duration::from_millis(100));
+        let steps = {
+            let world = physics.lock();
+            assert!(world.body(sphere).unwrap().position.z < 10.0);
+            world.step_count()
+        };
+        assert!(steps > 0);
+        let world = physics.stop();
+        assert!(world.step_count() >= steps);
+        assert!(world.body(sphere).unwrap().linear_velocity.z < 0.0);
+    }
+}
@@ -0,0 +1,219 @@
+use super::{Aabb, Ray, Shape};
+use crate::{math::types::Matrix4, scene::ShapeHandle};
+
+#[derive(Debug, Clone, Copy)]
+enum AabbNode {
+    Leaf(Aabb, ShapeHandle),
+    Branch(Aabb, usize, usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AabbTree {
+    nodes: Vec<AabbNode>,
+    // Leaf shapes with their inverse world transforms, indexed by handle
+    shapes: Vec<(Shape, Matrix4)>,
+}
+
+impl AabbNode {
+    #[inline]
+    fn aabb(&self) -> &Aabb {
+        match self {
+            AabbNode::Leaf(aabb, _) | AabbNode::Branch(aabb, _, _) => aabb,
+        }
+    }
+}
+
+impl AabbTree {
+    pub fn build(shapes: &[(Shape, Matrix4)]) -> AabbTree {
+        let mut items: Vec<_> = shapes
+            .iter()
+            .enumerate()
+            .map(|(index, (shape, world))| (Aabb::from_shape(shape, world), ShapeHandle { index }))
+            .collect();
+        let mut tree = AabbTree {
+            nodes: Vec::with_capacity(2 * items.len()),
+            shapes: shapes
+                .iter()
+                .map(|(shape, world)| (shape.clone(), world.inv()))
+                .collect(),
+        };
+        if !items.is_empty() {
+            tree.build_node(&mut items);
+        }
+        tree
+    }
+
+    fn build_node(&mut self, items: &mut [(Aabb, ShapeHandle)]) -> usize {
+        if let [(aabb, shape)] = items {
+            self.nodes.push(AabbNode::Leaf(*aabb, *shape));
+            return self.nodes.len() - 1;
+        }
+        let bounds = items[1..]
+            .iter()
+            .fold(items[0].0, |bounds, (aabb, _)| bounds.union(aabb));
+        let centers = Aabb::from_points(
+            &items
+                .iter()
+                .map(|(aabb, _)| aabb.center())
+                .collect::<Vec<_>>(),
+        );
+        let extent = centers.extent();
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].partial_cmp(&extent[b]).unwrap())
+            .unwrap();
+        let mid = items.len() / 2;
+        items.select_nth_unstable_by(mid, |(a, _), (b, _)| {
+            a.center()[axis].partial_cmp(&b.center()[axis]).unwrap()
+        });
+
+        let index = self.nodes.len();
+        self.nodes.push(AabbNode::Branch(bounds, 0, 0));
+        let (left_items, right_items) = items.split_at_mut(mid);
+        let left = self.build_node(left_items);
+        let right = self.build_node(right_items);
+        self.nodes[index] = AabbNode::Branch(bounds, left, right);
+        index
+    }
+
+    /// Shapes hit by `ray` with the ray parameter of their exact entry point,
+    /// ordered from the nearest one. Bounding boxes only cull the candidates.
+    pub fn query_ray(&self, ray: &Ray) -> Vec<(ShapeHandle, f32)> {
+        let mut hits = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.aabb().ray_intersection(ray).is_none() {
+                continue;
+            }
+            match *node {
+                AabbNode::Leaf(_, handle) => {
+                    let (shape, world_inv) = &self.shapes[handle.index];
+                    // Affine maps keep the ray parameter of every point
+                    let local = Ray {
+                        origin: world_inv.transform_point(ray.origin),
+                        dir: world_inv.transform_direction(ray.dir),
+                    };
+                    if let Some(t) = shape.ray_intersection(&local) {
+                        hits.push((handle, t));
+                    }
+                }
+                AabbNode::Branch(_, left, right) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+        hits.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+        hits
+    }
+
+    pub fn query_aabb(&self, query: &Aabb) -> Vec<ShapeHandle> {
+        let mut hits = Vec::new();
+        let mut stack = Vec::new();
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.aabb().overlaps(query) {
+                continue;
+            }
+            match *node {
+                AabbNode::Leaf(_, shape) => hits.push(shape),
+                AabbNode::Branch(_, left, right) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{
+        transforms::{scale, translate},
+        types::Vector3,
+    };
+
+    fn assert_hit(shape: Shape, t: f32) {
+        let tree = AabbTree::build(&[(shape.clone(), Matrix4::IDENTITY)]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let hits = tree.query_ray(&ray);
+        assert_eq!(hits.len(), 1, "{:?} missed", shape);
+        assert!(
+            f32::abs(hits[0].1 - t) < 1e-3,
+            "{:?} hit at {}",
+            shape,
+            hits[0].1
+        );
+    }
+
+    #[test]
+    fn reports_exact_entry_of_every_shape() {
+        assert_hit(Shape::new_cuboid(Vector3::new(2.0, 2.0, 2.0)), 4.0);
+        assert_hit(Shape::new_sphere(1.0), 4.0);
+        assert_hit(Shape::new_capsule(0.5, 1.0), 3.5);
+        assert_hit(Shape::new_cylinder(1.0, 2.0), 3.0);
+        assert_hit(Shape::new_plane(Vector3::new(0.0, 0.0, 1.0)), 5.0);
+        assert_hit(
+            Shape::new_convex_hull(&[
+                Vector3::new(-1.0, -1.0, -1.0),
+                Vector3::new(1.0, -1.0, -1.0),
+                Vector3::new(0.0, 1.0, -1.0),
+                Vector3::new(0.0, 0.0, 1.0),
+            ]),
+            4.0,
+        );
+    }
+
+    #[test]
+    fn ray_through_torus_hole_misses() {
+        let tree = AabbTree::build(&[(Shape::new_torus(2.0, 0.5), Matrix4::IDENTITY)]);
+        let down = Vector3::new(0.0, 0.0, -1.0);
+        assert!(tree
+            .query_ray(&Ray::new(Vector3::new(0.0, 0.0, 5.0), down))
+            .is_empty());
+        let hits = tree.query_ray(&Ray::new(Vector3::new(2.0, 0.0, 5.0), down));
+        assert_eq!(hits.len(), 1);
+        assert!(f32::abs(hits[0].1 - 4.5) < 1e-3);
+    }
+
+    #[test]
+    fn ray_clipping_sphere_bounds_misses_the_sphere() {
+        let shapes = (0..3)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .map(|(x, y)| {
+                (
+                    Shape::new_sphere(1.0),
+                    translate(Vector3::new(4.0 * x as f32, 4.0 * y as f32, 0.0)),
+                )
+            })
+            .collect::<Vec<_>>();
+        let tree = AabbTree::build(&shapes);
+        // Passes through the corners of the boxes around the spheres
+        let corner = Vector3::new(0.95, 0.95, 5.0);
+        let down = Vector3::new(0.0, 0.0, -1.0);
+        assert!(tree.query_ray(&Ray::new(corner, down)).is_empty());
+
+        let hits = tree.query_ray(&Ray::new(Vector3::new(8.0, 4.0, 5.0), down));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].0, ShapeHandle { index: 7 });
+        assert!(f32::abs(hits[0].1 - 4.0) < 1e-4);
+    }
+
+    #[test]
+    fn hit_parameter_is_in_world_space() {
+        let world = translate(Vector3::new(0.0, 0.0, -2.0)) * scale(2.0);
+        let tree = AabbTree::build(&[(Shape::new_sphere(1.0), world)]);
+        let ray = Ray::new(Vector3::new(0.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let hits = tree.query_ray(&ray);
+        assert_eq!(hits.len(), 1);
+        assert!(f32::abs(hits[0].1 - 5.0) < 1e-4);
+    }
+}
@@ -0,0 +1,831 @@
+use super::{
+    collision::{self, Contact},
+    Aabb, MaterialTable, RigidBody, SpatialHash,
+};
+#[cfg(feature = "profile")]
+use crate::utils::profiler;
+use crate::{math::types::Vector3, utils::static_assert_send_sync};
+use std::fmt;
+
+const DEFAULT_GRAVITY: Vector3 = Vector3::new(0.0, 0.0, -9.81);
+const BROADPHASE_CELL_SIZE: f32 = 2.0;
+const PENETRATION_SLOP: f32 = 0.005;
+const DEPENETRATION_RATE: f32 = 0.2;
+const MAX_DEPENETRATION: f32 = 0.05;
+const DEFAULT_RESTITUTION_THRESHOLD: f32 = 1.0;
+const DEFAULT_REST_VELOCITY: f32 = 0.01;
+const DEFAULT_SOLVER_ITERATIONS: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BodyHandle {
+    index: usize,
+    generation: u32,
+}
+
+/// Candidate pair reported by the broadphase, `contact` tells whether the
+/// narrowphase confirmed it.
+#[derive(Debug, Clone, Copy)]
+pub struct BroadphasePair {
+    pub a: BodyHandle,
+    pub b: BodyHandle,
+    pub aabb_a: Aabb,
+    pub aabb_b: Aabb,
+    pub contact: bool,
+}
+
+#[derive(Debug, Clone)]
+struct Slot {
+    generation: u32,
+    body: Option<RigidBody>,
+}
+
+/// `Send + Sync`: it can be stepped on a background thread, and shared
+/// behind a lock for queries while no step is running.
+#[derive(Debug, Clone)]
+pub struct PhysicsWorld {
+    slots: Vec<Slot>,
+    free: Vec<usize>,
+    body_count: usize,
+    gravity: Vector3,
+    restitution_threshold: f32,
+    rest_velocity: f32,
+    solver_iterations: u32,
+    materials: MaterialTable,
+    broadphase: SpatialHash,
+    // Set when bodies may have moved since the broadphase was rebuilt
+    broadphase_stale: bool,
+    pairs: Vec<BroadphasePair>,
+    elapsed_time: f64,
+    step_count: u64,
+}
+
+static_assert_send_sync!(PhysicsWorld, BodyHandle);
+
+impl BodyHandle {
+    #[inline]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free: vec![],
+            body_count: 0,
+            gravity: DEFAULT_GRAVITY,
+            restitution_threshold: DEFAULT_RESTITUTION_THRESHOLD,
+            rest_velocity: DEFAULT_REST_VELOCITY,
+            solver_iterations: DEFAULT_SOLVER_ITERATIONS,
+            materials: MaterialTable::new(),
+            broadphase: SpatialHash::new(BROADPHASE_CELL_SIZE),
+            broadphase_stale: false,
+            pairs: vec![],
+            elapsed_time: 0.0,
+            step_count: 0,
+        }
+    }
+
+    #[inline]
+    pub fn gravity(&self) -> Vector3 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vector3) {
+        self.gravity = gravity;
+    }
+
+    #[inline]
+    pub fn restitution_threshold(&self) -> f32 {
+        self.restitution_threshold
+    }
+
+    pub fn set_restitution_threshold(&mut self, threshold: f32) {
+        self.restitution_threshold = threshold;
+    }
+
+    #[inline]
+    pub fn rest_velocity(&self) -> f32 {
+        self.rest_velocity
+    }
+
+    pub fn set_rest_velocity(&mut self, velocity: f32) {
+        self.rest_velocity = velocity;
+    }
+
+    #[inline]
+    pub fn solver_iterations(&self) -> u32 {
+        self.solver_iterations
+    }
+
+    /// Number of passes of the velocity solver over the contacts of a step,
+    /// more passes let impulses propagate through stacks at a linear cost.
+    pub fn set_solver_iterations(&mut self, iterations: u32) {
+        self.solver_iterations = iterations.max(1);
+    }
+
+    #[inline]
+    pub fn materials(&self) -> &MaterialTable {
+        &self.materials
+    }
+
+    pub fn materials_mut(&mut self) -> &mut MaterialTable {
+        &mut self.materials
+    }
+
+    #[inline]
+    pub fn elapsed_time(&self) -> f32 {
+        self.elapsed_time as f32
+    }
+
+    #[inline]
+    pub fn step_count(&self) -> u64 {
+        self.step_count
+    }
+
+    #[inline]
+    pub fn body_count(&self) -> usize {
+        self.body_count
+    }
+
+    /// Dynamic bodies whose linear and angular speeds are below
+    /// `rest_velocity`, they are still simulated like any other body.
+    pub fn sleeping_count(&self) -> usize {
+        let rest_velocity_squared = self.rest_velocity * self.rest_velocity;
+        self.bodies()
+            .filter(|(_, body)| {
+                body.inv_mass > 0.0
+                    && body.linear_velocity.mag_squared() < rest_velocity_squared
+                    && body.angular_velocity.mag_squared() < rest_velocity_squared
+            })
+            .count()
+    }
+
+    pub fn add_body(&mut self, body: RigidBody) -> BodyHandle {
+        self.body_count += 1;
+        let aabb = Aabb::from_body(&body);
+        let handle = match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.body = Some(body);
+                BodyHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    body: Some(body),
+                });
+                BodyHandle {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        };
+        self.broadphase.insert(handle.index, aabb);
+        handle
+    }
+
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<RigidBody> {
+        let slot = self.slots.get_mut(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+        let body = slot.body.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.body_count -= 1;
+        self.broadphase.remove(handle.index);
+        Some(body)
+    }
+
+    pub fn clear(&mut self) {
+        self.free.clear();
+        for (index, slot) in self.slots.iter_mut().enumerate().rev() {
+            slot.body = None;
+            slot.generation = slot.generation.wrapping_add(1);
+            self.free.push(index);
+        }
+        self.body_count = 0;
+        self.broadphase.clear();
+        self.broadphase_stale = false;
+        self.pairs.clear();
+        self.elapsed_time = 0.0;
+        self.step_count = 0;
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&RigidBody> {
+        self.slots
+            .get(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?
+            .body
+            .as_ref()
+    }
+
+    /// Queries scan every body until the next `step` rebuilds the broadphase,
+    /// the body may be moved through the returned reference.
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut RigidBody> {
+        self.broadphase_stale = true;
+        self.slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)?
+            .body
+            .as_mut()
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &RigidBody)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let handle = BodyHandle {
+                index,
+                generation: slot.generation,
+            };
+            slot.body.as_ref().map(|body| (handle, body))
+        })
+    }
+
+    pub fn bodies_mut(&mut self) -> impl Iterator<Item = (BodyHandle, &mut RigidBody)> {
+        self.broadphase_stale = true;
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let handle = BodyHandle {
+                    index,
+                    generation: slot.generation,
+                };
+                slot.body.as_mut().map(|body| (handle, body))
+            })
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        #[cfg(feature = "profile")]
+        let _scope = profiler::Profiler::begin_scope("PhysicsWorld::step");
+        self.integrate_velocities(dt);
+        self.update_broadphase();
+        self.solve_velocities();
+        self.integrate_positions(dt);
+        self.update_broadphase();
+        if self.depenetrate() {
+            self.update_broadphase();
+        }
+        self.elapsed_time += dt as f64;
+        self.step_count += 1;
+    }
+
+    /// Runs only the integration phase of `step`, skipping collision
+    /// detection and response. The broadphase is left untouched until the
+    /// next full `step`, which makes this suitable for profiling the
+    /// integrator in isolation. Queries scan every body until then.
+    pub fn integrate_only(&mut self, dt: f32) {
+        #[cfg(feature = "profile")]
+        let _scope = profiler::Profiler::begin_scope("PhysicsWorld::integrate_only");
+        self.integrate_velocities(dt);
+        self.integrate_positions(dt);
+        self.broadphase_stale = true;
+        self.elapsed_time += dt as f64;
+        self.step_count += 1;
+    }
+
+    fn live_bodies_mut(&mut self) -> impl Iterator<Item = &mut RigidBody> {
+        self.slots.iter_mut().filter_map(|slot| slot.body.as_mut())
+    }
+
+    fn integrate_velocities(&mut self, dt: f32) {
+        let gravity = self.gravity;
+        for body in self.live_bodies_mut() {
+            if body.inv_mass > 0.0 {
+                body.apply_force(gravity * (body.mass() * body.gravity_scale));
+            }
+            body.integrate_velocity(dt);
+        }
+    }
+
+    fn integrate_positions(&mut self, dt: f32) {
+        for body in self.live_bodies_mut() {
+            body.integrate_position(dt);
+        }
+    }
+
+    fn body_pair_mut(&mut self, a: usize, b: usize) -> Option<(&mut RigidBody, &mut RigidBody)> {
+        let (head, tail) = self.slots.split_at_mut(b);
+        match (&mut head[a].body, &mut tail[0].body) {
+            (Some(body_a), Some(body_b)) => Some((body_a, body_b)),
+            _ => None,
+        }
+    }
+
+    /// Runs the narrowphase over the broadphase candidates, recording every
+    /// candidate for `broadphase_pairs`.
+    fn contacts(&mut self) -> Vec<(usize, usize, Contact)> {
+        let mut pairs = Vec::new();
+        let mut contacts = Vec::new();
+        for (a, b) in self.broadphase.candidate_pairs() {
+            let (slot_a, slot_b) = (&self.slots[a], &self.slots[b]);
+            let (body_a, body_b) = match (&slot_a.body, &slot_b.body) {
+                (Some(body_a), Some(body_b)) => (body_a, body_b),
+                _ => continue,
+            };
+            let contact = collision::narrowphase(body_a, body_b);
+            pairs.push(BroadphasePair {
+                a: BodyHandle {
+                    index: a,
+                    generation: slot_a.generation,
+                },
+                b: BodyHandle {
+                    index: b,
+                    generation: slot_b.generation,
+                },
+                aabb_a: Aabb::from_body(body_a),
+                aabb_b: Aabb::from_body(body_b),
+                contact: contact.is_some(),
+            });
+            contacts.extend(contact.map(|contact| (a, b, contact)));
+        }
+        self.pairs = pairs;
+        contacts
+    }
+
+    /// Candidate pairs of the broadphase checked by the narrowphase during
+    /// the last `step`, the pairs without `contact` are its false positives.
+    #[inline]
+    pub fn broadphase_pairs(&self) -> &[BroadphasePair] {
+        &self.pairs
+    }
+
+    fn solve_velocities(&mut self) {
+        let (restitution_threshold, rest_velocity) =
+            (self.restitution_threshold, self.rest_velocity);
+        let contacts = self.contacts();
+        let materials = std::mem::take(&mut self.materials);
+        for _ in 0..self.solver_iterations {
+            for &(a, b, contact) in &contacts {
+                let (body_a, body_b) = match self.body_pair_mut(a, b) {
+                    Some(pair) => pair,
+                    None => continue,
+                };
+                let inv_mass = body_a.inv_mass + body_b.inv_mass;
+                let relative_velocity = body_b.linear_velocity - body_a.linear_velocity;
+                let normal_velocity = relative_velocity * contact.normal;
+                if inv_mass <= 0.0 || normal_velocity >= 0.0 {
+                    continue;
+                }
+                let material = materials.combine(body_a.material_id, body_b.material_id);
+                let restitution = if -normal_velocity < restitution_threshold {
+                    0.0
+                } else {
+                    material.restitution
+                };
+                let normal_impulse = -(1.0 + restitution) * normal_velocity / inv_mass;
+                let impulse = contact.normal * normal_impulse;
+                body_a.linear_velocity = body_a.linear_velocity - impulse * body_a.inv_mass;
+                body_b.linear_velocity = body_b.linear_velocity + impulse * body_b.inv_mass;
+
+                // Coulomb friction, sticking while the impulse needed to stop sliding
+                // stays inside the static cone and sliding with dynamic friction otherwise
+                let tangent_velocity = relative_velocity - contact.normal * normal_velocity;
+                let tangent_speed = tangent_velocity.mag();
+                if tangent_speed > f32::EPSILON {
+                    let stop_impulse = tangent_speed / inv_mass;
+                    let friction_impulse =
+                        if stop_impulse <= material.static_friction * normal_impulse {
+                            stop_impulse
+                        } else {
+                            f32::min(stop_impulse, material.dynamic_friction * normal_impulse)
+                        };
+                    let impulse = tangent_velocity * (friction_impulse / tangent_speed);
+                    body_a.linear_velocity = body_a.linear_velocity + impulse * body_a.inv_mass;
+                    body_b.linear_velocity = body_b.linear_velocity - impulse * body_b.inv_mass;
+                }
+                for body in [body_a, body_b] {
+                    if body.linear_velocity.mag_squared() < rest_velocity * rest_velocity {
+                        body.linear_velocity = Vector3::ZERO;
+                    }
+                }
+            }
+        }
+        self.materials = materials;
+    }
+
+    fn depenetrate(&mut self) -> bool {
+        let mut moved = false;
+        for (a, b, contact) in self.contacts() {
+            let (body_a, body_b) = match self.body_pair_mut(a, b) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let inv_mass = body_a.inv_mass + body_b.inv_mass;
+            let depth = contact.depth - PENETRATION_SLOP;
+            if inv_mass <= 0.0 || depth <= 0.0 {
+                continue;
+            }
+            let correction = contact.normal
+                * (f32::min(depth * DEPENETRATION_RATE, MAX_DEPENETRATION) / inv_mass);
+            body_a.position = body_a.position - correction * body_a.inv_mass;
+            body_b.position = body_b.position + correction * body_b.inv_mass;
+            moved = true;
+        }
+        moved
+    }
+
+    fn update_broadphase(&mut self) {
+        self.broadphase_stale = false;
+        self.broadphase.clear();
+        for (index, slot) in self.slots.iter().enumerate() {
+            if let Some(body) = &slot.body {
+                self.broadphase.insert(index, Aabb::from_body(body));
+            }
+        }
+    }
+
+    fn query(&self, bounds: &Aabb, test: impl Fn(&Aabb) -> bool) -> Vec<BodyHandle> {
+        let candidates = if self.broadphase_stale {
+            (0..self.slots.len()).collect()
+        } else {
+            self.broadphase.query(bounds)
+        };
+        candidates
+            .into_iter()
+            .filter_map(|index| {
+                let slot = &self.slots[index];
+                let body = slot.body.as_ref()?;
+                test(&Aabb::from_body(body)).then_some(BodyHandle {
+                    index,
+                    generation: slot.generation,
+                })
+            })
+            .collect()
+    }
+
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<BodyHandle> {
+        self.query(aabb, |body_aabb| body_aabb.overlaps(aabb))
+    }
+
+    pub fn query_sphere(&self, center: Vector3, radius: f32) -> Vec<BodyHandle> {
+        let extent = Vector3::new(radius, radius, radius);
+        self.query(&Aabb::new(center - extent, center + extent), |body_aabb| {
+            (body_aabb.closest_point(center) - center).mag_squared() <= radius * radius
+        })
+    }
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for PhysicsWorld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "PhysicsWorld(bodies={}, sleeping={}, steps={}, elapsed={:.3}s)",
+            self.body_count,
+            self.sleeping_count(),
+            self.step_count,
+            self.elapsed_time
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{RigidBodyBuilder, Shape};
+
+    fn unit_box(position: Vector3) -> RigidBody {
+        RigidBody::new(
+            Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)),
+            position,
+            1.0,
+        )
+    }
+
+    fn around(center: Vector3) -> Aabb {
+        let extent = Vector3::new(0.1, 0.1, 0.1);
+        Aabb::new(center - extent, center + extent)
+    }
+
+    #[test]
+    fn removed_body_leaves_the_broadphase() {
+        let mut world = PhysicsWorld::new();
+        let origin = Vector3::ZERO;
+        let first = world.add_body(unit_box(origin));
+        world.remove_body(first);
+        assert!(world.query_aabb(&around(origin)).is_empty());
+
+        // The freed slot is reused by a body elsewhere
+        let far = Vector3::new(10.0, 0.0, 0.0);
+        let second = world.add_body(unit_box(far));
+        assert_eq!(second.index(), first.index());
+        // The old handle does not reach the new occupant of its slot
+        assert!(world.body(first).is_none());
+        assert!(world.body_mut(first).is_none());
+        assert!(world.remove_body(first).is_none());
+        assert_eq!(world.body(second).unwrap().position, far);
+        assert!(world.query_aabb(&around(origin)).is_empty());
+        assert_eq!(world.query_aabb(&around(far)), vec![second]);
+        assert!(world.broadphase_pairs().is_empty());
+    }
+
+    #[test]
+    fn step_records_checked_pairs() {
+        let mut world = PhysicsWorld::new();
+        world.set_gravity(Vector3::ZERO);
+        let a = world.add_body(unit_box(Vector3::ZERO));
+        let b = world.add_body(unit_box(Vector3::new(0.5, 0.0, 0.0)));
+        // Bounding boxes share a broadphase cell without touching
+        let c = world.add_body(unit_box(Vector3::new(0.0, 1.5, 0.0)));
+        world.step(1.0 / 60.0);
+        let mut pairs = world
+            .broadphase_pairs()
+            .iter()
+            .map(|pair| (pair.a, pair.b, pair.contact))
+            .collect::<Vec<_>>();
+        pairs.sort_by_key(|&(a, b, _)| (a.index(), b.index()));
+        assert_eq!(pairs, vec![(a, b, true), (a, c, false), (b, c, false)]);
+    }
+
+    #[test]
+    fn queries_see_bodies_moved_through_body_mut() {
+        let mut world = PhysicsWorld::new();
+        let handle = world.add_body(unit_box(Vector3::ZERO));
+        let target = Vector3::new(0.0, 20.0, 0.0);
+        world.body_mut(handle).unwrap().position = target;
+        assert!(world.query_aabb(&around(Vector3::ZERO)).is_empty());
+        assert_eq!(world.query_sphere(target, 0.1), vec![handle]);
+    }
+
+    #[test]
+    fn overlapping_spawns_separate_without_velocity() {
+        let mut world = PhysicsWorld::new();
+        world.set_gravity(Vector3::ZERO);
+        let sphere = |x| RigidBody::new(Shape::new_sphere(1.0), Vector3::new(x, 0.0, 0.0), 1.0);
+        let a = world.add_body(sphere(0.0));
+        let b = world.add_body(sphere(0.5));
+        let distance = |world: &PhysicsWorld| {
+            (world.body(b).unwrap().position - world.body(a).unwrap().position).mag()
+        };
+        let mut previous = distance(&world);
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+            let current = distance(&world);
+            // Pushed apart a little every step, never overshooting
+            assert!(current >= previous);
+            assert!(current - previous <= MAX_DEPENETRATION + 1e-6);
+            assert!(current <= 2.0);
+            previous = current;
+            for handle in [a, b] {
+                assert!(world.body(handle).unwrap().linear_velocity.mag() < 1e-6);
+            }
+        }
+        assert!(previous >= 2.0 - 2.0 * PENETRATION_SLOP);
+    }
+
+    fn ground() -> RigidBody {
+        RigidBody::new(
+            Shape::new_cuboid(Vector3::new(20.0, 20.0, 1.0)),
+            Vector3::ZERO,
+            0.0,
+        )
+    }
+
+    #[test]
+    fn bouncing_sphere_comes_to_rest() {
+        let mut world = PhysicsWorld::new();
+        world.add_body(ground());
+        let mut sphere = RigidBody::new(Shape::new_sphere(0.5), Vector3::new(0.0, 0.0, 2.0), 1.0);
+        sphere.material_id = world.materials_mut().find_or_add_restitution(0.3);
+        let sphere = world.add_body(sphere);
+        let mut bounced = false;
+        for _ in 0..300 {
+            world.step(1.0 / 60.0);
+            bounced |= world.body(sphere).unwrap().linear_velocity.z > 0.0;
+        }
+        assert!(bounced);
+        // Resting, not oscillating below the restitution threshold
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+            let body = world.body(sphere).unwrap();
+            assert_eq!(body.linear_velocity, Vector3::ZERO);
+            assert!(
+                f32::abs(body.position.z - 1.0) < 0.01,
+                "{:?}",
+                body.position
+            );
+        }
+    }
+
+    fn dropped_sphere(world: &mut PhysicsWorld) -> BodyHandle {
+        world.add_body(RigidBody::new(
+            Shape::new_sphere(0.5),
+            Vector3::new(0.0, 0.0, 10.0),
+            2.0,
+        ))
+    }
+
+    #[test]
+    fn zero_gravity_leaves_bodies_at_rest() {
+        let mut world = PhysicsWorld::new();
+        world.set_gravity(Vector3::ZERO);
+        let sphere = dropped_sphere(&mut world);
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+        let body = world.body(sphere).unwrap();
+        assert_eq!(body.linear_velocity, Vector3::ZERO);
+        assert_eq!(body.position, Vector3::new(0.0, 0.0, 10.0));
+    }
+
+    #[test]
+    fn reversed_gravity_lifts_bodies() {
+        let mut world = PhysicsWorld::new();
+        assert_eq!(world.gravity(), Vector3::new(0.0, 0.0, -9.81));
+        world.set_gravity(Vector3::new(0.0, 0.0, 9.81));
+        let sphere = dropped_sphere(&mut world);
+        let dt = 1.0 / 60.0;
+        world.step(dt);
+        // Acceleration does not depend on the mass
+        let body = world.body(sphere).unwrap();
+        assert!((body.linear_velocity - Vector3::new(0.0, 0.0, 9.81 * dt)).mag() < 1e-5);
+        assert!(body.position.z > 10.0);
+    }
+
+    fn sorted(mut handles: Vec<BodyHandle>) -> Vec<BodyHandle> {
+        handles.sort_by_key(BodyHandle::index);
+        handles
+    }
+
+    #[test]
+    fn region_queries_include_boundary_bodies() {
+        let mut world = PhysicsWorld::new();
+        let inside = world.add_body(unit_box(Vector3::ZERO));
+        let touching = world.add_body(unit_box(Vector3::new(2.5, 0.0, 0.0)));
+        let outside = world.add_body(unit_box(Vector3::new(3.0, 0.0, 0.0)));
+        let diagonal = world.add_body(unit_box(Vector3::new(1.9, 1.9, 0.0)));
+        let corner = world.add_body(unit_box(Vector3::new(2.0, 2.0, 0.0)));
+        let region = Aabb::new(Vector3::new(-2.0, -2.0, -2.0), Vector3::new(2.0, 2.0, 2.0));
+        assert_eq!(
+            sorted(world.query_aabb(&region)),
+            vec![inside, touching, diagonal, corner]
+        );
+        // The corner box overlaps the bounds of the sphere but not the sphere
+        assert_eq!(
+            sorted(world.query_sphere(Vector3::ZERO, 2.0)),
+            vec![inside, touching, diagonal]
+        );
+        assert!(!world.query_sphere(Vector3::ZERO, 2.0).contains(&outside));
+        assert!(world
+            .query_sphere(Vector3::new(0.0, 0.0, 10.0), 1.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn launched_body_moves_by_its_velocity() {
+        let mut world = PhysicsWorld::new();
+        world.set_gravity(Vector3::ZERO);
+        let (start, velocity) = (Vector3::new(1.0, 2.0, 3.0), Vector3::new(6.0, -3.0, 12.0));
+        let spin = Vector3::new(0.0, 0.0, 3.0);
+        let body = world.add_body(
+            RigidBodyBuilder::new()
+                .with_position(start)
+                .with_linear_velocity(velocity)
+                .with_angular_velocity(spin)
+                .build(Shape::new_sphere(0.5)),
+        );
+        let dt = 1.0 / 60.0;
+        world.step(dt);
+        let body = world.body(body).unwrap();
+        assert!((body.position - (start + velocity * dt)).mag() < 1e-6);
+        assert_eq!(body.linear_velocity, velocity);
+        // Spin of 3 rad/s turns the body by 0.05 rad around Z
+        let rotated = body.orientation.rotate_point(Vector3::X);
+        assert!((rotated - Vector3::new(f32::cos(0.05), f32::sin(0.05), 0.0)).mag() < 1e-6);
+    }
+
+    #[test]
+    fn steps_accumulate_elapsed_time() {
+        let mut world = PhysicsWorld::new();
+        dropped_sphere(&mut world);
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+        assert_eq!(world.step_count(), 10);
+        assert!(f32::abs(world.elapsed_time() - 0.1667) < 1e-4);
+    }
+
+    #[test]
+    fn clear_invalidates_handles_and_keeps_settings() {
+        let mut world = PhysicsWorld::new();
+        world.set_gravity(Vector3::new(0.0, 0.0, 9.81));
+        world.set_solver_iterations(4);
+        let handles = [
+            world.add_body(unit_box(Vector3::ZERO)),
+            world.add_body(unit_box(Vector3::new(5.0, 0.0, 0.0))),
+        ];
+        world.step(1.0 / 60.0);
+        world.clear();
+        assert_eq!(world.body_count(), 0);
+        assert_eq!(world.bodies().count(), 0);
+        assert_eq!((world.step_count(), world.elapsed_time()), (0, 0.0));
+        assert!(world.broadphase_pairs().is_empty());
+        for handle in handles {
+            assert!(world.body(handle).is_none());
+        }
+        assert_eq!(world.gravity(), Vector3::new(0.0, 0.0, 9.81));
+        assert_eq!(world.solver_iterations(), 4);
+
+        // A body reusing a slot is out of reach of the old handle
+        let reused = world.add_body(unit_box(Vector3::ZERO));
+        assert!(handles
+            .iter()
+            .any(|handle| handle.index() == reused.index()));
+        for handle in handles {
+            assert!(world.body(handle).is_none());
+        }
+        assert_eq!(world.query_aabb(&around(Vector3::ZERO)), vec![reused]);
+    }
+
+    #[test]
+    fn integrate_only_matches_step_without_contacts() {
+        let mut stepped = PhysicsWorld::new();
+        for i in 0..4 {
+            stepped.add_body(
+                RigidBodyBuilder::new()
+                    .with_position(Vector3::new(10.0 * i as f32, 0.0, 0.0))
+                    .with_linear_velocity(Vector3::new(0.0, i as f32, 2.0))
+                    .with_angular_velocity(Vector3::new(1.0, 0.0, i as f32))
+                    .with_damping(0.1, 0.2)
+                    .build(Shape::new_cuboid(Vector3::ONE)),
+            );
+        }
+        let mut integrated = stepped.clone();
+        for _ in 0..60 {
+            stepped.step(1.0 / 60.0);
+            integrated.integrate_only(1.0 / 60.0);
+        }
+        assert!(stepped.broadphase_pairs().iter().all(|pair| !pair.contact));
+        assert_eq!(integrated.bodies().count(), 4);
+        for ((a, body_a), (b, body_b)) in stepped.bodies().zip(integrated.bodies()) {
+            assert_eq!(a, b);
+            assert_eq!(body_a.position, body_b.position);
+            assert_eq!(body_a.orientation, body_b.orientation);
+            assert_eq!(body_a.linear_velocity, body_b.linear_velocity);
+            assert_eq!(body_a.angular_velocity, body_b.angular_velocity);
+        }
+        assert_eq!(stepped.step_count(), integrated.step_count());
+        assert_eq!(stepped.elapsed_time(), integrated.elapsed_time());
+    }
+
+    /// Summed height error of a 5-box stack resting on the ground, a single
+    /// solver pass cannot carry the weight of the boxes above down the stack.
+    fn stack_error(iterations: u32) -> f32 {
+        let mut world = PhysicsWorld::new();
+        world.set_solver_iterations(iterations);
+        world.add_body(ground());
+        let boxes = (0..5)
+            .map(|i| world.add_body(unit_box(Vector3::new(0.0, 0.0, 1.0 + i as f32))))
+            .collect::<Vec<_>>();
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        boxes
+            .iter()
+            .enumerate()
+            .map(|(i, &handle)| {
+                let height = world.body(handle).unwrap().position.z;
+                f32::abs(height - (1.0 + i as f32))
+            })
+            .sum()
+    }
+
+    #[test]
+    fn solver_iterations_settle_stacks() {
+        let (coarse, fine) = (stack_error(1), stack_error(10));
+        assert!(fine < 0.25 * coarse, "{} {}", coarse, fine);
+    }
+
+    #[test]
+    fn display_counts_sleeping_bodies() {
+        let mut world = PhysicsWorld::new();
+        assert_eq!(
+            world.to_string(),
+            "PhysicsWorld(bodies=0, sleeping=0, steps=0, elapsed=0.000s)"
+        );
+        world.add_body(ground());
+        world.add_body(unit_box(Vector3::new(0.0, 0.0, 1.0)));
+        let mut moving = unit_box(Vector3::new(5.0, 0.0, 1.0));
+        moving.angular_velocity = Vector3::new(0.0, 0.0, 1.0);
+        world.add_body(moving);
+        assert_eq!(world.sleeping_count(), 1);
+        world.integrate_only(0.25);
+        world.integrate_only(0.25);
+        // Gravity woke up the resting box, static bodies never count
+        assert_eq!(
+            world.to_string(),
+            "PhysicsWorld(bodies=3, sleeping=0, steps=2, elapsed=0.500s)"
+        );
+    }
+}
@@ -0,0 +1,9 @@
+pub use crate::{
+    app::{Application, ApplicationBuilder},
+    math::types::{Matrix3, Matrix4, Quaternion, Vector2, Vector3, Vector4},
+    physics::{
+        Aabb, BodyHandle, PhysicsMaterial, PhysicsThread, PhysicsWorld, Ray, RigidBody,
+        RigidBodyBuilder, Shape,
+    },
+    scene::{Scene, SceneBuilder},
+};
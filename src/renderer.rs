@@ -1,34 +1,283 @@
-use crate::math::types::Matrix4;
+use crate::math::types::{Matrix4, Vector2, Vector3, Vector4};
+use crate::physics::{RigidBody, Shape};
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
 use winit::window::Window;
 
 use crate::utils::StaticResult;
 
 mod camera;
+mod debug;
+mod immediate;
+pub mod lod;
 mod mesh;
+mod particles;
+mod skeleton;
+mod text;
 mod vulkan;
 
-pub use camera::{Camera, CameraBuilder};
-pub(super) use mesh::Mesh;
+pub use camera::{Camera, CameraBuilder, CAMERA_UP};
+pub use debug::{aabb_lines, body_lines, broadphase_lines, DebugLine, DEBUG_VELOCITY_SCALE};
+pub use mesh::{IndexType, Mesh, TessellationQuality};
+pub use particles::{DynamicMesh, Emitter, Particle, ParticlePool};
+pub use skeleton::{Skeleton, SkinnedVertex, MAX_JOINT_INFLUENCES};
+pub use text::{Glyph, TextRenderer};
 
 #[derive(Debug, Clone, Copy)]
 pub struct MeshHandle(pub usize);
 
+impl MeshHandle {
+    pub const INVALID: MeshHandle = MeshHandle(usize::MAX);
+
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.0 != usize::MAX
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureHandle(pub usize);
+
+/// Texel formats accepted by texture uploads, one f32 per channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    R32Float,
+    Rg32Float,
+    Rgba32Float,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FrameInfo {
+    pub frame_index: u64,
+    pub swapchain_image_index: u32,
+    pub dt_seconds: f32,
+}
+
+#[derive(Debug, Default)]
+struct FrameClock {
+    frame_index: u64,
+    last_frame_end: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Backend {
     Vulkan,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthBias {
+    pub constant: f32,
+    pub slope: f32,
+}
+
+/// SPIR-V shader code, either read from a file when the pipeline is created
+/// or supplied directly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShaderSource {
+    Path(PathBuf),
+    SpirV(Vec<u8>),
+}
+
+/// Replacements for the built-in shaders, they have to consume the same
+/// vertex attributes, push constants and material uniform block as
+/// `shaders/src/shader.*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shaders {
+    pub vertex: ShaderSource,
+    pub fragment: ShaderSource,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub validation: bool,
+    pub linear_colors: bool,
+    pub shadow_depth_bias: Option<DepthBias>,
+    pub stencil: bool,
+    pub msaa_samples: u32,
+    pub shaders: Option<Shaders>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DepthFormat {
+    D16,
+    D16S8,
+    D24S8,
+    D32,
+    D32S8,
+}
+
+/// Limits and optional features of the device picked by the backend,
+/// `msaa_samples` is the sample count in use after clamping `Config::msaa_samples`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    pub max_msaa_samples: u32,
+    pub msaa_samples: u32,
+    pub depth_format: DepthFormat,
+    pub max_push_constant_size: u32,
+    pub fill_mode_non_solid: bool,
+    pub wide_lines: bool,
+    pub multi_draw_indirect: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CullMode {
+    None,
+    Front,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    VertexColor = 0,
+    MaterialColor = 1,
+}
+
+/// Color space a color is given in, converted to the one of the surface
+/// format, linear for `*_SRGB` surfaces and sRGB encoded for `*_UNORM` ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StencilCompare {
+    Never,
+    Less,
+    Equal,
+    LessOrEqual,
+    Greater,
+    NotEqual,
+    GreaterOrEqual,
+    Always,
+}
+
 pub trait Renderer {
-    fn begin_frame(&mut self, camera: &Camera) -> StaticResult<()>;
+    fn begin_frame(&mut self, camera: &Camera) -> StaticResult<FrameInfo>;
     fn draw(&mut self, model: MeshHandle, world: &Matrix4);
+    fn draw_transparent(&mut self, model: MeshHandle, world: &Matrix4, alpha: f32);
+    fn upload_mesh(&mut self, mesh: &Mesh) -> StaticResult<MeshHandle>;
+    /// Uploads a mesh that can be posed with `skin_mesh`, until then it is
+    /// drawn in its bind pose.
+    fn upload_skinned_mesh(
+        &mut self,
+        vertices: &[SkinnedVertex],
+        indices: &[u32],
+    ) -> StaticResult<MeshHandle>;
+    fn skin_mesh(&mut self, mesh: MeshHandle, skeleton: &Skeleton) -> StaticResult<()>;
+    fn draw_line(&mut self, line: &DebugLine);
+    /// Makes `font` the font used by `draw_text`, uploading its atlas.
+    fn load_font(&mut self, font: TextRenderer) -> StaticResult<()>;
+    /// Draws `text` over the frame with its top left corner at `position` in
+    /// window pixels, `size` is the line height in pixels. Does nothing until
+    /// a font is loaded.
+    fn draw_text(&mut self, text: &str, position: Vector2, size: f32, color: Vector3);
+    /// Draws `shape` for the current frame only. Meshes are generated once per
+    /// distinct shape and color and uploaded before the next frame begins, so a
+    /// shape first seen mid-frame shows up from the following frame on.
+    fn draw_shape_immediate(&mut self, shape: &Shape, transform: &Matrix4, color: Vector4);
+    fn draw_body_debug(&mut self, body: &RigidBody) {
+        for line in body_lines(body) {
+            self.draw_line(&line);
+        }
+    }
+    /// Returns the raw depth buffer value of the last rendered frame at the
+    /// given window pixel, see `Camera::linear_depth` for view space distance.
+    fn read_depth(&mut self, x: u32, y: u32) -> StaticResult<f32>;
+    fn capabilities(&self) -> Capabilities;
+    /// Uploads a `width` x `height` x `depth` volume, `data` holds the texels
+    /// row by row and slice by slice.
+    fn load_texture_3d(
+        &mut self,
+        data: &[f32],
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+    ) -> StaticResult<TextureHandle>;
+    fn set_cull_mode(&mut self, mode: CullMode) -> StaticResult<()>;
+    /// Toggles the depth test and depth writes of subsequent draws, e.g. for
+    /// overlays drawn on top of the scene. Both are enabled by default.
+    fn set_depth(&mut self, test: bool, write: bool) -> StaticResult<()>;
+    /// Offsets the depth of subsequent draws by `Config::shadow_depth_bias`,
+    /// e.g. for geometry drawn over coplanar surfaces. Disabled by default.
+    fn set_depth_bias(&mut self, enabled: bool) -> StaticResult<()>;
+    fn set_color_mode(&mut self, mode: ColorMode, albedo: Vector4) -> StaticResult<()>;
+    /// Color the frame is cleared to from the next frame on.
+    fn set_clear_color(&mut self, color: Vector4, space: ColorSpace);
+    fn set_stencil_op(
+        &mut self,
+        compare: Option<StencilCompare>,
+        write_mask: u32,
+        reference: u32,
+    ) -> StaticResult<()>;
+    fn resize(&mut self, width: u32, height: u32) -> StaticResult<()>;
     fn end_frame(&mut self) -> StaticResult<()>;
 }
 
+impl FrameClock {
+    fn frame_info(&self, swapchain_image_index: u32) -> FrameInfo {
+        FrameInfo {
+            frame_index: self.frame_index,
+            swapchain_image_index,
+            dt_seconds: self
+                .last_frame_end
+                .map_or(0.0, |end| end.elapsed().as_secs_f32()),
+        }
+    }
+
+    fn end_frame(&mut self) {
+        self.frame_index += 1;
+        self.last_frame_end = Some(Instant::now());
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            validation: cfg!(debug_assertions),
+            linear_colors: true,
+            shadow_depth_bias: None,
+            stencil: false,
+            msaa_samples: 1,
+            shaders: None,
+        }
+    }
+}
+
+impl From<PathBuf> for ShaderSource {
+    fn from(path: PathBuf) -> Self {
+        ShaderSource::Path(path)
+    }
+}
+
+impl From<&Path> for ShaderSource {
+    fn from(path: &Path) -> Self {
+        ShaderSource::Path(path.to_owned())
+    }
+}
+
+impl From<Vec<u8>> for ShaderSource {
+    fn from(code: Vec<u8>) -> Self {
+        ShaderSource::SpirV(code)
+    }
+}
+
+impl From<&[u8]> for ShaderSource {
+    fn from(code: &[u8]) -> Self {
+        ShaderSource::SpirV(code.to_vec())
+    }
+}
+
 pub fn create(
     backend: Backend,
     window: &Window,
     meshes: &[Mesh],
+    config: &Config,
 ) -> StaticResult<Box<dyn Renderer>> {
     match backend {
-        Backend::Vulkan => Ok(Box::new(vulkan::Backend::new(window, meshes)?)),
+        Backend::Vulkan => Ok(Box::new(vulkan::Backend::new(window, meshes, config)?)),
     }
 }
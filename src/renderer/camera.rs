@@ -1,27 +1,60 @@
+use crate::log;
 use crate::math::{
     transforms::{look_at, perspective},
     types::{Matrix4, Vector2, Vector3},
 };
 
+pub const CAMERA_UP: Vector3 = Vector3::Z;
+
+#[derive(Debug, Clone, Copy)]
 pub struct CameraBuilder {
     eye: Vector3,
     center: Vector3,
 }
 
+#[derive(Debug, Clone, Copy)]
 pub struct Camera {
+    eye: Vector3,
     view: Matrix4,
     proj: Matrix4,
+    fovy_deg: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
 }
 
 impl Camera {
     fn new(eye: Vector3, center: Vector3, fovy_deg: f32, aspect: f32, near: f32, far: f32) -> Self {
         Self {
+            eye,
             proj: perspective(fovy_deg, aspect, near, far),
-            view: look_at(eye, center, Vector3::new(0.0, 0.0, 1.0)),
+            view: look_at(eye, center, CAMERA_UP),
+            fovy_deg,
+            aspect,
+            near,
+            far,
         }
     }
 
-    pub(super) fn matrix(&self) -> Matrix4 {
+    pub fn eye(&self) -> Vector3 {
+        self.eye
+    }
+
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+
+    pub fn set_aspect(&mut self, aspect: f32) {
+        self.aspect = aspect;
+        self.proj = perspective(self.fovy_deg, self.aspect, self.near, self.far);
+    }
+
+    pub fn linear_depth(&self, depth: f32) -> f32 {
+        self.far * self.near / (self.far - depth * (self.far - self.near))
+    }
+
+    /// World to clip space transform, e.g. for `lod::projected_screen_area`.
+    pub fn matrix(&self) -> Matrix4 {
         self.proj * self.view
     }
 }
@@ -32,6 +65,9 @@ impl CameraBuilder {
     }
 
     pub fn build(self, fovy_deg: f32, aspect: f32, near: f32, far: f32) -> Camera {
+        if (self.center - self.eye).mag_squared() <= f32::EPSILON * f32::EPSILON {
+            log::warning("Camera eye and center coincide, using default view direction");
+        }
         Camera::new(self.eye, self.center, fovy_deg, aspect, near, far)
     }
 }
@@ -0,0 +1,146 @@
+use crate::math::types::{Vector3, Vector4};
+use crate::physics::{Aabb, BroadphasePair, RigidBody};
+
+pub const DEBUG_VELOCITY_SCALE: f32 = 0.25;
+
+const LINEAR_VELOCITY_COLOR: Vector4 = Vector4::new(1.0, 0.8, 0.0, 1.0);
+const ANGULAR_VELOCITY_COLOR: Vector4 = Vector4::new(0.0, 0.8, 1.0, 1.0);
+const AABB_COLOR: Vector4 = Vector4::new(0.2, 1.0, 0.2, 1.0);
+const CONTACT_PAIR_COLOR: Vector4 = Vector4::new(1.0, 0.2, 0.2, 1.0);
+const FALSE_POSITIVE_PAIR_COLOR: Vector4 = Vector4::new(0.5, 0.5, 0.5, 1.0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub start: Vector3,
+    pub end: Vector3,
+    pub color: Vector4,
+}
+
+impl DebugLine {
+    #[inline]
+    pub fn new(start: Vector3, end: Vector3, color: Vector4) -> Self {
+        Self { start, end, color }
+    }
+}
+
+pub fn aabb_lines(aabb: &Aabb, color: Vector4) -> Vec<DebugLine> {
+    let corners = aabb.corners();
+    let mut lines = Vec::with_capacity(12);
+    for axis in &[1, 2, 4] {
+        for corner in (0..8).filter(|corner| corner & axis == 0) {
+            lines.push(DebugLine::new(
+                corners[corner],
+                corners[corner | axis],
+                color,
+            ));
+        }
+    }
+    lines
+}
+
+pub fn body_lines(body: &RigidBody) -> Vec<DebugLine> {
+    let mut lines = vec![
+        DebugLine::new(
+            body.position,
+            body.position + body.linear_velocity * DEBUG_VELOCITY_SCALE,
+            LINEAR_VELOCITY_COLOR,
+        ),
+        DebugLine::new(
+            body.position,
+            body.position + body.angular_velocity * DEBUG_VELOCITY_SCALE,
+            ANGULAR_VELOCITY_COLOR,
+        ),
+    ];
+    lines.extend(aabb_lines(&Aabb::from_body(body), AABB_COLOR));
+    lines
+}
+
+/// One line per pair between the AABB centers, red when the narrowphase
+/// confirmed the contact and grey for broadphase false positives.
+pub fn broadphase_lines(pairs: &[BroadphasePair]) -> Vec<DebugLine> {
+    pairs
+        .iter()
+        .map(|pair| {
+            let color = if pair.contact {
+                CONTACT_PAIR_COLOR
+            } else {
+                FALSE_POSITIVE_PAIR_COLOR
+            };
+            DebugLine::new(pair.aabb_a.center(), pair.aabb_b.center(), color)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{PhysicsWorld, Shape};
+
+    #[test]
+    fn broadphase_lines_join_pair_centers() {
+        let mut world = PhysicsWorld::new();
+        let shape = Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0));
+        let a = world.add_body(RigidBody::new(shape.clone(), Vector3::ZERO, 1.0));
+        let b = world.add_body(RigidBody::new(shape, Vector3::new(0.0, 1.5, 0.0), 1.0));
+        let aabb = |offset: f32| Aabb::new(Vector3::ZERO, Vector3::new(1.0, 1.0, 1.0 + offset));
+        let pairs = [
+            BroadphasePair {
+                a,
+                b,
+                aabb_a: aabb(0.0),
+                aabb_b: aabb(2.0),
+                contact: true,
+            },
+            BroadphasePair {
+                a,
+                b,
+                aabb_a: aabb(4.0),
+                aabb_b: aabb(6.0),
+                contact: false,
+            },
+        ];
+        assert_eq!(
+            broadphase_lines(&pairs),
+            vec![
+                DebugLine::new(
+                    Vector3::new(0.5, 0.5, 0.5),
+                    Vector3::new(0.5, 0.5, 1.5),
+                    CONTACT_PAIR_COLOR
+                ),
+                DebugLine::new(
+                    Vector3::new(0.5, 0.5, 2.5),
+                    Vector3::new(0.5, 0.5, 3.5),
+                    FALSE_POSITIVE_PAIR_COLOR
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn body_lines_start_at_body_position() {
+        let mut body = RigidBody::new(Shape::new_sphere(0.5), Vector3::new(1.0, 2.0, 3.0), 1.0);
+        body.linear_velocity = Vector3::new(4.0, 0.0, -2.0);
+        body.angular_velocity = Vector3::new(0.0, 8.0, 0.0);
+        let lines = body_lines(&body);
+        assert_eq!(
+            lines[0],
+            DebugLine::new(
+                body.position,
+                Vector3::new(2.0, 2.0, 2.5),
+                LINEAR_VELOCITY_COLOR
+            )
+        );
+        assert_eq!(
+            lines[1],
+            DebugLine::new(
+                body.position,
+                Vector3::new(1.0, 4.0, 3.0),
+                ANGULAR_VELOCITY_COLOR
+            )
+        );
+        assert_eq!(
+            lines[2..],
+            aabb_lines(&Aabb::from_body(&body), AABB_COLOR)[..]
+        );
+    }
+}
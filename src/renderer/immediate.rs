@@ -0,0 +1,154 @@
+use super::{Mesh, MeshHandle, TessellationQuality};
+use crate::log;
+use crate::math::types::{Vector3, Vector4};
+use crate::physics::Shape;
+use crate::utils::StaticResult;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) struct ShapeKey(Vec<u32>);
+
+#[derive(Debug, Default)]
+pub(super) struct ShapeMeshCache {
+    meshes: HashMap<ShapeKey, MeshHandle>,
+    pending: Vec<(ShapeKey, Mesh)>,
+}
+
+impl ShapeKey {
+    pub(super) fn new(shape: &Shape, color: Vector4) -> Self {
+        let mut key = vec![];
+        let push_vec = |key: &mut Vec<u32>, v: Vector3| {
+            key.extend_from_slice(&[v.x.to_bits(), v.y.to_bits(), v.z.to_bits()])
+        };
+        match shape {
+            Shape::Cuboid(cuboid) => {
+                key.push(0);
+                push_vec(&mut key, cuboid.bounds_min);
+                push_vec(&mut key, cuboid.bounds_max);
+            }
+            Shape::Sphere(sphere) => key.extend_from_slice(&[1, sphere.radius.to_bits()]),
+            Shape::ConvexHull(hull) => {
+                key.push(2);
+                for &vertex in &hull.vertices {
+                    push_vec(&mut key, vertex);
+                }
+                key.extend(hull.faces.iter().flatten());
+            }
+            Shape::Capsule(capsule) => {
+                key.extend_from_slice(&[3, capsule.radius.to_bits(), capsule.half_height.to_bits()])
+            }
+            Shape::Cylinder(cylinder) => key.extend_from_slice(&[
+                4,
+                cylinder.radius.to_bits(),
+                cylinder.half_height.to_bits(),
+            ]),
+            Shape::Plane(plane) => {
+                key.push(5);
+                push_vec(&mut key, plane.normal);
+            }
+            Shape::Torus(torus) => key.extend_from_slice(&[
+                6,
+                torus.major_radius.to_bits(),
+                torus.minor_radius.to_bits(),
+            ]),
+        }
+        key.extend_from_slice(&[
+            color.x.to_bits(),
+            color.y.to_bits(),
+            color.z.to_bits(),
+            color.w.to_bits(),
+        ]);
+        Self(key)
+    }
+}
+
+impl ShapeMeshCache {
+    pub(super) fn get(&self, key: &ShapeKey) -> Option<MeshHandle> {
+        self.meshes.get(key).copied()
+    }
+
+    pub(super) fn request(&mut self, key: ShapeKey, shape: &Shape, color: Vector4) {
+        if self.meshes.contains_key(&key) || self.pending.iter().any(|(k, _)| *k == key) {
+            return;
+        }
+        let mesh = Mesh::from_shape(shape, TessellationQuality::Low).with_color(color);
+        self.pending.push((key, mesh));
+    }
+
+    pub(super) fn upload_pending<F>(&mut self, mut upload: F) -> StaticResult<()>
+    where
+        F: FnMut(&Mesh) -> StaticResult<MeshHandle>,
+    {
+        for (key, mesh) in self.pending.drain(..) {
+            let handle = upload(&mesh)?;
+            log::verbose(&format!(
+                "Cached immediate mode mesh {} ({} vertices)",
+                handle.0,
+                mesh.vertex_count()
+            ));
+            self.meshes.insert(key, handle);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Vector4 = Vector4::new(1.0, 0.0, 0.0, 1.0);
+
+    // Mirrors `Renderer::draw_shape_immediate`, counting uploads
+    fn draw(cache: &mut ShapeMeshCache, shape: &Shape, color: Vector4, uploads: &mut usize) {
+        let key = ShapeKey::new(shape, color);
+        if cache.get(&key).is_none() {
+            cache.request(key, shape, color);
+        }
+        cache
+            .upload_pending(|_| {
+                *uploads += 1;
+                Ok(MeshHandle(*uploads))
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn identical_shapes_share_one_upload() {
+        let mut cache = ShapeMeshCache::default();
+        let mut uploads = 0;
+        let sphere = Shape::new_sphere(0.5);
+        for _ in 0..10 {
+            draw(&mut cache, &sphere, RED, &mut uploads);
+            draw(&mut cache, &Shape::new_sphere(0.5), RED, &mut uploads);
+        }
+        assert_eq!(uploads, 1);
+        assert_eq!(
+            cache
+                .get(&ShapeKey::new(&sphere, RED))
+                .map(|handle| handle.0),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn different_shapes_or_colors_get_own_uploads() {
+        let mut cache = ShapeMeshCache::default();
+        let mut uploads = 0;
+        let blue = Vector4::new(0.0, 0.0, 1.0, 1.0);
+        draw(&mut cache, &Shape::new_sphere(0.5), RED, &mut uploads);
+        draw(&mut cache, &Shape::new_sphere(0.5), blue, &mut uploads);
+        draw(&mut cache, &Shape::new_sphere(0.6), RED, &mut uploads);
+        draw(&mut cache, &Shape::new_capsule(0.5, 0.5), RED, &mut uploads);
+        assert_eq!(uploads, 4);
+    }
+
+    #[test]
+    fn repeated_requests_before_upload_queue_once() {
+        let mut cache = ShapeMeshCache::default();
+        let shape = Shape::new_cuboid(Vector3::new(1.0, 2.0, 3.0));
+        for _ in 0..3 {
+            cache.request(ShapeKey::new(&shape, RED), &shape, RED);
+        }
+        assert_eq!(cache.pending.len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    math::types::{Matrix4, Vector4},
+    physics::Aabb,
+};
+
+/// Screen area in pixels covered by the axis aligned rectangle bounding the
+/// projected corners of `aabb`, `vp` maps world space to clip space and
+/// `viewport_area` is the viewport width times height. The rectangle is
+/// clipped to the viewport, a box entirely behind the eye covers none of it
+/// and one straddling the eye plane all of it.
+pub fn projected_screen_area(aabb: &Aabb, vp: &Matrix4, viewport_area: f32) -> f32 {
+    let clip = aabb
+        .corners()
+        .iter()
+        .map(|&corner| *vp * Vector4::hom_point(corner))
+        .collect::<Vec<_>>();
+    let behind = clip.iter().filter(|clip| clip.w <= f32::EPSILON).count();
+    if behind == clip.len() {
+        return 0.0;
+    } else if behind > 0 {
+        return viewport_area;
+    }
+    let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+    for clip in &clip {
+        for (axis, &value) in [clip.x, clip.y].iter().enumerate() {
+            let ndc = value / clip.w;
+            min[axis] = f32::min(min[axis], ndc);
+            max[axis] = f32::max(max[axis], ndc);
+        }
+    }
+    // Normalized device coordinates span [-1, 1] on both axes
+    let width = f32::max(f32::min(max[0], 1.0) - f32::max(min[0], -1.0), 0.0);
+    let height = f32::max(f32::min(max[1], 1.0) - f32::max(min[1], -1.0), 0.0);
+    width * height / 4.0 * viewport_area
+}
+
+/// Index of the most detailed level whose triangles still cover at least
+/// `pixels_per_triangle` pixels of `screen_area` on average. Levels are
+/// ordered from the most detailed one, the last level is used when none
+/// of them qualifies.
+pub fn select_lod(screen_area: f32, triangle_counts: &[usize], pixels_per_triangle: f32) -> usize {
+    triangle_counts
+        .iter()
+        .position(|&count| screen_area >= count as f32 * pixels_per_triangle)
+        .unwrap_or_else(|| triangle_counts.len().saturating_sub(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{transforms, types::Vector3};
+
+    const VIEWPORT_AREA: f32 = 1000.0 * 1000.0;
+
+    // Square viewport with a 90 degree field of view, looking down +y
+    fn view_projection(eye: Vector3) -> Matrix4 {
+        transforms::perspective(90.0, 1.0, 0.1, 100.0)
+            * transforms::look_at(eye, eye + Vector3::Y, Vector3::Z)
+    }
+
+    fn unit_box(center: Vector3) -> Aabb {
+        Aabb::new(
+            center - Vector3::new(1.0, 1.0, 1.0),
+            center + Vector3::new(1.0, 1.0, 1.0),
+        )
+    }
+
+    #[test]
+    fn area_follows_the_nearest_face() {
+        let vp = view_projection(Vector3::ZERO);
+        // The face nearest to the eye is 9 units away and 2 units wide
+        let area =
+            projected_screen_area(&unit_box(Vector3::new(0.0, 10.0, 0.0)), &vp, VIEWPORT_AREA);
+        let expected = (2.0f32 / 9.0).powi(2) / 4.0 * VIEWPORT_AREA;
+        assert!(f32::abs(area - expected) < 1e-3 * expected);
+        let farther =
+            projected_screen_area(&unit_box(Vector3::new(0.0, 20.0, 0.0)), &vp, VIEWPORT_AREA);
+        assert!(farther < area / 3.0);
+    }
+
+    #[test]
+    fn area_is_clipped_to_the_viewport() {
+        let vp = view_projection(Vector3::ZERO);
+        let behind = unit_box(Vector3::new(0.0, -10.0, 0.0));
+        assert_eq!(projected_screen_area(&behind, &vp, VIEWPORT_AREA), 0.0);
+        let around_eye = unit_box(Vector3::ZERO);
+        assert_eq!(
+            projected_screen_area(&around_eye, &vp, VIEWPORT_AREA),
+            VIEWPORT_AREA
+        );
+        // Fills the view without reaching the eye plane
+        let close = Aabb::new(
+            Vector3::new(-50.0, 1.0, -50.0),
+            Vector3::new(50.0, 2.0, 50.0),
+        );
+        assert_eq!(
+            projected_screen_area(&close, &vp, VIEWPORT_AREA),
+            VIEWPORT_AREA
+        );
+        // Half of the box in front is left of the view
+        let edge = unit_box(Vector3::new(-10.0, 10.0, 0.0));
+        let area = projected_screen_area(&edge, &vp, VIEWPORT_AREA);
+        let expected = (1.0 - 9.0 / 11.0) * (2.0 / 9.0) / 4.0 * VIEWPORT_AREA;
+        assert!(f32::abs(area - expected) < 1e-3 * expected);
+        let outside = unit_box(Vector3::new(30.0, 10.0, 0.0));
+        assert_eq!(projected_screen_area(&outside, &vp, VIEWPORT_AREA), 0.0);
+    }
+
+    #[test]
+    fn lod_keeps_triangles_above_pixel_budget() {
+        let counts = [1000, 250, 60];
+        assert_eq!(select_lod(20_000.0, &counts, 10.0), 0);
+        assert_eq!(select_lod(9_999.0, &counts, 10.0), 1);
+        assert_eq!(select_lod(2_500.0, &counts, 10.0), 1);
+        assert_eq!(select_lod(600.0, &counts, 10.0), 2);
+        // Too small for every level still picks the coarsest one
+        assert_eq!(select_lod(1.0, &counts, 10.0), 2);
+        assert_eq!(select_lod(1.0, &[], 10.0), 0);
+    }
+}
@@ -1,6 +1,10 @@
-use crate::math::types::{Vector2, Vector3, Vector4};
-use crate::physics::{Cuboid, Shape, Sphere};
+use crate::math::types::{Matrix4, Vector2, Vector3, Vector4};
+use crate::physics::{Capsule, ConvexHull, Cuboid, Cylinder, Plane, Shape, Sphere, Torus};
 use bytemuck::{Pod, Zeroable};
+use std::mem::size_of;
+
+mod obj;
+mod simplify;
 
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
@@ -15,19 +19,185 @@ pub struct Vertex {
 unsafe impl Zeroable for Vertex {}
 unsafe impl Pod for Vertex {}
 
+#[derive(Debug, Clone)]
 pub struct Mesh {
     pub(super) vertices: Vec<Vertex>,
     pub(super) indices: Vec<u32>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexType {
+    U16,
+    U32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TessellationQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl IndexType {
+    #[inline]
+    pub fn size(self) -> usize {
+        match self {
+            IndexType::U16 => size_of::<u16>(),
+            IndexType::U32 => size_of::<u32>(),
+        }
+    }
+}
+
+impl TessellationQuality {
+    fn cube_subdivisions(self) -> usize {
+        match self {
+            TessellationQuality::Low => 0,
+            TessellationQuality::Medium => 2,
+            TessellationQuality::High => 8,
+        }
+    }
+
+    fn sphere_subdivisions(self) -> usize {
+        match self {
+            TessellationQuality::Low => 4,
+            TessellationQuality::Medium => 10,
+            TessellationQuality::High => 20,
+        }
+    }
+
+    fn cylinder_segments(self) -> u32 {
+        match self {
+            TessellationQuality::Low => 12,
+            TessellationQuality::Medium => 32,
+            TessellationQuality::High => 64,
+        }
+    }
+}
+
 impl Mesh {
-    pub fn from_shape(shape: &Shape) -> Self {
+    pub fn from_shape(shape: &Shape, quality: TessellationQuality) -> Self {
         match shape {
-            Shape::Cuboid(cuboid) => Mesh::tessellated_cube(cuboid, 0),
-            Shape::Sphere(sphere) => Mesh::sphere_mesh(sphere),
+            Shape::Cuboid(cuboid) => Mesh::tessellated_cube(cuboid, quality.cube_subdivisions()),
+            Shape::Sphere(sphere) => Mesh::sphere_mesh(sphere, quality.sphere_subdivisions()),
+            Shape::ConvexHull(hull) => Mesh::from_convex_hull(hull),
+            Shape::Capsule(capsule) => Mesh::capsule_mesh(capsule, quality.sphere_subdivisions()),
+            Shape::Cylinder(cylinder) => Mesh::cylinder_mesh(cylinder, quality.cylinder_segments()),
+            Shape::Plane(plane) => Mesh::plane_mesh(plane),
+            Shape::Torus(torus) => {
+                let segments = quality.cylinder_segments();
+                Mesh::torus_mesh(torus, segments, segments / 2)
+            }
         }
     }
 
+    #[inline]
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len()
+    }
+
+    #[inline]
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    #[inline]
+    pub fn index_type(&self) -> IndexType {
+        if self.vertices.len() <= u16::MAX as usize {
+            IndexType::U16
+        } else {
+            IndexType::U32
+        }
+    }
+
+    /// Indices packed as [`Mesh::index_type`], zero padded to a 4 byte multiple
+    /// so that index data of consecutive meshes stays aligned for either type.
+    pub(super) fn index_bytes(&self) -> Vec<u8> {
+        let mut bytes = match self.index_type() {
+            IndexType::U16 => {
+                let indices: Vec<_> = self.indices.iter().map(|&index| index as u16).collect();
+                bytemuck::cast_slice(&indices).to_vec()
+            }
+            IndexType::U32 => bytemuck::cast_slice(&self.indices).to_vec(),
+        };
+        bytes.resize((bytes.len() + 3) & !3, 0);
+        bytes
+    }
+
+    pub fn with_color(mut self, color: Vector4) -> Self {
+        for vertex in &mut self.vertices {
+            vertex.color = color;
+        }
+        self
+    }
+
+    pub fn merge(&mut self, other: &Mesh, transform: &Matrix4) {
+        let [a, b, c] = [transform.i, transform.j, transform.k]
+            .map(|column| Vector3::new(column.x, column.y, column.z));
+        // Columns of the cofactor matrix, det(M) * M^-T, avoid dividing by the determinant
+        let [na, nb, nc] = [b.cross(c), c.cross(a), a.cross(b)];
+        let handedness = f32::signum(a * na);
+        let transform_normal = |norm: Vector3| {
+            let norm = (na * norm.x + nb * norm.y + nc * norm.z) * handedness;
+            if norm.mag_squared() > 0.0 {
+                norm.normalized()
+            } else {
+                norm
+            }
+        };
+
+        let base = self.vertices.len() as u32;
+        self.vertices.extend(other.vertices.iter().map(|&vertex| {
+            let tang = transform.transform_direction(Vector3::new(
+                vertex.tang.x,
+                vertex.tang.y,
+                vertex.tang.z,
+            ));
+            let tang = if tang.mag_squared() > 0.0 {
+                tang.normalized()
+            } else {
+                tang
+            };
+            Vertex {
+                pos: transform.transform_point(vertex.pos),
+                norm: transform_normal(vertex.norm),
+                tang: Vector4::new(tang.x, tang.y, tang.z, vertex.tang.w * handedness),
+                ..vertex
+            }
+        }));
+        // Mirroring transforms flip the triangle winding, swap it back to keep front faces
+        for triangle in other.indices.chunks_exact(3) {
+            let [i0, i1, i2] = [triangle[0], triangle[1], triangle[2]].map(|index| index + base);
+            if handedness < 0.0 {
+                self.indices.extend_from_slice(&[i0, i2, i1]);
+            } else {
+                self.indices.extend_from_slice(&[i0, i1, i2]);
+            }
+        }
+    }
+
+    pub fn from_convex_hull(hull: &ConvexHull) -> Mesh {
+        let mut vertices = Vec::with_capacity(3 * hull.faces.len());
+        for face in &hull.faces {
+            let [a, b, c] = [
+                hull.vertices[face[0] as usize],
+                hull.vertices[face[1] as usize],
+                hull.vertices[face[2] as usize],
+            ];
+            let norm = Vector3::cross(b - a, c - a).normalized();
+            for &pos in &[a, b, c] {
+                vertices.push(Vertex {
+                    pos,
+                    norm,
+                    color: Vector4::new(0.8, 0.8, 0.8, 1.0),
+                    ..Default::default()
+                });
+            }
+        }
+        let indices = (0..vertices.len() as u32).collect();
+        Mesh { vertices, indices }
+    }
+
     fn tessellated_cube(cuboid: &Cuboid, subdiv: usize) -> Mesh {
         let face_vertices = (subdiv + 2).pow(2);
         let face_indices = (subdiv + 1).pow(2) * 6;
@@ -146,15 +316,306 @@ impl Mesh {
         }
     }
 
-    fn sphere_mesh(sphere: &Sphere) -> Mesh {
+    fn sphere_mesh(sphere: &Sphere, subdiv: usize) -> Mesh {
         let unit_cube = Cuboid {
             bounds_min: Vector3::new(-0.5, -0.5, -0.5),
             bounds_max: Vector3::new(0.5, 0.5, 0.5),
         };
-        let mut unit_cube_mesh = Mesh::tessellated_cube(&unit_cube, 10);
+        let mut unit_cube_mesh = Mesh::tessellated_cube(&unit_cube, subdiv);
         for vert in &mut unit_cube_mesh.vertices {
             vert.pos = vert.pos.normalized() * sphere.radius;
         }
         unit_cube_mesh
     }
+
+    fn capsule_mesh(capsule: &Capsule, subdiv: usize) -> Mesh {
+        let unit_cube = Cuboid {
+            bounds_min: Vector3::new(-0.5, -0.5, -0.5),
+            bounds_max: Vector3::new(0.5, 0.5, 0.5),
+        };
+        let mut unit_cube_mesh = Mesh::tessellated_cube(&unit_cube, subdiv);
+        for vert in &mut unit_cube_mesh.vertices {
+            let norm = vert.pos.normalized();
+            let offset = if norm.z > 0.0 {
+                capsule.half_height
+            } else {
+                -capsule.half_height
+            };
+            vert.pos = norm * capsule.radius + Vector3::Z * offset;
+            vert.norm = norm;
+        }
+        unit_cube_mesh
+    }
+
+    fn cylinder_mesh(cylinder: &Cylinder, segments: u32) -> Mesh {
+        let color = Vector4::new(0.8, 0.8, 0.8, 1.0);
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let ring = |k: u32| {
+            let (s, c) = f32::sin_cos(2.0 * std::f32::consts::PI * k as f32 / segments as f32);
+            Vector3::new(c, s, 0.0)
+        };
+        let (top, bottom) = (
+            Vector3::Z * cylinder.half_height,
+            Vector3::NEG_Z * cylinder.half_height,
+        );
+
+        for k in 0..=segments {
+            let norm = ring(k);
+            for &cap in &[bottom, top] {
+                vertices.push(Vertex {
+                    pos: cap + norm * cylinder.radius,
+                    norm,
+                    color,
+                    ..Default::default()
+                });
+            }
+        }
+        for k in 0..segments {
+            let (b0, t0, b1, t1) = (2 * k, 2 * k + 1, 2 * k + 2, 2 * k + 3);
+            indices.extend_from_slice(&[b0, b1, t1, b0, t1, t0]);
+        }
+
+        for &(cap, norm) in &[(top, Vector3::Z), (bottom, Vector3::NEG_Z)] {
+            let center = vertices.len() as u32;
+            vertices.push(Vertex {
+                pos: cap,
+                norm,
+                color,
+                ..Default::default()
+            });
+            for k in 0..segments {
+                vertices.push(Vertex {
+                    pos: cap + ring(k) * cylinder.radius,
+                    norm,
+                    color,
+                    ..Default::default()
+                });
+            }
+            for k in 0..segments {
+                let (a, b) = (center + 1 + k, center + 1 + (k + 1) % segments);
+                if norm.z > 0.0 {
+                    indices.extend_from_slice(&[center, a, b]);
+                } else {
+                    indices.extend_from_slice(&[center, b, a]);
+                }
+            }
+        }
+        Mesh { vertices, indices }
+    }
+
+    /// Samples `P(u, v) = ((R + r cos v) cos u, (R + r cos v) sin u, r sin v)`
+    /// with `tubular_segments` steps around the Z axis and `radial_segments`
+    /// steps around the tube, seam vertices are duplicated.
+    pub fn torus_mesh(torus: &Torus, tubular_segments: u32, radial_segments: u32) -> Mesh {
+        let color = Vector4::new(0.8, 0.8, 0.8, 1.0);
+        let (tubular_segments, radial_segments) = (tubular_segments.max(3), radial_segments.max(3));
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let angle = |k: u32, segments: u32| {
+            f32::sin_cos(2.0 * std::f32::consts::PI * k as f32 / segments as f32)
+        };
+
+        for i in 0..=tubular_segments {
+            let (sin_u, cos_u) = angle(i, tubular_segments);
+            for j in 0..=radial_segments {
+                let (sin_v, cos_v) = angle(j, radial_segments);
+                let norm = Vector3::new(cos_v * cos_u, cos_v * sin_u, sin_v);
+                let center = Vector3::new(cos_u, sin_u, 0.0) * torus.major_radius;
+                vertices.push(Vertex {
+                    pos: center + norm * torus.minor_radius,
+                    norm,
+                    color,
+                    ..Default::default()
+                });
+            }
+        }
+        let stride = radial_segments + 1;
+        for i in 0..tubular_segments {
+            for j in 0..radial_segments {
+                let (a, b) = (i * stride + j, (i + 1) * stride + j);
+                indices.extend_from_slice(&[a, b, b + 1, a, b + 1, a + 1]);
+            }
+        }
+        Mesh { vertices, indices }
+    }
+
+    fn plane_mesh(plane: &Plane) -> Mesh {
+        let vertices = plane
+            .corners()
+            .iter()
+            .map(|&pos| Vertex {
+                pos,
+                norm: plane.normal,
+                color: Vector4::new(0.8, 0.8, 0.8, 1.0),
+                ..Default::default()
+            })
+            .collect();
+        Mesh {
+            vertices,
+            indices: vec![0, 1, 2, 0, 2, 3],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::transforms::translate;
+    use crate::physics::Aabb;
+
+    fn cube() -> Mesh {
+        Mesh::from_shape(
+            &Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)),
+            TessellationQuality::Low,
+        )
+    }
+
+    #[test]
+    fn merge_appends_transformed_mesh() {
+        let mut merged = cube();
+        let other = cube();
+        let offset = Vector3::new(5.0, 0.0, 0.0);
+        merged.merge(&other, &translate(offset));
+        assert_eq!(merged.vertex_count(), 2 * other.vertex_count());
+        assert_eq!(merged.triangle_count(), 2 * other.triangle_count());
+        let count = other.vertex_count();
+        for (merged, original) in merged.vertices[count..].iter().zip(&other.vertices) {
+            assert_eq!(merged.pos, original.pos + offset);
+            assert_eq!(merged.norm, original.norm);
+        }
+        for (merged, &original) in merged.indices[other.indices.len()..]
+            .iter()
+            .zip(&other.indices)
+        {
+            assert_eq!(*merged, original + count as u32);
+        }
+    }
+
+    #[test]
+    fn merge_keeps_mirrored_normals_outward() {
+        let mut merged = Mesh {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+        };
+        let mirror = Matrix4::from_trs(
+            Vector3::ZERO,
+            Default::default(),
+            Vector3::new(-1.0, 2.0, 1.0),
+        );
+        merged.merge(&cube(), &mirror);
+        for triangle in merged.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]]
+                .map(|index| merged.vertices[index as usize]);
+            // Counter-clockwise winding seen from the outside
+            let face = (b.pos - a.pos).cross(c.pos - a.pos);
+            assert!(face * a.norm > 0.0);
+            assert!(a.pos * a.norm > 0.0);
+            assert!(f32::abs(a.norm.mag() - 1.0) < 1e-5);
+        }
+    }
+
+    fn every_shape() -> Vec<Shape> {
+        vec![
+            Shape::new_cuboid(Vector3::new(1.0, 2.0, 3.0)),
+            Shape::new_sphere(0.75),
+            Shape::new_convex_hull(&[
+                Vector3::ZERO,
+                Vector3::X,
+                Vector3::Y * 2.0,
+                Vector3::Z * 3.0,
+            ]),
+            Shape::new_capsule(0.5, 1.0),
+            Shape::new_cylinder(0.5, 1.5),
+            Shape::new_plane(Vector3::Z),
+            Shape::new_torus(2.0, 0.5),
+        ]
+    }
+
+    #[test]
+    fn every_shape_gets_a_mesh_inside_its_aabb() {
+        for shape in every_shape() {
+            let mesh = Mesh::from_shape(&shape, TessellationQuality::Medium);
+            assert!(mesh.triangle_count() > 0, "{:?}", shape);
+            assert!(mesh
+                .indices
+                .iter()
+                .all(|&index| (index as usize) < mesh.vertex_count()));
+            let aabb = shape.aabb();
+            let points = mesh
+                .vertices
+                .iter()
+                .map(|vertex| vertex.pos)
+                .collect::<Vec<_>>();
+            let bounds = Aabb::from_points(&points);
+            for i in 0..3 {
+                // Tessellation may cut corners of curved shapes, never overshoot
+                assert!(bounds.min[i] >= aabb.min[i] - 1e-4, "{:?}", shape);
+                assert!(bounds.max[i] <= aabb.max[i] + 1e-4, "{:?}", shape);
+                assert!(bounds.extent()[i] >= 0.9 * aabb.extent()[i], "{:?}", shape);
+            }
+        }
+    }
+
+    #[test]
+    fn higher_quality_adds_vertices() {
+        for shape in [
+            Shape::new_sphere(1.0),
+            Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)),
+        ] {
+            let low = Mesh::from_shape(&shape, TessellationQuality::Low);
+            let high = Mesh::from_shape(&shape, TessellationQuality::High);
+            assert!(high.vertex_count() > low.vertex_count(), "{:?}", shape);
+            assert!(high.triangle_count() > low.triangle_count(), "{:?}", shape);
+        }
+    }
+
+    #[test]
+    fn index_type_follows_vertex_count() {
+        let triangle = Mesh {
+            vertices: cube().vertices[..3].to_vec(),
+            indices: vec![0, 1, 2],
+        };
+        assert_eq!(triangle.index_type(), IndexType::U16);
+        let bytes = triangle.index_bytes();
+        assert_eq!(bytes.len(), 8);
+        assert_eq!(bytemuck::cast_slice::<_, u16>(&bytes), &[0, 1, 2, 0]);
+
+        let large = Mesh::torus_mesh(
+            &Torus {
+                major_radius: 2.0,
+                minor_radius: 0.5,
+            },
+            300,
+            300,
+        );
+        assert!(large.vertex_count() > u16::MAX as usize);
+        assert_eq!(large.index_type(), IndexType::U32);
+        let bytes = large.index_bytes();
+        assert_eq!(bytes.len(), 4 * large.indices.len());
+        assert_eq!(bytemuck::cast_slice::<_, u32>(&bytes), &large.indices[..]);
+    }
+
+    #[test]
+    fn torus_vertices_lie_on_the_surface() {
+        let torus = Torus {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        let mesh = Mesh::torus_mesh(&torus, 24, 12);
+        for vertex in &mesh.vertices {
+            let pos = vertex.pos;
+            let ring = f32::sqrt(pos.x * pos.x + pos.y * pos.y) - torus.major_radius;
+            let distance = f32::sqrt(ring * ring + pos.z * pos.z);
+            assert!(f32::abs(distance - torus.minor_radius) < 1e-5, "{:?}", pos);
+            assert!(f32::abs(vertex.norm.mag() - 1.0) < 1e-5);
+        }
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] =
+                [triangle[0], triangle[1], triangle[2]].map(|index| mesh.vertices[index as usize]);
+            let face = (b.pos - a.pos).cross(c.pos - a.pos);
+            assert!(face.mag() > 1e-6);
+            assert!(face * (a.norm + b.norm + c.norm) > 0.0);
+        }
+    }
 }
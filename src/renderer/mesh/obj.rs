@@ -0,0 +1,302 @@
+use super::{Mesh, Vertex};
+use crate::{
+    log,
+    math::types::{Vector2, Vector3, Vector4},
+    utils::StaticResult,
+};
+use std::{collections::HashMap, fmt::Write, fs, path::Path};
+
+const OBJ_VERTEX_COLOR: Vector4 = Vector4::new(0.8, 0.8, 0.8, 1.0);
+
+// Faces without explicit normals get flat shading, so their corners are keyed
+// by face index and never shared with neighbouring faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CornerKey {
+    position: usize,
+    tex: Option<usize>,
+    norm: Option<usize>,
+    face: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct ObjData {
+    positions: Vec<Vector3>,
+    normals: Vec<Vector3>,
+    tex_coords: Vec<Vector2>,
+}
+
+fn parse_floats<const N: usize>(args: &[&str]) -> Option<[f32; N]> {
+    let mut values = [0.0; N];
+    if args.len() < N {
+        return None;
+    }
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg.parse().ok()?;
+    }
+    Some(values)
+}
+
+fn resolve_index(index: &str, count: usize) -> Option<usize> {
+    let index = index.parse::<isize>().ok()?;
+    let resolved = match index {
+        0 => return None,
+        index if index > 0 => index - 1,
+        index => count as isize + index,
+    };
+    (0..count as isize)
+        .contains(&resolved)
+        .then_some(resolved as usize)
+}
+
+impl ObjData {
+    fn parse_corner(&self, corner: &str) -> Option<CornerKey> {
+        let mut parts = corner.split('/');
+        let position = resolve_index(parts.next()?, self.positions.len())?;
+        let tex = match parts.next() {
+            None | Some("") => None,
+            Some(index) => Some(resolve_index(index, self.tex_coords.len())?),
+        };
+        let norm = match parts.next() {
+            None | Some("") => None,
+            Some(index) => Some(resolve_index(index, self.normals.len())?),
+        };
+        Some(CornerKey {
+            position,
+            tex,
+            norm,
+            face: None,
+        })
+    }
+
+    // Sum of fan triangle normals, equal to twice the polygon area vector
+    // for planar polygons regardless of convexity
+    fn polygon_normal(&self, corners: &[CornerKey]) -> Vector3 {
+        let origin = self.positions[corners[0].position];
+        corners[1..].windows(2).fold(Vector3::ZERO, |normal, edge| {
+            let a = self.positions[edge[0].position] - origin;
+            let b = self.positions[edge[1].position] - origin;
+            normal + a.cross(b)
+        })
+    }
+}
+
+impl Mesh {
+    pub fn from_obj(path: &Path) -> StaticResult<Mesh> {
+        let source = fs::read_to_string(path)?;
+        Mesh::parse_obj(&source)
+            .map_err(|err| format!("Failed to load {}: {}", path.display(), err).into())
+    }
+
+    pub fn to_obj(&self, path: &Path) -> StaticResult<()> {
+        fs::write(path, self.write_obj())
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err).into())
+    }
+
+    /// Every vertex gets its own position, texture coordinate and normal, so
+    /// `parse_obj` reads back as many vertices and indices, numbered in order
+    /// of first use by the faces. Vertices no face references are lost.
+    pub fn write_obj(&self) -> String {
+        let mut source = String::new();
+        for vertex in &self.vertices {
+            let Vertex { pos, norm, tex, .. } = vertex;
+            writeln!(source, "v {} {} {}", pos.x, pos.y, pos.z).unwrap();
+            writeln!(source, "vt {} {}", tex.x, 1.0 - tex.y).unwrap();
+            writeln!(source, "vn {} {} {}", norm.x, norm.y, norm.z).unwrap();
+        }
+        for face in self.indices.chunks_exact(3) {
+            let [a, b, c] = [face[0] + 1, face[1] + 1, face[2] + 1];
+            writeln!(source, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", a, b, c).unwrap();
+        }
+        source
+    }
+
+    pub fn parse_obj(source: &str) -> StaticResult<Mesh> {
+        let mut data = ObjData::default();
+        let mut vertices = vec![];
+        let mut indices = vec![];
+        let mut corner_indices = HashMap::new();
+        let mut face_count = 0;
+
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("");
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let invalid = || format!("Invalid OBJ statement at line {}", number + 1);
+            match fields.as_slice() {
+                ["v", args @ ..] => {
+                    let [x, y, z] = parse_floats(args).ok_or_else(invalid)?;
+                    data.positions.push(Vector3::new(x, y, z));
+                }
+                ["vn", args @ ..] => {
+                    let [x, y, z] = parse_floats(args).ok_or_else(invalid)?;
+                    data.normals.push(Vector3::new(x, y, z).normalized());
+                }
+                ["vt", args @ ..] => {
+                    // OBJ texture origin is bottom-left, Vulkan samples from top-left
+                    let [u, v] = parse_floats(args).ok_or_else(invalid)?;
+                    data.tex_coords.push(Vector2::new(u, 1.0 - v));
+                }
+                ["f", args @ ..] => {
+                    let mut corners = args
+                        .iter()
+                        .map(|corner| data.parse_corner(corner))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(invalid)?;
+                    if corners.len() < 3 {
+                        log::warning(&format!(
+                            "Skipping degenerate OBJ face with {} vertices at line {}",
+                            corners.len(),
+                            number + 1
+                        ));
+                        continue;
+                    }
+                    let face_normal = data.polygon_normal(&corners).normalized();
+                    if corners.len() > 3 {
+                        let origin = data.positions[corners[0].position];
+                        let concave = corners[1..].windows(2).any(|edge| {
+                            let a = data.positions[edge[0].position] - origin;
+                            let b = data.positions[edge[1].position] - origin;
+                            a.cross(b) * face_normal < 0.0
+                        });
+                        if concave {
+                            log::warning(&format!(
+                                "Concave OBJ face at line {} will be fan triangulated with overlaps",
+                                number + 1
+                            ));
+                        }
+                    }
+                    for corner in &mut corners {
+                        if corner.norm.is_none() {
+                            corner.face = Some(face_count);
+                        }
+                    }
+                    let corner_index = |corner: &CornerKey| -> u32 {
+                        *corner_indices.entry(*corner).or_insert_with(|| {
+                            let norm = match corner.norm {
+                                Some(norm) => data.normals[norm],
+                                None if face_normal.is_valid() => face_normal,
+                                None => Vector3::ZERO,
+                            };
+                            vertices.push(Vertex {
+                                pos: data.positions[corner.position],
+                                norm,
+                                color: OBJ_VERTEX_COLOR,
+                                tex: corner
+                                    .tex
+                                    .map(|tex| data.tex_coords[tex])
+                                    .unwrap_or_default(),
+                                ..Default::default()
+                            });
+                            vertices.len() as u32 - 1
+                        })
+                    };
+                    let corners: Vec<u32> = corners.iter().map(corner_index).collect();
+                    // Fan around the first corner keeps the face winding
+                    for edge in corners[1..].windows(2) {
+                        indices.extend_from_slice(&[corners[0], edge[0], edge[1]]);
+                    }
+                    face_count += 1;
+                }
+                _ => {}
+            }
+        }
+        Ok(Mesh { vertices, indices })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+f 1 2 3 4
+";
+
+    fn triangle_normal(mesh: &Mesh, triangle: &[u32]) -> Vector3 {
+        let [a, b, c] = [0, 1, 2].map(|corner| mesh.vertices[triangle[corner] as usize].pos);
+        (b - a).cross(c - a)
+    }
+
+    #[test]
+    fn quad_becomes_two_triangles_with_its_winding() {
+        let mesh = Mesh::parse_obj(QUAD).unwrap();
+        assert_eq!(mesh.vertex_count(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        for triangle in mesh.indices.chunks_exact(3) {
+            let normal = triangle_normal(&mesh, triangle);
+            assert!(normal.z > 0.0);
+        }
+        // Flat shading follows the counter-clockwise winding
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.norm == Vector3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn ngons_and_relative_indices_are_fanned() {
+        let source = "
+v 1 0 0
+v 0.5 0.8 0
+v -0.5 0.8 0
+v -1 0 0
+v -0.5 -0.8 0
+v 0.5 -0.8 0
+f -6 -5 -4 -3 -2 -1
+f 1 2
+";
+        let mesh = Mesh::parse_obj(source).unwrap();
+        assert_eq!(mesh.triangle_count(), 4);
+        for triangle in mesh.indices.chunks_exact(3) {
+            assert_eq!(triangle[0], 0);
+            assert!(triangle_normal(&mesh, triangle).z > 0.0);
+        }
+    }
+
+    #[test]
+    fn concave_face_keeps_winding_and_area() {
+        // Arrow head pointing up, concave at its fourth corner. Fan triangles
+        // overlap, but their signed areas still add up to the polygon area
+        let source = "
+v 0 0 0
+v 2 0 0
+v 1 2 0
+v 1 1 0
+f 1 2 3 4
+";
+        let mesh = Mesh::parse_obj(source).unwrap();
+        let area = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| triangle_normal(&mesh, triangle).z / 2.0)
+            .sum::<f32>();
+        assert!(f32::abs(area - 1.5) < 1e-6);
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|vertex| vertex.norm == Vector3::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        assert!(Mesh::parse_obj("v 0 0 0\nf 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn written_mesh_reads_back() {
+        let mesh = Mesh::sphere_mesh(&crate::physics::Sphere { radius: 1.5 }, 4);
+        let parsed = Mesh::parse_obj(&mesh.write_obj()).unwrap();
+        // Vertices are renumbered in order of first use, so compare corners
+        assert_eq!(parsed.indices.len(), mesh.indices.len());
+        for (&parsed_index, &index) in parsed.indices.iter().zip(&mesh.indices) {
+            let parsed = parsed.vertices[parsed_index as usize];
+            let vertex = mesh.vertices[index as usize];
+            assert!((parsed.pos - vertex.pos).mag() < 1e-6);
+            assert!((parsed.norm - vertex.norm).mag() < 1e-6);
+            assert!((parsed.tex - vertex.tex).mag() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+use super::Mesh;
+use crate::math::types::Vector3;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+const WELD_EPSILON: f32 = 1e-5;
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Quadric([f64; 10]);
+
+struct Collapse {
+    cost: f64,
+    edge: (usize, usize),
+    versions: (u32, u32),
+    target: Vector3,
+}
+
+impl Quadric {
+    fn from_plane(a: f64, b: f64, c: f64, d: f64) -> Self {
+        Self([
+            a * a,
+            a * b,
+            a * c,
+            a * d,
+            b * b,
+            b * c,
+            b * d,
+            c * c,
+            c * d,
+            d * d,
+        ])
+    }
+
+    fn add(self, rhs: Self) -> Self {
+        let mut q = self.0;
+        for (lhs, rhs) in q.iter_mut().zip(rhs.0.iter()) {
+            *lhs += rhs;
+        }
+        Self(q)
+    }
+
+    fn error(&self, p: Vector3) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (p.x as f64, p.y as f64, p.z as f64);
+        q[0] * x * x
+            + 2.0 * q[1] * x * y
+            + 2.0 * q[2] * x * z
+            + 2.0 * q[3] * x
+            + q[4] * y * y
+            + 2.0 * q[5] * y * z
+            + 2.0 * q[6] * y
+            + q[7] * z * z
+            + 2.0 * q[8] * z
+            + q[9]
+    }
+
+    fn optimal(&self) -> Option<Vector3> {
+        let q = &self.0;
+        let det = q[0] * (q[4] * q[7] - q[5] * q[5]) - q[1] * (q[1] * q[7] - q[5] * q[2])
+            + q[2] * (q[1] * q[5] - q[4] * q[2]);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let (bx, by, bz) = (-q[3], -q[6], -q[8]);
+        let x = (bx * (q[4] * q[7] - q[5] * q[5]) - q[1] * (by * q[7] - q[5] * bz)
+            + q[2] * (by * q[5] - q[4] * bz))
+            / det;
+        let y = (q[0] * (by * q[7] - q[5] * bz) - bx * (q[1] * q[7] - q[5] * q[2])
+            + q[2] * (q[1] * bz - by * q[2]))
+            / det;
+        let z = (q[0] * (q[4] * bz - by * q[5]) - q[1] * (q[1] * bz - by * q[2])
+            + bx * (q[1] * q[5] - q[4] * q[2]))
+            / det;
+        Some(Vector3::new(x as f32, y as f32, z as f32))
+    }
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+struct Simplifier {
+    positions: Vec<Vector3>,
+    sources: Vec<usize>,
+    quadrics: Vec<Quadric>,
+    versions: Vec<u32>,
+    removed: Vec<bool>,
+    triangles: Vec<[usize; 3]>,
+    alive: Vec<bool>,
+    adjacency: Vec<Vec<usize>>,
+    live_triangles: usize,
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Self {
+        let mut positions = Vec::new();
+        let mut sources = Vec::new();
+        let mut welded = HashMap::new();
+        let remap: Vec<_> = mesh
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(index, vertex)| {
+                let key = [
+                    (vertex.pos.x / WELD_EPSILON).round() as i64,
+                    (vertex.pos.y / WELD_EPSILON).round() as i64,
+                    (vertex.pos.z / WELD_EPSILON).round() as i64,
+                ];
+                *welded.entry(key).or_insert_with(|| {
+                    positions.push(vertex.pos);
+                    sources.push(index);
+                    positions.len() - 1
+                })
+            })
+            .collect();
+
+        let triangles: Vec<_> = mesh
+            .indices
+            .chunks_exact(3)
+            .map(|face| {
+                [
+                    remap[face[0] as usize],
+                    remap[face[1] as usize],
+                    remap[face[2] as usize],
+                ]
+            })
+            .filter(|&[a, b, c]| a != b && b != c && c != a)
+            .collect();
+
+        let mut quadrics = vec![Quadric::default(); positions.len()];
+        let mut adjacency = vec![Vec::new(); positions.len()];
+        for (index, &[a, b, c]) in triangles.iter().enumerate() {
+            let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+            if normal.mag_squared() > 0.0 {
+                let normal = normal.normalized();
+                let plane = Quadric::from_plane(
+                    normal.x as f64,
+                    normal.y as f64,
+                    normal.z as f64,
+                    -(normal * positions[a]) as f64,
+                );
+                for &vertex in &[a, b, c] {
+                    quadrics[vertex] = quadrics[vertex].add(plane);
+                }
+            }
+            for &vertex in &[a, b, c] {
+                adjacency[vertex].push(index);
+            }
+        }
+
+        Self {
+            versions: vec![0; positions.len()],
+            removed: vec![false; positions.len()],
+            alive: vec![true; triangles.len()],
+            live_triangles: triangles.len(),
+            positions,
+            sources,
+            quadrics,
+            triangles,
+            adjacency,
+        }
+    }
+
+    fn collapse_cost(&self, u: usize, v: usize) -> Collapse {
+        let quadric = self.quadrics[u].add(self.quadrics[v]);
+        let midpoint = (self.positions[u] + self.positions[v]) / 2.0;
+        let mut candidates = vec![self.positions[u], self.positions[v], midpoint];
+        if let Some(optimal) = quadric.optimal() {
+            if optimal.is_valid() {
+                candidates.insert(0, optimal);
+            }
+        }
+        let (cost, target) = candidates.into_iter().map(|p| (quadric.error(p), p)).fold(
+            (f64::MAX, midpoint),
+            |best, candidate| {
+                if candidate.0 < best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            },
+        );
+        Collapse {
+            cost,
+            edge: (u, v),
+            versions: (self.versions[u], self.versions[v]),
+            target,
+        }
+    }
+
+    fn neighbours(&self, vertex: usize) -> HashSet<usize> {
+        self.adjacency[vertex]
+            .iter()
+            .filter(|&&triangle| self.alive[triangle])
+            .flat_map(|&triangle| self.triangles[triangle].iter().copied())
+            .filter(|&other| other != vertex)
+            .collect()
+    }
+
+    fn flips(&self, u: usize, v: usize, target: Vector3) -> bool {
+        for &vertex in &[u, v] {
+            for &triangle in &self.adjacency[vertex] {
+                let face = self.triangles[triangle];
+                if !self.alive[triangle] || (face.contains(&u) && face.contains(&v)) {
+                    continue;
+                }
+                let corners: Vec<_> = face.iter().map(|&i| self.positions[i]).collect();
+                let moved: Vec<_> = face
+                    .iter()
+                    .map(|&i| {
+                        if i == u || i == v {
+                            target
+                        } else {
+                            self.positions[i]
+                        }
+                    })
+                    .collect();
+                let before = (corners[1] - corners[0]).cross(corners[2] - corners[0]);
+                let after = (moved[1] - moved[0]).cross(moved[2] - moved[0]);
+                if before * after <= 0.0 {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    fn collapse(&mut self, u: usize, v: usize, target: Vector3) {
+        self.positions[u] = target;
+        self.quadrics[u] = self.quadrics[u].add(self.quadrics[v]);
+        self.removed[v] = true;
+        self.versions[u] += 1;
+        self.versions[v] += 1;
+
+        let moved = std::mem::take(&mut self.adjacency[v]);
+        for triangle in moved {
+            if !self.alive[triangle] {
+                continue;
+            }
+            let face = &mut self.triangles[triangle];
+            if face.contains(&u) {
+                self.alive[triangle] = false;
+                self.live_triangles -= 1;
+            } else {
+                for index in face.iter_mut() {
+                    if *index == v {
+                        *index = u;
+                    }
+                }
+                self.adjacency[u].push(triangle);
+            }
+        }
+        let alive = &self.alive;
+        self.adjacency[u].retain(|&triangle| alive[triangle]);
+    }
+
+    fn run(&mut self, target_triangle_count: usize) {
+        let mut heap = BinaryHeap::new();
+        for &[a, b, c] in &self.triangles {
+            for &(u, v) in &[(a, b), (b, c), (c, a)] {
+                if u < v {
+                    heap.push(self.collapse_cost(u, v));
+                }
+            }
+        }
+
+        while self.live_triangles > target_triangle_count {
+            let collapse = match heap.pop() {
+                Some(collapse) => collapse,
+                None => break,
+            };
+            let (u, v) = collapse.edge;
+            if self.removed[u]
+                || self.removed[v]
+                || collapse.versions != (self.versions[u], self.versions[v])
+                || self.flips(u, v, collapse.target)
+            {
+                continue;
+            }
+            self.collapse(u, v, collapse.target);
+            for neighbour in self.neighbours(u) {
+                heap.push(self.collapse_cost(u, neighbour));
+            }
+        }
+    }
+
+    fn build(self, source: &Mesh) -> Mesh {
+        let mut remap = vec![None; self.positions.len()];
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (triangle, face) in self.triangles.iter().enumerate() {
+            if !self.alive[triangle] {
+                continue;
+            }
+            for &index in face {
+                let mapped = *remap[index].get_or_insert_with(|| {
+                    let mut vertex = source.vertices[self.sources[index]];
+                    vertex.pos = self.positions[index];
+                    vertex.norm = Vector3::default();
+                    vertices.push(vertex);
+                    vertices.len() - 1
+                });
+                indices.push(mapped as u32);
+            }
+        }
+        for face in indices.chunks_exact(3) {
+            let [a, b, c] = [face[0] as usize, face[1] as usize, face[2] as usize];
+            let normal =
+                (vertices[b].pos - vertices[a].pos).cross(vertices[c].pos - vertices[a].pos);
+            for &index in &[a, b, c] {
+                vertices[index].norm = vertices[index].norm + normal;
+            }
+        }
+        for vertex in &mut vertices {
+            vertex.norm = if vertex.norm.mag_squared() > 0.0 {
+                vertex.norm.normalized()
+            } else {
+                Vector3::new(0.0, 0.0, 1.0)
+            };
+        }
+        Mesh { vertices, indices }
+    }
+}
+
+impl Mesh {
+    pub fn simplify(&self, target_triangle_count: usize) -> Mesh {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.run(target_triangle_count);
+        simplifier.build(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Sphere;
+
+    #[test]
+    fn sphere_simplifies_to_target_triangle_count() {
+        let sphere = Mesh::sphere_mesh(&Sphere { radius: 1.0 }, 12);
+        assert!(sphere.triangle_count() >= 2000);
+        let simplified = sphere.simplify(200);
+        assert_eq!(simplified.triangle_count(), 200);
+        assert!(simplified
+            .indices
+            .iter()
+            .all(|&index| (index as usize) < simplified.vertex_count()));
+        for vertex in &simplified.vertices {
+            assert!(f32::abs(vertex.norm.mag() - 1.0) < 1e-4);
+            // Contractions stay close to the surface and normals point outwards
+            assert!(f32::abs(vertex.pos.mag() - 1.0) < 0.1);
+            assert!(vertex.norm * vertex.pos > 0.0);
+        }
+    }
+
+    #[test]
+    fn target_above_triangle_count_keeps_mesh() {
+        let sphere = Mesh::sphere_mesh(&Sphere { radius: 1.0 }, 2);
+        let simplified = sphere.simplify(sphere.triangle_count() + 10);
+        assert_eq!(simplified.triangle_count(), sphere.triangle_count());
+    }
+}
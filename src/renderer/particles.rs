@@ -0,0 +1,273 @@
+use super::{mesh::Vertex, Mesh};
+use crate::math::types::{Vector3, Vector4};
+use std::f32::consts::PI;
+
+const DEFAULT_PARTICLE_SIZE: f32 = 0.05;
+const DEFAULT_PARTICLE_COLOR: Vector4 = Vector4::new(1.0, 1.0, 1.0, 1.0);
+const MIN_PARTICLE_LIFETIME: f32 = 1e-3;
+// Bounds the work of a single update after a long frame or a rate spike
+const MAX_SPAWNS_PER_UPDATE: usize = 1024;
+
+/// Spawns `rate` particles per second at `position`, each moving along
+/// `initial_velocity` deflected by up to `spread` radians and living for
+/// `lifetime` seconds. At most `MAX_SPAWNS_PER_UPDATE` particles spawn per
+/// update, the rest of a larger burst is dropped.
+#[derive(Debug, Clone, Copy)]
+pub struct Emitter {
+    pub position: Vector3,
+    pub rate: f32,
+    pub initial_velocity: Vector3,
+    pub spread: f32,
+    pub lifetime: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub age: f32,
+    pub color: Vector4,
+}
+
+/// CPU side mesh rebuilt in place every frame, vertex and index storage is
+/// reused between rebuilds.
+#[derive(Debug, Clone)]
+pub struct DynamicMesh {
+    mesh: Mesh,
+}
+
+#[derive(Debug, Clone)]
+pub struct ParticlePool {
+    pub emitter: Emitter,
+    pub particles: Vec<Particle>,
+    pub mesh_data: DynamicMesh,
+    gravity: Vector3,
+    size: f32,
+    color: Vector4,
+    facing: (Vector3, Vector3),
+    pending_spawns: f32,
+    seed: u32,
+}
+
+impl DynamicMesh {
+    pub fn new() -> Self {
+        Self {
+            mesh: Mesh {
+                vertices: Vec::new(),
+                indices: Vec::new(),
+            },
+        }
+    }
+
+    #[inline]
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    fn clear(&mut self) {
+        self.mesh.vertices.clear();
+        self.mesh.indices.clear();
+    }
+
+    fn push_quad(&mut self, center: Vector3, right: Vector3, up: Vector3, color: Vector4) {
+        let base = self.mesh.vertices.len() as u32;
+        let norm = right.cross(up).normalized();
+        for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            self.mesh.vertices.push(Vertex {
+                pos: center + right * x + up * y,
+                norm,
+                color,
+                ..Default::default()
+            });
+        }
+        self.mesh
+            .indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+impl ParticlePool {
+    pub fn new(emitter: Emitter) -> Self {
+        Self {
+            emitter,
+            particles: Vec::new(),
+            mesh_data: DynamicMesh::new(),
+            gravity: Vector3::ZERO,
+            size: DEFAULT_PARTICLE_SIZE,
+            color: DEFAULT_PARTICLE_COLOR,
+            facing: (Vector3::X, Vector3::Z),
+            pending_spawns: 0.0,
+            seed: 0x9e37_79b9,
+        }
+    }
+
+    pub fn with_gravity(self, gravity: Vector3) -> Self {
+        Self { gravity, ..self }
+    }
+
+    pub fn with_size(self, size: f32) -> Self {
+        Self { size, ..self }
+    }
+
+    pub fn with_color(self, color: Vector4) -> Self {
+        Self { color, ..self }
+    }
+
+    pub fn with_seed(self, seed: u32) -> Self {
+        Self {
+            seed: seed.max(1),
+            ..self
+        }
+    }
+
+    /// Orients the quads of the next rebuild, pass the camera right and up
+    /// vectors to keep particles facing the viewer.
+    pub fn set_facing(&mut self, right: Vector3, up: Vector3) {
+        self.facing = (right.normalized(), up.normalized());
+    }
+
+    /// Ages and integrates live particles, removes the expired ones, spawns
+    /// new ones for the elapsed time and rebuilds `mesh_data`.
+    pub fn update(&mut self, dt: f32) {
+        let lifetime = self.lifetime();
+        let gravity = self.gravity;
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity = particle.velocity + gravity * dt;
+            particle.position = particle.position + particle.velocity * dt;
+        }
+        self.particles.retain(|particle| particle.age < lifetime);
+
+        self.pending_spawns += self.emitter.rate.max(0.0) * dt;
+        let spawns = usize::min(self.pending_spawns as usize, MAX_SPAWNS_PER_UPDATE);
+        self.pending_spawns = if spawns == MAX_SPAWNS_PER_UPDATE {
+            self.pending_spawns.fract()
+        } else {
+            self.pending_spawns - spawns as f32
+        };
+        for _ in 0..spawns {
+            let particle = self.spawn();
+            self.particles.push(particle);
+        }
+        self.rebuild_mesh();
+    }
+
+    // Non positive lifetimes would divide the fade by zero
+    fn lifetime(&self) -> f32 {
+        self.emitter.lifetime.max(MIN_PARTICLE_LIFETIME)
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let speed = self.emitter.initial_velocity.mag();
+        let velocity = if speed > 0.0 {
+            // Uniform direction within the cone of half angle `spread`
+            let (axis, u, v) = self.emitter.initial_velocity.ortho();
+            let cos_theta = 1.0 - self.random() * (1.0 - f32::cos(self.emitter.spread));
+            let sin_theta = f32::sqrt(1.0 - cos_theta * cos_theta);
+            let (sin_phi, cos_phi) = f32::sin_cos(2.0 * PI * self.random());
+            (axis * cos_theta + (u * cos_phi + v * sin_phi) * sin_theta) * speed
+        } else {
+            Vector3::ZERO
+        };
+        Particle {
+            position: self.emitter.position,
+            velocity,
+            age: 0.0,
+            color: self.color,
+        }
+    }
+
+    fn rebuild_mesh(&mut self) {
+        let (right, up) = self.facing;
+        let (right, up) = (right * self.size, up * self.size);
+        let lifetime = self.lifetime();
+        self.mesh_data.clear();
+        for particle in &self.particles {
+            let fade = 1.0 - particle.age / lifetime;
+            let color = Vector4::new(
+                particle.color.x,
+                particle.color.y,
+                particle.color.z,
+                particle.color.w * fade,
+            );
+            self.mesh_data
+                .push_quad(particle.position, right, up, color);
+        }
+    }
+
+    // xorshift32, uniform in [0, 1)
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        (self.seed >> 8) as f32 / (1u32 << 24) as f32
+    }
+}
+
+impl Default for DynamicMesh {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter(rate: f32, lifetime: f32) -> Emitter {
+        Emitter {
+            position: Vector3::ZERO,
+            rate,
+            initial_velocity: Vector3::new(0.0, 0.0, 1.0),
+            spread: 0.5,
+            lifetime,
+        }
+    }
+
+    #[test]
+    fn update_ages_and_removes_particles() {
+        let mut pool = ParticlePool::new(emitter(10.0, 1.0));
+        pool.update(0.5);
+        assert_eq!(pool.particles.len(), 5);
+        assert!(pool.particles.iter().all(|particle| particle.age == 0.0));
+        assert_eq!(pool.mesh_data.mesh().vertices.len(), 4 * 5);
+
+        pool.emitter.rate = 0.0;
+        pool.update(0.25);
+        assert!(pool.particles.iter().all(|particle| particle.age == 0.25));
+        assert!(pool
+            .particles
+            .iter()
+            .all(|particle| particle.position.z > 0.0));
+        let fade = pool.mesh_data.mesh().vertices[0].color.w;
+        assert!(f32::abs(fade - 0.75) < 1e-6);
+
+        pool.update(1.0);
+        assert!(pool.particles.is_empty());
+        assert!(pool.mesh_data.mesh().indices.is_empty());
+    }
+
+    #[test]
+    fn spawns_are_capped_per_update() {
+        let mut pool = ParticlePool::new(emitter(1e6, 10.0));
+        pool.update(1.0);
+        assert_eq!(pool.particles.len(), MAX_SPAWNS_PER_UPDATE);
+        // The dropped burst is not carried into later updates
+        pool.emitter.rate = 0.0;
+        pool.update(0.0);
+        assert_eq!(pool.particles.len(), MAX_SPAWNS_PER_UPDATE);
+    }
+
+    #[test]
+    fn zero_lifetime_keeps_colors_finite() {
+        let mut pool = ParticlePool::new(emitter(100.0, 0.0));
+        pool.update(0.1);
+        pool.update(0.0);
+        assert!(pool
+            .mesh_data
+            .mesh()
+            .vertices
+            .iter()
+            .all(|vertex| vertex.color.w.is_finite()));
+    }
+}
@@ -0,0 +1,134 @@
+use super::mesh::Vertex;
+use crate::math::types::{Matrix4, Vector3, Vector4};
+use bytemuck::{Pod, Zeroable};
+
+pub const MAX_JOINT_INFLUENCES: usize = 4;
+
+/// Bind pose vertex with up to four joint influences, the layout read by the
+/// skinning compute shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SkinnedVertex {
+    pub vertex: Vertex,
+    pub joints: [u32; MAX_JOINT_INFLUENCES],
+    pub weights: Vector4,
+}
+
+unsafe impl Zeroable for SkinnedVertex {}
+unsafe impl Pod for SkinnedVertex {}
+
+/// Joint matrices map bind pose space to the posed mesh space, i.e. the
+/// joint world transform already multiplied by its inverse bind matrix.
+#[derive(Debug, Clone)]
+pub struct Skeleton {
+    joints: Vec<Matrix4>,
+}
+
+impl Skeleton {
+    pub fn new(joint_count: usize) -> Self {
+        Self {
+            joints: vec![Matrix4::IDENTITY; joint_count],
+        }
+    }
+
+    pub fn from_joints(joints: Vec<Matrix4>) -> Self {
+        Self { joints }
+    }
+
+    #[inline]
+    pub fn joints(&self) -> &[Matrix4] {
+        &self.joints
+    }
+
+    #[inline]
+    pub fn joints_mut(&mut self) -> &mut [Matrix4] {
+        &mut self.joints
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.joints.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.joints.is_empty()
+    }
+
+    /// CPU reference of the skinning shader, influences referencing joints
+    /// outside of the skeleton are ignored.
+    pub fn skin(&self, skinned: &SkinnedVertex) -> Vertex {
+        let weights = [
+            skinned.weights.x,
+            skinned.weights.y,
+            skinned.weights.z,
+            skinned.weights.w,
+        ];
+        let mut skin = Matrix4::ZERO;
+        for (&joint, &weight) in skinned.joints.iter().zip(&weights) {
+            if let Some(matrix) = self.joints.get(joint as usize) {
+                skin = skin + *matrix * weight;
+            }
+        }
+        let vertex = skinned.vertex;
+        // Generated meshes leave tangents zeroed, keep them that way
+        let direction = |v: Vector3| {
+            let v = skin.transform_direction(v);
+            if v.mag_squared() > 0.0 {
+                v.normalized()
+            } else {
+                v
+            }
+        };
+        let tang = direction(vertex.tang.xyz());
+        Vertex {
+            pos: skin.transform_point(vertex.pos),
+            norm: direction(vertex.norm),
+            tang: Vector4::new(tang.x, tang.y, tang.z, vertex.tang.w),
+            ..vertex
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::transforms::translate;
+
+    fn skinned_vertex(joints: [u32; MAX_JOINT_INFLUENCES], weights: Vector4) -> SkinnedVertex {
+        SkinnedVertex {
+            vertex: Vertex {
+                pos: Vector3::new(1.0, 2.0, 3.0),
+                norm: Vector3::new(0.0, 1.0, 0.0),
+                tang: Vector4::new(1.0, 0.0, 0.0, -1.0),
+                color: Vector4::new(0.2, 0.4, 0.6, 1.0),
+                ..Default::default()
+            },
+            joints,
+            weights,
+        }
+    }
+
+    #[test]
+    fn identity_skeleton_keeps_bind_pose() {
+        let skinned = skinned_vertex([0, 1, 0, 0], Vector4::new(0.25, 0.75, 0.0, 0.0));
+        let vertex = Skeleton::new(2).skin(&skinned);
+        assert_eq!(vertex.pos, skinned.vertex.pos);
+        assert_eq!(vertex.norm, skinned.vertex.norm);
+        assert_eq!(vertex.tang, skinned.vertex.tang);
+        assert_eq!(vertex.color, skinned.vertex.color);
+    }
+
+    #[test]
+    fn weights_blend_joint_transforms() {
+        let skeleton = Skeleton::from_joints(vec![
+            Matrix4::IDENTITY,
+            translate(Vector3::new(2.0, 0.0, 0.0)),
+        ]);
+        // The third influence references a missing joint and is ignored
+        let skinned = skinned_vertex([0, 1, 7, 0], Vector4::new(0.5, 0.5, 1.0, 0.0));
+        let vertex = skeleton.skin(&skinned);
+        assert_eq!(vertex.pos, Vector3::new(2.0, 2.0, 3.0));
+        assert_eq!(vertex.norm, skinned.vertex.norm);
+    }
+}
@@ -0,0 +1,263 @@
+use super::mesh::Vertex;
+use crate::{
+    math::types::{Vector2, Vector3, Vector4},
+    utils::StaticResult,
+};
+use std::{collections::HashMap, fs, path::Path};
+
+const FALLBACK_GLYPH: char = '?';
+
+/// Glyph of the atlas in atlas pixels, `offset` places the glyph quad
+/// relative to the pen position at the top of the line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyph {
+    pub position: Vector2,
+    pub size: Vector2,
+    pub offset: Vector2,
+    pub advance: f32,
+}
+
+/// Signed distance field font, a single channel atlas where 0.5 marks the
+/// glyph outline, together with the glyph layout table describing it.
+#[derive(Debug, Clone)]
+pub struct TextRenderer {
+    glyphs: HashMap<char, Glyph>,
+    line_height: f32,
+    atlas_width: u32,
+    atlas_height: u32,
+    atlas: Vec<u8>,
+}
+
+fn parse_attributes(fields: &[&str]) -> HashMap<String, f32> {
+    fields
+        .iter()
+        .filter_map(|field| {
+            let mut parts = field.splitn(2, '=');
+            let key = parts.next()?;
+            let value = parts.next()?.parse().ok()?;
+            Some((key.to_owned(), value))
+        })
+        .collect()
+}
+
+// Binary PGM: "P5", width, height and max value separated by whitespace,
+// followed by one byte per pixel
+fn parse_pgm(bytes: &[u8]) -> Option<(u32, u32, Vec<u8>)> {
+    let mut header = [0u32; 3];
+    let mut cursor = 2;
+    if !bytes.starts_with(b"P5") {
+        return None;
+    }
+    for value in &mut header {
+        while bytes.get(cursor)?.is_ascii_whitespace() {
+            cursor += 1;
+        }
+        let start = cursor;
+        while bytes.get(cursor)?.is_ascii_digit() {
+            cursor += 1;
+        }
+        *value = std::str::from_utf8(&bytes[start..cursor])
+            .ok()?
+            .parse()
+            .ok()?;
+    }
+    let [width, height, max_value] = header;
+    let pixels = bytes.get(cursor + 1..)?;
+    if max_value > 255 || pixels.len() != (width * height) as usize {
+        return None;
+    }
+    Some((width, height, pixels.to_vec()))
+}
+
+impl TextRenderer {
+    /// Loads a glyph layout table in the BMFont text format and its atlas as
+    /// an 8 bit binary PGM image.
+    pub fn load(layout_path: &Path, atlas_path: &Path) -> StaticResult<Self> {
+        let layout = fs::read_to_string(layout_path)?;
+        let (width, height, atlas) = parse_pgm(&fs::read(atlas_path)?)
+            .ok_or_else(|| format!("Failed to load {}: not a binary PGM", atlas_path.display()))?;
+        TextRenderer::parse(&layout, atlas, width, height)
+            .map_err(|err| format!("Failed to load {}: {}", layout_path.display(), err).into())
+    }
+
+    /// Reads the `common` and `char` entries of a BMFont text layout table,
+    /// other entries such as kerning pairs are ignored.
+    pub fn parse(layout: &str, atlas: Vec<u8>, width: u32, height: u32) -> StaticResult<Self> {
+        if atlas.len() != (width * height) as usize {
+            Err(format!(
+                "Atlas holds {} bytes, expected {} for {}x{}",
+                atlas.len(),
+                width * height,
+                width,
+                height
+            ))?;
+        }
+        let mut glyphs = HashMap::new();
+        let mut line_height = None;
+        for (number, line) in layout.lines().enumerate() {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let attributes = parse_attributes(fields.get(1..).unwrap_or(&[]));
+            let get = |key: &str| {
+                attributes
+                    .get(key)
+                    .copied()
+                    .ok_or_else(|| format!("Missing {} at line {}", key, number + 1))
+            };
+            match fields.first() {
+                Some(&"common") => line_height = Some(get("lineHeight")?),
+                Some(&"char") => {
+                    let id = get("id")? as u32;
+                    let glyph = Glyph {
+                        position: Vector2::new(get("x")?, get("y")?),
+                        size: Vector2::new(get("width")?, get("height")?),
+                        offset: Vector2::new(get("xoffset")?, get("yoffset")?),
+                        advance: get("xadvance")?,
+                    };
+                    if let Some(code) = std::char::from_u32(id) {
+                        glyphs.insert(code, glyph);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let line_height = line_height.ok_or("Missing common entry with lineHeight")?;
+        if line_height <= 0.0 {
+            Err("Line height must be positive")?;
+        }
+        Ok(Self {
+            glyphs,
+            line_height,
+            atlas_width: width,
+            atlas_height: height,
+            atlas,
+        })
+    }
+
+    #[inline]
+    pub fn atlas(&self) -> &[u8] {
+        &self.atlas
+    }
+
+    #[inline]
+    pub fn atlas_extent(&self) -> (u32, u32) {
+        (self.atlas_width, self.atlas_height)
+    }
+
+    #[inline]
+    pub fn glyph(&self, code: char) -> Option<&Glyph> {
+        self.glyphs.get(&code)
+    }
+
+    /// Two triangles per visible character, in window pixels with the origin
+    /// at the top left corner. `position` is the top left corner of the first
+    /// line and `size` the line height in pixels. Characters missing from the
+    /// atlas are drawn as '?' when the atlas has it and skipped otherwise.
+    pub fn layout(&self, text: &str, position: Vector2, size: f32, color: Vector3) -> Vec<Vertex> {
+        let scale = size / self.line_height;
+        let color = Vector4::new(color.x, color.y, color.z, 1.0);
+        let atlas_size = Vector2::new(self.atlas_width as f32, self.atlas_height as f32);
+        let mut vertices = Vec::with_capacity(6 * text.len());
+        let mut pen = position;
+        for code in text.chars() {
+            if code == '\n' {
+                pen = Vector2::new(position.x, pen.y + size);
+                continue;
+            }
+            let glyph = match self.glyph(code).or_else(|| self.glyph(FALLBACK_GLYPH)) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if glyph.size.x > 0.0 && glyph.size.y > 0.0 {
+                let min = pen + glyph.offset * scale;
+                let max = min + glyph.size * scale;
+                let uv_min = Vector2::new(
+                    glyph.position.x / atlas_size.x,
+                    glyph.position.y / atlas_size.y,
+                );
+                let uv_max = Vector2::new(
+                    (glyph.position.x + glyph.size.x) / atlas_size.x,
+                    (glyph.position.y + glyph.size.y) / atlas_size.y,
+                );
+                let corner = |x: bool, y: bool| Vertex {
+                    pos: Vector3::new(
+                        if x { max.x } else { min.x },
+                        if y { max.y } else { min.y },
+                        0.0,
+                    ),
+                    color,
+                    tex: Vector2::new(
+                        if x { uv_max.x } else { uv_min.x },
+                        if y { uv_max.y } else { uv_min.y },
+                    ),
+                    ..Default::default()
+                };
+                let (top_left, top_right) = (corner(false, false), corner(true, false));
+                let (bottom_left, bottom_right) = (corner(false, true), corner(true, true));
+                vertices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    bottom_right,
+                    top_left,
+                    bottom_right,
+                    top_right,
+                ]);
+            }
+            pen.x += glyph.advance * scale;
+        }
+        vertices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAYOUT: &str = "info face=Test size=32\n\
+        common lineHeight=32 base=26 scaleW=64 scaleH=32\n\
+        char id=65 x=0 y=0 width=16 height=20 xoffset=1 yoffset=4 xadvance=14\n\
+        char id=63 x=16 y=0 width=12 height=20 xoffset=2 yoffset=4 xadvance=12\n\
+        char id=32 x=0 y=0 width=0 height=0 xoffset=0 yoffset=0 xadvance=8\n";
+
+    fn font() -> TextRenderer {
+        TextRenderer::parse(LAYOUT, vec![0; 64 * 32], 64, 32).unwrap()
+    }
+
+    #[test]
+    fn parse_reads_glyphs() {
+        let font = font();
+        assert_eq!(font.atlas_extent(), (64, 32));
+        assert_eq!(
+            font.glyph('A'),
+            Some(&Glyph {
+                position: Vector2::new(0.0, 0.0),
+                size: Vector2::new(16.0, 20.0),
+                offset: Vector2::new(1.0, 4.0),
+                advance: 14.0,
+            })
+        );
+        assert!(TextRenderer::parse(LAYOUT, vec![0; 16], 64, 32).is_err());
+    }
+
+    #[test]
+    fn layout_places_glyph_quads() {
+        let vertices = font().layout("A A\nB", Vector2::new(100.0, 50.0), 64.0, Vector3::ZERO);
+        // Space has no quad, 'B' falls back to '?'
+        assert_eq!(vertices.len(), 3 * 6);
+        let top_left = |quad: usize| vertices[6 * quad].pos;
+        assert_eq!(top_left(0), Vector3::new(102.0, 58.0, 0.0));
+        assert_eq!(
+            top_left(1),
+            Vector3::new(102.0 + 2.0 * (14.0 + 8.0), 58.0, 0.0)
+        );
+        assert_eq!(top_left(2), Vector3::new(104.0, 50.0 + 64.0 + 8.0, 0.0));
+        assert_eq!(vertices[6 * 2].tex, Vector2::new(0.25, 0.0));
+    }
+
+    #[test]
+    fn parse_pgm_reads_binary_header() {
+        let mut bytes = b"P5\n2 2\n255\n".to_vec();
+        bytes.extend_from_slice(&[0, 64, 128, 255]);
+        assert_eq!(parse_pgm(&bytes), Some((2, 2, vec![0, 64, 128, 255])));
+        assert_eq!(parse_pgm(b"P2\n2 2\n255\n0 0 0 0"), None);
+    }
+}
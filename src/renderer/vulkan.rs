@@ -1,7 +1,8 @@
-use ash::{self, vk};
+use ash::{self, extensions::ext, vk};
 use winit::window::Window;
 
 use std::ffi::CStr;
+use std::fmt;
 use std::os::raw::c_char;
 
 mod debug;
@@ -11,30 +12,45 @@ mod surface;
 use device::{Device, Frame};
 use surface::Surface;
 
-use crate::math::types::Matrix4;
-use crate::renderer::{MeshHandle, Renderer};
-use crate::utils::StaticResult;
+use crate::math::types::{Matrix4, Vector2, Vector3, Vector4};
+use crate::physics::Shape;
+use crate::renderer::{
+    Capabilities, ColorMode, ColorSpace, Config, CullMode, DebugLine, MeshHandle, Renderer,
+    Skeleton, SkinnedVertex, StencilCompare, TextRenderer, TextureFormat, TextureHandle,
+};
+use crate::utils::{ResultExt, StaticResult};
 
-use super::{Camera, Mesh};
+use super::immediate::{ShapeKey, ShapeMeshCache};
+use super::{Camera, FrameClock, FrameInfo, Mesh};
 
 struct Instance {
     instance: ash::Instance,
     entry: ash::Entry,
+    validation: bool,
 }
 
 pub struct Backend {
     current_frame: Option<Frame>,
+    frame_info: FrameInfo,
+    clock: FrameClock,
+    shape_cache: ShapeMeshCache,
+    font: Option<TextRenderer>,
     device: Device,
     surface: Surface,
-    messenger: debug::Messenger,
+    messenger: Option<debug::Messenger>,
     instance: Instance,
 }
 
 impl Instance {
-    fn new(window: &Window) -> StaticResult<Self> {
+    fn new(window: &Window, config: &Config) -> StaticResult<Self> {
         let entry = unsafe { ash::Entry::new()? };
+        let supported_layers = entry.enumerate_instance_layer_properties()?;
+        let (required_layers, mut validation_extensions) =
+            debug::validation_requirements(config.validation, &supported_layers);
+        let validation = !required_layers.is_empty();
+
         let mut required_extensions: Vec<_> = ash_window::enumerate_required_extensions(window)?;
-        required_extensions.append(&mut debug::required_extensions());
+        required_extensions.append(&mut validation_extensions);
 
         let supported_extensions = entry.enumerate_instance_extension_properties()?;
         for &req in &required_extensions {
@@ -47,18 +63,6 @@ impl Instance {
                 ))?;
         }
 
-        let required_layers = debug::required_layers();
-        let supported_layers = entry.enumerate_instance_layer_properties()?;
-        for &req in &required_layers {
-            supported_layers
-                .iter()
-                .find(|layer| unsafe { CStr::from_ptr(&layer.layer_name as *const c_char) } == req)
-                .ok_or(format!(
-                    "Required Vulkan layer [{}] not supported",
-                    req.to_str().unwrap_or("UTF8 PARSE ERROR")
-                ))?;
-        }
-
         let required_extensions: Vec<_> =
             required_extensions.iter().map(|ext| ext.as_ptr()).collect();
 
@@ -69,18 +73,21 @@ impl Instance {
             ..Default::default()
         };
 
-        let instance = unsafe {
-            entry.create_instance(
-                &vk::InstanceCreateInfo::builder()
-                    .application_info(&app_info)
-                    .enabled_extension_names(&required_extensions)
-                    .enabled_layer_names(&required_layers)
-                    .push_next(debug::MessengerBuilder::new().as_mut()),
-                None,
-            )?
-        };
+        let mut messenger_builder = debug::MessengerBuilder::new();
+        let mut create_info = vk::InstanceCreateInfo::builder()
+            .application_info(&app_info)
+            .enabled_extension_names(&required_extensions)
+            .enabled_layer_names(&required_layers);
+        if validation {
+            create_info = create_info.push_next(messenger_builder.as_mut());
+        }
+        let instance = unsafe { entry.create_instance(&create_info, None)? };
 
-        Ok(Self { instance, entry })
+        Ok(Self {
+            instance,
+            entry,
+            validation,
+        })
     }
 }
 
@@ -97,11 +104,28 @@ impl Drop for Instance {
 }
 
 impl Backend {
-    pub fn new(window: &Window, meshes: &[Mesh]) -> StaticResult<Self> {
-        let instance = Instance::new(window)?;
-        let messenger = debug::MessengerBuilder::new().build(&instance.entry, instance.as_ref())?;
+    pub fn new(window: &Window, meshes: &[Mesh], config: &Config) -> StaticResult<Self> {
+        let instance = Instance::new(window, config)?;
+        let messenger = if instance.validation {
+            Some(debug::MessengerBuilder::new().build(&instance.entry, instance.as_ref())?)
+        } else {
+            None
+        };
         let surface = Surface::new(&instance.entry, instance.as_ref(), window)?;
-        let device = Device::new(instance.as_ref(), &surface, meshes)?;
+        let size = window.inner_size();
+        let device = Device::new(
+            instance.as_ref(),
+            &surface,
+            meshes,
+            vk::Extent2D {
+                width: size.width,
+                height: size.height,
+            },
+            config,
+            instance
+                .validation
+                .then(|| ext::DebugUtils::new(&instance.entry, instance.as_ref())),
+        )?;
 
         Ok(Self {
             device,
@@ -109,16 +133,49 @@ impl Backend {
             messenger,
             instance,
             current_frame: None,
+            frame_info: FrameInfo::default(),
+            clock: FrameClock::default(),
+            shape_cache: ShapeMeshCache::default(),
+            font: None,
         })
     }
 }
 
+impl fmt::Debug for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Backend")
+            .field("device", &self.device.name())
+            .field(
+                "current_frame",
+                &self.current_frame.as_ref().map(Frame::image_index),
+            )
+            .field("validation", &self.instance.validation)
+            .finish()
+    }
+}
+
 impl Renderer for Backend {
-    fn begin_frame(&mut self, camera: &Camera) -> StaticResult<()> {
+    fn begin_frame(&mut self, camera: &Camera) -> StaticResult<FrameInfo> {
         if self.current_frame.is_none() {
-            self.current_frame = Some(self.device.begin_frame(&camera.matrix())?);
+            let device = &mut self.device;
+            self.shape_cache
+                .upload_pending(|mesh| device.upload_mesh(mesh))?;
+            let frame = match self.device.begin_frame(&camera.matrix(), camera.eye()) {
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    let extent = self.device.extent();
+                    self.device.recreate_swapchain(
+                        self.instance.as_ref(),
+                        &self.surface,
+                        extent,
+                    )?;
+                    self.device.begin_frame(&camera.matrix(), camera.eye())?
+                }
+                frame => frame?,
+            };
+            self.frame_info = self.clock.frame_info(frame.image_index());
+            self.current_frame = Some(frame);
         }
-        Ok(())
+        Ok(self.frame_info)
     }
     fn draw(&mut self, mesh: MeshHandle, world: &Matrix4) {
         if self.current_frame.is_some() {
@@ -126,9 +183,154 @@ impl Renderer for Backend {
             self.device.draw(frame, mesh, world)
         }
     }
+    fn draw_transparent(&mut self, mesh: MeshHandle, world: &Matrix4, alpha: f32) {
+        if self.current_frame.is_some() {
+            self.device.draw_transparent(mesh, world, alpha)
+        }
+    }
+    fn upload_mesh(&mut self, mesh: &Mesh) -> StaticResult<MeshHandle> {
+        if self.current_frame.is_some() {
+            Err("Cannot upload a mesh while a frame is being recorded")?;
+        }
+        self.device.upload_mesh(mesh)
+    }
+    fn upload_skinned_mesh(
+        &mut self,
+        vertices: &[SkinnedVertex],
+        indices: &[u32],
+    ) -> StaticResult<MeshHandle> {
+        if self.current_frame.is_some() {
+            Err("Cannot upload a mesh while a frame is being recorded")?;
+        }
+        self.device.upload_skinned_mesh(vertices, indices)
+    }
+    fn skin_mesh(&mut self, mesh: MeshHandle, skeleton: &Skeleton) -> StaticResult<()> {
+        if self.current_frame.is_some() {
+            Err("Cannot skin a mesh while a frame is being recorded")?;
+        }
+        self.device.skin_uploaded_mesh(mesh, skeleton)
+    }
+    fn read_depth(&mut self, x: u32, y: u32) -> StaticResult<f32> {
+        if self.current_frame.is_some() {
+            Err("Cannot read back depth while a frame is being recorded")?;
+        }
+        self.device.read_depth(x, y)
+    }
+    fn capabilities(&self) -> Capabilities {
+        self.device.capabilities()
+    }
+    fn load_texture_3d(
+        &mut self,
+        data: &[f32],
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: TextureFormat,
+    ) -> StaticResult<TextureHandle> {
+        if self.current_frame.is_some() {
+            Err("Cannot upload a texture while a frame is being recorded")?;
+        }
+        self.device
+            .load_texture_3d(data, width, height, depth, format.into())
+            .with_context(|| {
+                format!(
+                    "Failed to load {}x{}x{} {:?} texture",
+                    width, height, depth, format
+                )
+            })
+    }
+    fn draw_line(&mut self, line: &DebugLine) {
+        if self.current_frame.is_some() {
+            self.device.draw_line(line)
+        }
+    }
+    fn load_font(&mut self, font: TextRenderer) -> StaticResult<()> {
+        if self.current_frame.is_some() {
+            Err("Cannot load a font while a frame is being recorded")?;
+        }
+        let (width, height) = font.atlas_extent();
+        self.device
+            .load_font_atlas(font.atlas(), width, height)
+            .context("Failed to load font atlas")?;
+        self.font = Some(font);
+        Ok(())
+    }
+    fn draw_text(&mut self, text: &str, position: Vector2, size: f32, color: Vector3) {
+        if let (Some(_), Some(font)) = (&self.current_frame, &self.font) {
+            self.device
+                .draw_text(&font.layout(text, position, size, color));
+        }
+    }
+    fn draw_shape_immediate(&mut self, shape: &Shape, transform: &Matrix4, color: Vector4) {
+        if self.current_frame.is_none() {
+            return;
+        }
+        let key = ShapeKey::new(shape, color);
+        match self.shape_cache.get(&key) {
+            Some(mesh) => self.draw(mesh, transform),
+            None => self.shape_cache.request(key, shape, color),
+        }
+    }
+    fn set_cull_mode(&mut self, mode: CullMode) -> StaticResult<()> {
+        self.device
+            .set_cull_mode(self.current_frame.as_ref(), mode)?;
+        Ok(())
+    }
+    fn set_depth(&mut self, test: bool, write: bool) -> StaticResult<()> {
+        self.device
+            .set_depth(self.current_frame.as_ref(), test, write)?;
+        Ok(())
+    }
+    fn set_depth_bias(&mut self, enabled: bool) -> StaticResult<()> {
+        self.device
+            .set_depth_bias_enabled(self.current_frame.as_ref(), enabled)?;
+        Ok(())
+    }
+    fn set_color_mode(&mut self, mode: ColorMode, albedo: Vector4) -> StaticResult<()> {
+        self.device
+            .set_color_mode(self.current_frame.as_ref(), mode, albedo)?;
+        Ok(())
+    }
+    fn set_clear_color(&mut self, color: Vector4, space: ColorSpace) {
+        self.device.set_clear_color(color, space)
+    }
+    fn set_stencil_op(
+        &mut self,
+        compare: Option<StencilCompare>,
+        write_mask: u32,
+        reference: u32,
+    ) -> StaticResult<()> {
+        if let Some(frame) = self.current_frame.as_ref() {
+            self.device
+                .cmd_set_stencil_op(frame.command(), compare, write_mask, reference)?;
+        }
+        Ok(())
+    }
+    fn resize(&mut self, width: u32, height: u32) -> StaticResult<()> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        if let Some(frame) = self.current_frame.take() {
+            self.device.end_frame(frame)?;
+            self.clock.end_frame();
+        }
+        self.device.recreate_swapchain(
+            self.instance.as_ref(),
+            &self.surface,
+            vk::Extent2D { width, height },
+        )?;
+        Ok(())
+    }
     fn end_frame(&mut self) -> StaticResult<()> {
         if self.current_frame.is_some() {
-            self.device.end_frame(self.current_frame.take().unwrap())?;
+            let result = self.device.end_frame(self.current_frame.take().unwrap());
+            self.clock.end_frame();
+            let swapchain_stale = result?;
+            if swapchain_stale {
+                let extent = self.device.extent();
+                self.device
+                    .recreate_swapchain(self.instance.as_ref(), &self.surface, extent)?;
+            }
         }
         Ok(())
     }
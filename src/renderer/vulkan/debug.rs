@@ -1,7 +1,9 @@
 use std::convert::AsRef;
 use std::ffi::{c_void, CStr};
 use std::ops::{Deref, DerefMut};
+use std::os::raw::c_char;
 
+use crate::log::{self, Level};
 use crate::utils::StaticResult;
 use ash::{extensions::ext, vk, Entry, Instance};
 
@@ -20,12 +22,12 @@ unsafe extern "system" fn vulkan_debug_callback(
     data: *const vk::DebugUtilsMessengerCallbackDataEXT,
     _user_data: *mut c_void,
 ) -> vk::Bool32 {
-    let message_severity = match message_severity {
-        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => "[ERROR]",
-        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => "[WARNING]",
-        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => "[INFO]",
-        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => "[VERBOSE]",
-        _ => "[UNKNOWN]",
+    let (level, message_severity) = match message_severity {
+        vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => (Level::Error, "[ERROR]"),
+        vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => (Level::Warning, "[WARNING]"),
+        vk::DebugUtilsMessageSeverityFlagsEXT::INFO => (Level::Info, "[INFO]"),
+        vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => (Level::Verbose, "[VERBOSE]"),
+        _ => (Level::Verbose, "[UNKNOWN]"),
     };
     let message_type = match message_type {
         vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "[GENERAL]",
@@ -34,21 +36,62 @@ unsafe extern "system" fn vulkan_debug_callback(
         _ => "[UNKNOWN]",
     };
     let message = CStr::from_ptr((*data).p_message);
-    println!("[Debug]{}{}{:?}", message_severity, message_type, message);
+    log::log(
+        level,
+        &format!("[Debug]{}{}{:?}", message_severity, message_type, message),
+    );
     vk::FALSE
 }
 
-pub fn required_layers() -> Vec<&'static CStr> {
+pub fn required_layers(validation: bool) -> Vec<&'static CStr> {
+    if !validation {
+        return vec![];
+    }
     REQUIRED_VALIDATION_LAYERS
         .iter()
         .map(|&layer| CStr::from_bytes_with_nul(layer).unwrap())
         .collect()
 }
 
-pub fn required_extensions() -> Vec<&'static CStr> {
+pub fn available_layers(
+    required: Vec<&'static CStr>,
+    supported: &[vk::LayerProperties],
+) -> Vec<&'static CStr> {
+    let (available, missing): (Vec<_>, Vec<_>) = required.into_iter().partition(|&req| {
+        supported
+            .iter()
+            .any(|layer| unsafe { CStr::from_ptr(&layer.layer_name as *const c_char) } == req)
+    });
+    for layer in missing {
+        log::warning(&format!(
+            "Vulkan layer [{}] not supported, continuing without validation",
+            layer.to_str().unwrap_or("UTF8 PARSE ERROR")
+        ));
+    }
+    if available.len() < REQUIRED_VALIDATION_LAYERS.len() {
+        return vec![];
+    }
+    available
+}
+
+pub fn required_extensions(validation: bool) -> Vec<&'static CStr> {
+    if !validation {
+        return vec![];
+    }
     vec![ext::DebugUtils::name()]
 }
 
+/// Layers and extensions of the validation setup, both empty when validation
+/// is disabled or its layer is missing so the instance is created without it.
+pub fn validation_requirements(
+    validation: bool,
+    supported: &[vk::LayerProperties],
+) -> (Vec<&'static CStr>, Vec<&'static CStr>) {
+    let layers = available_layers(required_layers(validation), supported);
+    let extensions = required_extensions(!layers.is_empty());
+    (layers, extensions)
+}
+
 impl MessengerBuilder {
     pub fn new() -> Self {
         let info = vk::DebugUtilsMessengerCreateInfoEXT {
@@ -85,3 +128,48 @@ impl Drop for Messenger {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(name: &[u8]) -> vk::LayerProperties {
+        let mut properties = vk::LayerProperties::default();
+        for (dst, &src) in properties.layer_name.iter_mut().zip(name) {
+            *dst = src as c_char;
+        }
+        properties
+    }
+
+    #[test]
+    fn disabled_validation_requires_no_layers() {
+        assert!(required_layers(false).is_empty());
+        assert!(required_extensions(false).is_empty());
+        let required = required_layers(true);
+        assert_eq!(required.len(), 1);
+        assert_eq!(required[0].to_bytes(), b"VK_LAYER_KHRONOS_validation");
+    }
+
+    #[test]
+    fn layers_are_kept_only_when_supported() {
+        let validation = layer(b"VK_LAYER_KHRONOS_validation\0");
+        let other = layer(b"VK_LAYER_MESA_overlay\0");
+        assert_eq!(
+            available_layers(required_layers(true), &[other, validation]),
+            required_layers(true)
+        );
+        assert!(available_layers(required_layers(true), &[other]).is_empty());
+        assert!(available_layers(required_layers(true), &[]).is_empty());
+        assert!(available_layers(required_layers(false), &[validation]).is_empty());
+    }
+
+    #[test]
+    fn missing_layer_starts_without_validation() {
+        let other = layer(b"VK_LAYER_MESA_overlay\0");
+        assert_eq!(validation_requirements(true, &[other]), (vec![], vec![]));
+        let validation = layer(b"VK_LAYER_KHRONOS_validation\0");
+        let (layers, extensions) = validation_requirements(true, &[validation]);
+        assert_eq!(layers, required_layers(true));
+        assert_eq!(extensions, vec![ext::DebugUtils::name()]);
+    }
+}
@@ -1,37 +1,75 @@
-use crate::renderer::Mesh;
-use crate::{math::types::Matrix4, renderer::MeshHandle};
-use ash::{prelude::VkResult, vk, Instance};
+use crate::renderer::{
+    mesh::Vertex, Capabilities, ColorMode, ColorSpace, Config, CullMode, DebugLine, DepthBias,
+    DepthFormat, Mesh, StencilCompare,
+};
+use crate::{
+    math::types::{Matrix4, Vector3, Vector4},
+    renderer::MeshHandle,
+};
+use ash::{extensions::ext, prelude::VkResult, vk, Instance};
 
+mod allocator;
 mod buffer;
 mod command;
+mod image;
 mod layout;
+mod lines;
+mod material;
+mod naming;
+mod partial;
 mod pipeline;
+mod readback;
 mod render_pass;
+mod skinning;
 mod swapchain;
+mod text;
 
-use buffer::MeshData;
+use allocator::{Allocation, Allocator};
+use buffer::{ColorTransfer, MeshData, StagingBuffer};
 use command::CommandType;
+use image::Texture;
 use layout::Layout;
-use pipeline::Pipeline;
+use lines::LineBuffer;
+use material::MaterialRing;
+use pipeline::{Pipeline, PipelineState};
+use skinning::{SkinnedMesh, SkinningPipeline};
 pub use swapchain::Frame;
-use swapchain::Swapchain;
+use swapchain::{PresentMonitor, PresentStatus, Swapchain};
+use text::TextPipeline;
 
 use std::{
-    collections::HashSet, ffi::CStr, iter::FromIterator, mem::size_of, os::raw::c_char, slice,
+    collections::HashSet, ffi::CStr, fmt, iter::FromIterator, mem::size_of, os::raw::c_char, slice,
 };
 
 use super::Surface;
-use crate::utils::StaticResult;
+#[cfg(feature = "profile")]
+use crate::utils::profiler;
+use crate::{
+    log,
+    utils::{ResultExt, StaticResult},
+};
 
-const PREFERRED_SURFACE_FORMATS: &'static [vk::Format] =
-    &[vk::Format::R8G8B8A8_UNORM, vk::Format::B8G8R8A8_UNORM];
+const PREFERRED_SURFACE_FORMATS: &'static [vk::Format] = &[
+    vk::Format::R8G8B8A8_SRGB,
+    vk::Format::B8G8R8A8_SRGB,
+    vk::Format::R8G8B8A8_UNORM,
+    vk::Format::B8G8R8A8_UNORM,
+];
+// Surface encoded, the clear color used before `set_clear_color` is called
+const DEFAULT_CLEAR_COLOR: Vector4 = Vector4::new(0.1, 0.1, 0.1, 1.0);
 const PREFERRED_DEPTH_FORMATS: &'static [vk::Format] = &[
     vk::Format::D32_SFLOAT,
     vk::Format::D24_UNORM_S8_UINT,
     vk::Format::D16_UNORM,
     vk::Format::D16_UNORM_S8_UINT,
 ];
+const PREFERRED_DEPTH_STENCIL_FORMATS: &[vk::Format] = &[
+    vk::Format::D24_UNORM_S8_UINT,
+    vk::Format::D32_SFLOAT_S8_UINT,
+    vk::Format::D16_UNORM_S8_UINT,
+];
 
+#[derive(Debug)]
 pub struct Queues {
     pub graphics: vk::Queue,
     pub compute: vk::Queue,
@@ -39,12 +77,14 @@ pub struct Queues {
     pub present: vk::Queue,
 }
 
+#[derive(Debug)]
 struct CommandPools {
     graphics: vk::CommandPool,
     compute: vk::CommandPool,
     transfer: vk::CommandPool,
 }
 
+#[derive(Debug)]
 pub struct QueueFamilies {
     pub graphics: u32,
     pub compute: u32,
@@ -52,10 +92,12 @@ pub struct QueueFamilies {
     pub present: u32,
 }
 
+#[derive(Debug)]
 pub struct PhysicalDeviceConfig {
     pub device: vk::PhysicalDevice,
     pub queue_families: QueueFamilies,
     pub depth_format: vk::Format,
+    pub sample_count: vk::SampleCountFlags,
     pub present_mode: vk::PresentModeKHR,
     pub surface_format: vk::SurfaceFormatKHR,
     pub surface_capabilities: vk::SurfaceCapabilitiesKHR,
@@ -64,16 +106,75 @@ pub struct PhysicalDeviceConfig {
     pub memory_properties: vk::PhysicalDeviceMemoryProperties,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct TransparentDraw {
+    mesh: MeshHandle,
+    world: Matrix4,
+    alpha: f32,
+}
+
+struct PartialDevice {
+    device: Option<ash::Device>,
+    allocator: Allocator,
+    command_pools: Vec<vk::CommandPool>,
+    render_pass: Option<vk::RenderPass>,
+    swapchain: Option<Swapchain>,
+    layout: Option<Layout>,
+    pipeline: Option<Pipeline>,
+    mesh_data: Option<MeshData>,
+    staging_buffer: Option<StagingBuffer>,
+}
+
+impl PhysicalDeviceConfig {
+    pub fn has_stencil(&self) -> bool {
+        matches!(
+            self.depth_format,
+            vk::Format::D16_UNORM_S8_UINT
+                | vk::Format::D24_UNORM_S8_UINT
+                | vk::Format::D32_SFLOAT_S8_UINT
+        )
+    }
+
+    pub fn depth_aspect(&self) -> vk::ImageAspectFlags {
+        if self.has_stencil() {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        } else {
+            vk::ImageAspectFlags::DEPTH
+        }
+    }
+}
+
 pub struct Device {
     device: ash::Device,
+    allocator: Allocator,
     queues: Queues,
     command_pools: CommandPools,
     render_pass: vk::RenderPass,
     swapchain: Swapchain,
     layout: Layout,
     pipeline: Pipeline,
+    pipeline_state: PipelineState,
     config: PhysicalDeviceConfig,
     mesh_data: MeshData,
+    staging_buffer: StagingBuffer,
+    textures: Vec<Texture>,
+    skinning: Option<SkinningPipeline>,
+    skinned_meshes: Vec<SkinnedMesh>,
+    present_monitor: PresentMonitor,
+    shadow_depth_bias: Option<DepthBias>,
+    color_transfer: ColorTransfer,
+    line_vertices: Vec<Vertex>,
+    line_buffers: Vec<Option<LineBuffer>>,
+    text: Option<TextPipeline>,
+    text_vertices: Vec<Vertex>,
+    text_buffers: Vec<Option<LineBuffer>>,
+    transparent_draws: Vec<TransparentDraw>,
+    camera_position: Vector3,
+    color_mode: ColorMode,
+    albedo: Vector4,
+    materials: Vec<MaterialRing>,
+    clear_color: Vector4,
+    debug_utils: Option<ext::DebugUtils>,
 }
 
 impl Device {
@@ -81,18 +182,35 @@ impl Device {
         instance: &Instance,
         surface: &Surface,
         meshes: &[Mesh],
+        extent: vk::Extent2D,
+        renderer_config: &Config,
+        debug_utils: Option<ext::DebugUtils>,
     ) -> StaticResult<Self> {
         let devices = unsafe { instance.enumerate_physical_devices()? };
-        let config = devices
+        let mut config = devices
             .into_iter()
-            .find_map(|device| Device::is_suitable(device, instance, surface))
+            .find_map(|device| {
+                Device::is_suitable(device, instance, surface, renderer_config.stencil)
+            })
             .ok_or(format!("Failed to pick suitable physical device"))?;
 
-        println!("Chosen Vulkan physical device name: [{}]", unsafe {
-            CStr::from_ptr(&config.properties.device_name as *const c_char)
-                .to_str()
-                .unwrap_or("UTF8 PARSE ERROR")
-        });
+        log::info(&format!(
+            "Chosen Vulkan physical device name: [{}]",
+            unsafe {
+                CStr::from_ptr(&config.properties.device_name as *const c_char)
+                    .to_str()
+                    .unwrap_or("UTF8 PARSE ERROR")
+            }
+        ));
+
+        config.sample_count = Device::supported_sample_count(&config, renderer_config.msaa_samples);
+        if config.sample_count.as_raw() != renderer_config.msaa_samples {
+            log::warning(&format!(
+                "Requested {}x MSAA is not supported, using {}x",
+                renderer_config.msaa_samples,
+                config.sample_count.as_raw()
+            ));
+        }
 
         let queue_familites = &config.queue_families;
         let queue_infos: Vec<_> = HashSet::<u32>::from_iter([
@@ -123,8 +241,12 @@ impl Device {
                     .enabled_extension_names(&required_extensions)
                     .enabled_features(&config.enabled_features),
                 None,
-            )?
-        };
+            )
+        }
+        .context("Failed to create logical device")?;
+        let mut partial = PartialDevice::new(device);
+        let allocator = partial.allocator.clone();
+        let device = partial.device.as_ref().unwrap();
 
         let queues = unsafe {
             Queues {
@@ -135,53 +257,129 @@ impl Device {
             }
         };
 
-        let command_pools = unsafe {
-            CommandPools {
-                graphics: device.create_command_pool(
-                    &vk::CommandPoolCreateInfo::builder()
-                        .queue_family_index(config.queue_families.graphics)
-                        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
-                    None,
-                )?,
-                compute: device.create_command_pool(
-                    &vk::CommandPoolCreateInfo::builder()
-                        .queue_family_index(config.queue_families.compute)
-                        .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER),
-                    None,
-                )?,
-                transfer: device.create_command_pool(
-                    &vk::CommandPoolCreateInfo::builder()
-                        .queue_family_index(config.queue_families.transfer)
-                        .flags(vk::CommandPoolCreateFlags::TRANSIENT),
-                    None,
-                )?,
-            }
+        let create_pool = |queue_family_index, flags| unsafe {
+            device.create_command_pool(
+                &vk::CommandPoolCreateInfo::builder()
+                    .queue_family_index(queue_family_index)
+                    .flags(flags),
+                None,
+            )
+        };
+        partial.command_pools.push(create_pool(
+            config.queue_families.graphics,
+            vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+        )?);
+        partial.command_pools.push(create_pool(
+            config.queue_families.compute,
+            vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+        )?);
+        partial.command_pools.push(create_pool(
+            config.queue_families.transfer,
+            vk::CommandPoolCreateFlags::TRANSIENT,
+        )?);
+        let command_pools = CommandPools {
+            graphics: partial.command_pools[0],
+            compute: partial.command_pools[1],
+            transfer: partial.command_pools[2],
         };
 
-        let render_pass = Device::create_render_pass(&device, &config)?;
-        let swapchain =
-            Device::create_swapchain(instance, &device, &config, surface.handle, render_pass)?;
-        let layout = Device::create_layout(&device)?;
-        let pipeline = Device::create_pipeline(&device, &layout, &swapchain, render_pass)?;
-        let mesh_data = Device::load_mesh_data(&device, &config, &command_pools, &queues, meshes)?;
+        let render_pass = *partial.render_pass.insert(Device::create_render_pass(
+            device,
+            &config,
+            config.sample_count,
+        )?);
+        partial.swapchain = Some(
+            Device::create_swapchain(
+                instance,
+                device,
+                &config,
+                &allocator,
+                surface.handle,
+                render_pass,
+                extent,
+            )
+            .context("Failed to create swapchain")?,
+        );
+        partial.layout = Some(Device::create_layout(
+            device,
+            layout::PUSH_CONSTANT_SIZE,
+            config.properties.limits.max_push_constants_size,
+        )?);
+        partial.pipeline = Some(
+            Device::create_pipeline(
+                device,
+                config.sample_count,
+                renderer_config.shaders.as_ref(),
+            )
+            .context("Failed to create graphics pipeline")?,
+        );
+        let pipeline_state = PipelineState::default();
+        Device::name_object(
+            device,
+            debug_utils.as_ref(),
+            render_pass,
+            vk::ObjectType::RENDER_PASS,
+            "Device::render_pass",
+        );
+        let color_transfer =
+            ColorTransfer::new(config.surface_format.format, renderer_config.linear_colors);
+        let (mesh_data, staging_buffer) = Device::load_mesh_data(
+            device,
+            &config,
+            &allocator,
+            &command_pools,
+            &queues,
+            meshes,
+            color_transfer,
+        )
+        .context("Failed to upload scene meshes")?;
+        partial.mesh_data = Some(mesh_data);
+        partial.staging_buffer = Some(staging_buffer);
 
-        Ok(Self {
+        let (device, swapchain, layout, pipeline, mesh_data, staging_buffer) = partial.finish();
+        let mut device = Self {
             device,
+            allocator,
             queues,
             command_pools,
             render_pass,
             swapchain,
             layout,
             pipeline,
+            pipeline_state,
             config,
             mesh_data,
-        })
+            staging_buffer,
+            textures: Vec::new(),
+            skinning: None,
+            skinned_meshes: Vec::new(),
+            present_monitor: PresentMonitor::default(),
+            shadow_depth_bias: renderer_config.shadow_depth_bias,
+            color_transfer,
+            line_vertices: Vec::new(),
+            line_buffers: Vec::new(),
+            text: None,
+            text_vertices: Vec::new(),
+            text_buffers: Vec::new(),
+            transparent_draws: Vec::new(),
+            camera_position: Vector3::ZERO,
+            color_mode: ColorMode::VertexColor,
+            albedo: color_transfer.apply(Vector4::new(0.8, 0.8, 0.8, 1.0)),
+            materials: Vec::new(),
+            clear_color: DEFAULT_CLEAR_COLOR,
+            debug_utils,
+        };
+        device.name_swapchain_objects();
+        device.name_mesh_buffer();
+        device.pipeline_variant(pipeline_state)?;
+        Ok(device)
     }
 
     fn is_suitable(
         device: vk::PhysicalDevice,
         instance: &Instance,
         surface: &Surface,
+        stencil: bool,
     ) -> Option<PhysicalDeviceConfig> {
         let properties = unsafe { instance.get_physical_device_properties(device) };
         match properties.device_type {
@@ -199,15 +397,23 @@ impl Device {
             .unwrap_or(vk::PresentModeKHR::FIFO);
         let queue_families = Device::queue_families(device, instance, surface)?;
         let surface_formats = surface.device_surface_formats(device).ok()?;
-        let &surface_format = surface_formats
+        let &surface_format = PREFERRED_SURFACE_FORMATS
             .iter()
-            .find(|format| PREFERRED_SURFACE_FORMATS.contains(&format.format))
-            .unwrap_or(surface_formats.first().unwrap());
+            .find_map(|&preferred| {
+                surface_formats
+                    .iter()
+                    .find(|format| format.format == preferred)
+            })
+            .unwrap_or(surface_formats.first()?);
         let surface_capabilities = surface.device_surface_capabilities(device).ok()?;
         let depth_format = Device::supported_image_format(
             instance,
             device,
-            PREFERRED_DEPTH_FORMATS,
+            if stencil {
+                PREFERRED_DEPTH_STENCIL_FORMATS
+            } else {
+                PREFERRED_DEPTH_FORMATS
+            },
             vk::ImageTiling::OPTIMAL,
             vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT,
         )?;
@@ -220,6 +426,7 @@ impl Device {
             present_mode,
             surface_capabilities,
             depth_format,
+            sample_count: vk::SampleCountFlags::TYPE_1,
             memory_properties,
             enabled_features,
             properties,
@@ -253,6 +460,27 @@ impl Device {
         None
     }
 
+    fn supported_sample_count(
+        config: &PhysicalDeviceConfig,
+        requested: u32,
+    ) -> vk::SampleCountFlags {
+        let limits = &config.properties.limits;
+        let supported =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        [
+            vk::SampleCountFlags::TYPE_64,
+            vk::SampleCountFlags::TYPE_32,
+            vk::SampleCountFlags::TYPE_16,
+            vk::SampleCountFlags::TYPE_8,
+            vk::SampleCountFlags::TYPE_4,
+            vk::SampleCountFlags::TYPE_2,
+        ]
+        .iter()
+        .copied()
+        .find(|&count| count.as_raw() <= requested && supported.contains(count))
+        .unwrap_or(vk::SampleCountFlags::TYPE_1)
+    }
+
     fn queue_families(
         device: vk::PhysicalDevice,
         instance: &Instance,
@@ -317,37 +545,60 @@ impl Device {
         }
     }
 
-    fn features_supported(
-        device: vk::PhysicalDevice,
-        instance: &Instance,
-    ) -> Option<vk::PhysicalDeviceFeatures> {
-        let required = Device::required_features();
-        let supported = unsafe { instance.get_physical_device_features(device) };
+    fn optional_features() -> vk::PhysicalDeviceFeatures {
+        vk::PhysicalDeviceFeatures {
+            multi_draw_indirect: vk::TRUE,
+            fill_mode_non_solid: vk::TRUE,
+            wide_lines: vk::TRUE,
+            ..Default::default()
+        }
+    }
+
+    fn feature_flags(features: &mut vk::PhysicalDeviceFeatures) -> &mut [vk::Bool32] {
         let num_features = size_of::<vk::PhysicalDeviceFeatures>() / size_of::<vk::Bool32>();
-        let required_slice = unsafe {
-            slice::from_raw_parts::<vk::Bool32>(
-                std::mem::transmute::<*const vk::PhysicalDeviceFeatures, *const vk::Bool32>(
-                    &required,
-                ),
-                num_features,
-            )
-        };
-        let supported_slice = unsafe {
-            slice::from_raw_parts::<vk::Bool32>(
-                std::mem::transmute::<*const vk::PhysicalDeviceFeatures, *const vk::Bool32>(
-                    &supported,
-                ),
+        unsafe {
+            slice::from_raw_parts_mut(
+                features as *mut vk::PhysicalDeviceFeatures as *mut vk::Bool32,
                 num_features,
             )
-        };
-        for (&sup, &req) in supported_slice.iter().zip(required_slice) {
-            if req == vk::TRUE && sup != req {
+        }
+    }
+
+    fn negotiate_features(
+        mut required: vk::PhysicalDeviceFeatures,
+        mut optional: vk::PhysicalDeviceFeatures,
+        mut supported: vk::PhysicalDeviceFeatures,
+    ) -> Option<vk::PhysicalDeviceFeatures> {
+        let supported = Device::feature_flags(&mut supported);
+        let optional = Device::feature_flags(&mut optional);
+        let enabled = Device::feature_flags(&mut required);
+        for ((enabled, &opt), &sup) in enabled
+            .iter_mut()
+            .zip(optional.iter())
+            .zip(supported.iter())
+        {
+            if *enabled == vk::TRUE && sup != vk::TRUE {
                 return None;
             }
+            if opt == vk::TRUE && sup == vk::TRUE {
+                *enabled = vk::TRUE;
+            }
         }
         Some(required)
     }
 
+    fn features_supported(
+        device: vk::PhysicalDevice,
+        instance: &Instance,
+    ) -> Option<vk::PhysicalDeviceFeatures> {
+        let supported = unsafe { instance.get_physical_device_features(device) };
+        Device::negotiate_features(
+            Device::required_features(),
+            Device::optional_features(),
+            supported,
+        )
+    }
+
     fn memory_type_index(
         config: &PhysicalDeviceConfig,
         types: u32,
@@ -362,8 +613,16 @@ impl Device {
         None
     }
 
-    pub fn begin_frame(&mut self, camera_matrix: &Matrix4) -> VkResult<Frame> {
+    pub fn begin_frame(
+        &mut self,
+        camera_matrix: &Matrix4,
+        camera_position: Vector3,
+    ) -> VkResult<Frame> {
+        #[cfg(feature = "profile")]
+        let _scope = profiler::Profiler::begin_scope("Device::begin_frame");
+        self.camera_position = camera_position;
         let frame = self.swapchain.acquire_image(&self.device)?;
+        let pipeline = self.pipeline_variant(self.pipeline_state)?;
 
         unsafe {
             self.device.cmd_begin_render_pass(
@@ -373,14 +632,19 @@ impl Device {
                     .framebuffer(frame.framebuffer)
                     .clear_values(&[
                         vk::ClearValue {
-                            depth_stencil: vk::ClearDepthStencilValue {
-                                depth: 1.0f32,
-                                stencil: 0u32,
+                            color: vk::ClearColorValue {
+                                float32: [
+                                    self.clear_color.x,
+                                    self.clear_color.y,
+                                    self.clear_color.z,
+                                    self.clear_color.w,
+                                ],
                             },
                         },
                         vk::ClearValue {
-                            color: vk::ClearColorValue {
-                                float32: [0.1f32, 0.1f32, 0.1f32, 1.0f32],
+                            depth_stencil: vk::ClearDepthStencilValue {
+                                depth: 1.0f32,
+                                stencil: 0u32,
                             },
                         },
                     ])
@@ -390,31 +654,214 @@ impl Device {
                     }),
                 vk::SubpassContents::INLINE,
             );
-            self.device.cmd_bind_pipeline(
+            self.device
+                .cmd_bind_pipeline(frame.command, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            self.device.cmd_set_viewport(
                 frame.command,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline.pipeline,
+                0,
+                &[vk::Viewport {
+                    width: self.swapchain.extent.width as f32,
+                    height: -(self.swapchain.extent.height as f32),
+                    x: 0.0f32,
+                    y: self.swapchain.extent.height as f32,
+                    min_depth: 0.0f32,
+                    max_depth: 1.0f32,
+                }],
             );
+            self.device.cmd_set_scissor(
+                frame.command,
+                0,
+                &[vk::Rect2D {
+                    offset: vk::Offset2D { x: 0, y: 0 },
+                    extent: self.swapchain.extent,
+                }],
+            );
+            if self.pipeline_state.depth_bias {
+                let bias = self.shadow_depth_bias.unwrap_or(DepthBias {
+                    constant: 0.0,
+                    slope: 0.0,
+                });
+                self.set_depth_bias(frame.command, bias.constant, bias.slope);
+            }
 
             self.device.cmd_push_constants(
                 frame.command,
                 self.layout.pipeline_layout,
-                vk::ShaderStageFlags::VERTEX,
+                layout::PUSH_CONSTANT_STAGES,
                 layout::CAMERA_PUSH_OFFSET,
                 bytemuck::bytes_of(camera_matrix),
             )
         }
-        Device::bind_buffers(&self.device, frame.command, &self.mesh_data);
+        self.reset_materials(frame.image_index as usize);
+        self.bind_material(&frame, 1.0)?;
         Ok(frame)
     }
 
+    pub fn recreate_swapchain(
+        &mut self,
+        instance: &Instance,
+        surface: &Surface,
+        extent: vk::Extent2D,
+    ) -> VkResult<()> {
+        unsafe { self.device.device_wait_idle()? };
+        self.config.surface_capabilities =
+            surface.device_surface_capabilities(self.config.device)?;
+        Device::destroy_swapchain(&self.device, &mut self.swapchain);
+        self.swapchain = Device::create_swapchain(
+            instance,
+            &self.device,
+            &self.config,
+            &self.allocator,
+            surface.handle,
+            self.render_pass,
+            extent,
+        )?;
+        self.name_swapchain_objects();
+        Ok(())
+    }
+
+    pub fn set_cull_mode(&mut self, frame: Option<&Frame>, cull_mode: CullMode) -> VkResult<()> {
+        self.pipeline_state.cull_mode = cull_mode;
+        let pipeline = self.pipeline_variant(self.pipeline_state)?;
+        if let Some(frame) = frame {
+            unsafe {
+                self.device.cmd_bind_pipeline(
+                    frame.command,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Disabling the test draws later geometry over earlier geometry whatever
+    /// its depth, transparent draws never write depth either way.
+    pub fn set_depth(&mut self, frame: Option<&Frame>, test: bool, write: bool) -> VkResult<()> {
+        self.pipeline_state.depth_test = test;
+        self.pipeline_state.depth_write = write;
+        let pipeline = self.pipeline_variant(self.pipeline_state)?;
+        if let Some(frame) = frame {
+            unsafe {
+                self.device.cmd_bind_pipeline(
+                    frame.command,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Offsets the depth of subsequent draws by the configured shadow depth
+    /// bias, through the pipeline variant with dynamic depth bias state.
+    pub fn set_depth_bias_enabled(&mut self, frame: Option<&Frame>, enabled: bool) -> VkResult<()> {
+        let bias = match self.shadow_depth_bias {
+            Some(bias) => bias,
+            None if enabled => {
+                log::warning("Depth bias requested, but no shadow depth bias is configured");
+                return Ok(());
+            }
+            None => return Ok(()),
+        };
+        self.pipeline_state.depth_bias = enabled;
+        let pipeline = self.pipeline_variant(self.pipeline_state)?;
+        if let Some(frame) = frame {
+            unsafe {
+                self.device.cmd_bind_pipeline(
+                    frame.command,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline,
+                );
+            }
+            if enabled {
+                self.set_depth_bias(frame.command, bias.constant, bias.slope);
+            }
+        }
+        Ok(())
+    }
+
+    /// Stored in the encoding of the surface format, takes effect when the
+    /// next frame begins its render pass.
+    pub fn set_clear_color(&mut self, color: Vector4, space: ColorSpace) {
+        self.clear_color =
+            ColorTransfer::for_space(self.config.surface_format.format, space).apply(color);
+    }
+
+    pub fn set_color_mode(
+        &mut self,
+        frame: Option<&Frame>,
+        mode: ColorMode,
+        albedo: Vector4,
+    ) -> VkResult<()> {
+        self.color_mode = mode;
+        self.albedo = self.color_transfer.apply(albedo);
+        if let Some(frame) = frame {
+            self.bind_material(frame, 1.0)?;
+        }
+        Ok(())
+    }
+
+    pub fn cmd_set_stencil_op(
+        &mut self,
+        command: vk::CommandBuffer,
+        compare: Option<StencilCompare>,
+        write_mask: u32,
+        reference: u32,
+    ) -> VkResult<()> {
+        if !self.config.has_stencil() {
+            log::warning("Stencil operation requested, but the depth buffer has no stencil aspect");
+            return Ok(());
+        }
+        self.pipeline_state.stencil_compare = compare;
+        let pipeline = self.pipeline_variant(self.pipeline_state)?;
+        unsafe {
+            self.device
+                .cmd_bind_pipeline(command, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            if compare.is_some() {
+                let face = vk::StencilFaceFlags::FRONT_AND_BACK;
+                self.device
+                    .cmd_set_stencil_compare_mask(command, face, u32::MAX);
+                self.device
+                    .cmd_set_stencil_write_mask(command, face, write_mask);
+                self.device
+                    .cmd_set_stencil_reference(command, face, reference);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn upload_mesh(&mut self, mesh: &Mesh) -> StaticResult<MeshHandle> {
+        unsafe { self.device.device_wait_idle()? };
+        let handle = self.append_mesh_data(mesh)?;
+        self.name_mesh_buffer();
+        Ok(handle)
+    }
+
     pub fn draw(&mut self, frame: &Frame, mesh: MeshHandle, world: &Matrix4) {
-        let offsets = &self.mesh_data.mesh_offsets[mesh.0];
+        if !mesh.is_valid() {
+            log::warning("Skipping draw of invalid mesh handle");
+            return;
+        }
+        debug_assert!(
+            mesh.0 < self.mesh_data.mesh_offsets.len(),
+            "Mesh handle {} out of bounds",
+            mesh.0
+        );
+        let offsets = match self.mesh_data.mesh_offsets.get(mesh.0) {
+            Some(offsets) => offsets,
+            None => {
+                log::warning(&format!("Skipping draw of unknown mesh handle {}", mesh.0));
+                return;
+            }
+        };
+        // Index type varies per mesh, and line drawing rebinds the vertex buffer
+        Device::bind_buffers(&self.device, frame.command, &self.mesh_data, offsets);
         unsafe {
             self.device.cmd_push_constants(
                 frame.command,
                 self.layout.pipeline_layout,
-                vk::ShaderStageFlags::VERTEX,
+                layout::PUSH_CONSTANT_STAGES,
                 layout::WORLD_PUSH_OFFSET,
                 bytemuck::bytes_of(world),
             );
@@ -422,14 +869,148 @@ impl Device {
                 frame.command,
                 offsets.index_count as u32,
                 1,
-                offsets.index_offset as u32,
+                0,
                 offsets.vertex_offset as i32,
                 0,
             );
         }
     }
 
-    pub fn end_frame(&mut self, frame: Frame) -> VkResult<()> {
+    pub fn draw_transparent(&mut self, mesh: MeshHandle, world: &Matrix4, alpha: f32) {
+        self.transparent_draws.push(TransparentDraw {
+            mesh,
+            world: *world,
+            alpha: alpha.clamp(0.0, 1.0),
+        });
+    }
+
+    fn flush_transparent(&mut self, frame: &Frame) -> VkResult<()> {
+        if self.transparent_draws.is_empty() {
+            return Ok(());
+        }
+        let camera_position = self.camera_position;
+        let distance = |draw: &TransparentDraw| {
+            (draw.world.transform_point(Vector3::ZERO) - camera_position).mag_squared()
+        };
+        let mut draws = std::mem::take(&mut self.transparent_draws);
+        draws.sort_by(|a, b| {
+            distance(b)
+                .partial_cmp(&distance(a))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let pipeline = self.pipeline_variant(PipelineState {
+            blend: true,
+            ..self.pipeline_state
+        })?;
+        unsafe {
+            self.device
+                .cmd_bind_pipeline(frame.command, vk::PipelineBindPoint::GRAPHICS, pipeline);
+        }
+        for draw in draws.drain(..) {
+            self.bind_material(frame, draw.alpha)?;
+            self.draw(frame, draw.mesh, &draw.world);
+        }
+        self.transparent_draws = draws;
+        Ok(())
+    }
+
+    pub fn draw_line(&mut self, line: &DebugLine) {
+        let color = self.color_transfer.apply(line.color);
+        for &pos in &[line.start, line.end] {
+            self.line_vertices.push(Vertex {
+                pos,
+                color,
+                ..Default::default()
+            });
+        }
+    }
+
+    fn flush_lines(&mut self, frame: &Frame) -> VkResult<()> {
+        if self.line_vertices.is_empty() {
+            return Ok(());
+        }
+        let buffer = Device::upload_lines(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            &mut self.line_buffers,
+            frame.image_index as usize,
+            &self.line_vertices,
+        )?;
+        let pipeline = self.pipeline_variant(PipelineState {
+            lines: true,
+            ..self.pipeline_state
+        })?;
+        unsafe {
+            self.device
+                .cmd_bind_pipeline(frame.command, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            self.device.cmd_push_constants(
+                frame.command,
+                self.layout.pipeline_layout,
+                layout::PUSH_CONSTANT_STAGES,
+                layout::WORLD_PUSH_OFFSET,
+                bytemuck::bytes_of(&Matrix4::IDENTITY),
+            );
+            self.device
+                .cmd_bind_vertex_buffers(frame.command, 0, &[buffer], &[0]);
+            self.device
+                .cmd_draw(frame.command, self.line_vertices.len() as u32, 1, 0, 0);
+        }
+        self.line_vertices.clear();
+        Ok(())
+    }
+
+    pub fn set_depth_bias(&self, command: vk::CommandBuffer, constant: f32, slope_scale: f32) {
+        unsafe {
+            self.device
+                .cmd_set_depth_bias(command, constant, 0.0, slope_scale);
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        unsafe {
+            CStr::from_ptr(&self.config.properties.device_name as *const c_char)
+                .to_str()
+                .unwrap_or("UTF8 PARSE ERROR")
+        }
+    }
+
+    pub fn push_constant_budget(&self) -> u32 {
+        self.config.properties.limits.max_push_constants_size
+    }
+
+    pub fn capabilities(&self) -> Capabilities {
+        let features = &self.config.enabled_features;
+        Capabilities {
+            max_msaa_samples: Device::supported_sample_count(&self.config, u32::MAX).as_raw(),
+            msaa_samples: self.config.sample_count.as_raw(),
+            depth_format: match self.config.depth_format {
+                vk::Format::D16_UNORM => DepthFormat::D16,
+                vk::Format::D16_UNORM_S8_UINT => DepthFormat::D16S8,
+                vk::Format::D24_UNORM_S8_UINT => DepthFormat::D24S8,
+                vk::Format::D32_SFLOAT_S8_UINT => DepthFormat::D32S8,
+                _ => DepthFormat::D32,
+            },
+            max_push_constant_size: self.push_constant_budget(),
+            fill_mode_non_solid: features.fill_mode_non_solid == vk::TRUE,
+            wide_lines: features.wide_lines == vk::TRUE,
+            multi_draw_indirect: features.multi_draw_indirect == vk::TRUE,
+        }
+    }
+
+    pub fn extent(&self) -> vk::Extent2D {
+        self.swapchain.extent
+    }
+
+    /// Returns true when the swapchain has to be recreated before the next
+    /// frame, after the surface went out of date or suboptimal (window
+    /// resize, fullscreen transition) or the present mode fell back to FIFO.
+    pub fn end_frame(&mut self, frame: Frame) -> VkResult<bool> {
+        #[cfg(feature = "profile")]
+        let _scope = profiler::Profiler::begin_scope("Device::end_frame");
+        self.flush_lines(&frame)?;
+        self.flush_transparent(&frame)?;
+        self.flush_text(&frame)?;
         unsafe {
             self.device.cmd_end_render_pass(frame.command);
             self.device.end_command_buffer(frame.command)?;
@@ -444,8 +1025,119 @@ impl Device {
                 frame.available,
             )?;
         }
-        let _suboptimal = self.swapchain.present_image(frame, self.queues.present)?;
-        Ok(())
+        let status = self.swapchain.present_image(frame, self.queues.present)?;
+        if self.present_monitor.record(status)
+            && self.config.present_mode == vk::PresentModeKHR::MAILBOX
+        {
+            log::warning(
+                "Repeated suboptimal presentation with MAILBOX present mode, falling back to FIFO",
+            );
+            self.config.present_mode = vk::PresentModeKHR::FIFO;
+            return Ok(true);
+        }
+        Ok(status != PresentStatus::Optimal)
+    }
+}
+
+impl PartialDevice {
+    fn new(device: ash::Device) -> Self {
+        Self {
+            device: Some(device),
+            allocator: Allocator::new(),
+            command_pools: vec![],
+            render_pass: None,
+            swapchain: None,
+            layout: None,
+            pipeline: None,
+            mesh_data: None,
+            staging_buffer: None,
+        }
+    }
+
+    fn finish(
+        mut self,
+    ) -> (
+        ash::Device,
+        Swapchain,
+        Layout,
+        Pipeline,
+        MeshData,
+        StagingBuffer,
+    ) {
+        self.command_pools.clear();
+        self.render_pass = None;
+        (
+            self.device.take().unwrap(),
+            self.swapchain.take().unwrap(),
+            self.layout.take().unwrap(),
+            self.pipeline.take().unwrap(),
+            self.mesh_data.take().unwrap(),
+            self.staging_buffer.take().unwrap(),
+        )
+    }
+}
+
+impl Drop for PartialDevice {
+    fn drop(&mut self) {
+        let device = match self.device.take() {
+            Some(device) => device,
+            None => return,
+        };
+        log::warning("Device creation failed, destroying partially created resources");
+        unsafe {
+            device.device_wait_idle().ok();
+        }
+        if let Some(staging_buffer) = &mut self.staging_buffer {
+            Device::destroy_staging_buffer(&device, staging_buffer);
+        }
+        if let Some(mesh_data) = &mut self.mesh_data {
+            Device::destory_mesh_data(&device, mesh_data);
+        }
+        if let Some(pipeline) = &mut self.pipeline {
+            Device::destory_pipeline(&device, pipeline);
+        }
+        if let Some(layout) = &mut self.layout {
+            Device::destory_layout(&device, layout);
+        }
+        if let Some(swapchain) = &mut self.swapchain {
+            Device::destroy_swapchain(&device, swapchain);
+        }
+        unsafe {
+            if let Some(render_pass) = self.render_pass {
+                device.destroy_render_pass(render_pass, None);
+            }
+            for &pool in self.command_pools.iter().rev() {
+                device.destroy_command_pool(pool, None);
+            }
+        }
+        self.allocator.destroy(&device);
+        unsafe {
+            device.destroy_device(None);
+        }
+    }
+}
+
+impl fmt::Debug for Device {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Device")
+            .field("name", &self.name())
+            .field("extent", &self.swapchain.extent)
+            .field("present_mode", &self.config.present_mode)
+            .field("pipeline_state", &self.pipeline_state)
+            .field("queue_families", &self.config.queue_families)
+            .field("push_constant_budget", &self.push_constant_budget())
+            .field("memory_blocks", &self.allocator.block_count())
+            .field("allocations", &self.allocator.allocation_count())
+            .field("staging_bytes", &self.staging_buffer.size())
+            .field(
+                "textures",
+                &self
+                    .textures
+                    .iter()
+                    .map(Texture::extent)
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
     }
 }
 
@@ -457,7 +1149,30 @@ impl Drop for Device {
         Device::destory_pipeline(&self.device, &mut self.pipeline);
         Device::destory_layout(&self.device, &mut self.layout);
         Device::destory_mesh_data(&self.device, &mut self.mesh_data);
+        Device::destroy_staging_buffer(&self.device, &mut self.staging_buffer);
+        for texture in &mut self.textures {
+            Device::destroy_texture(&self.device, texture);
+        }
+        for skinned in &mut self.skinned_meshes {
+            Device::destroy_skinned_mesh(&self.device, skinned);
+        }
+        if let Some(skinning) = &mut self.skinning {
+            Device::destroy_skinning_pipeline(&self.device, skinning);
+        }
+        for buffer in self.line_buffers.iter_mut().flatten() {
+            Device::destroy_line_buffer(&self.device, buffer);
+        }
+        for ring in &mut self.materials {
+            Device::destroy_material_ring(&self.device, ring);
+        }
+        if let Some(text) = &mut self.text {
+            Device::destroy_text_pipeline(&self.device, text);
+        }
+        for buffer in self.text_buffers.iter_mut().flatten() {
+            Device::destroy_line_buffer(&self.device, buffer);
+        }
         Device::destroy_swapchain(&self.device, &mut self.swapchain);
+        self.allocator.destroy(&self.device);
         unsafe {
             self.device
                 .destroy_command_pool(self.command_pools.graphics, None);
@@ -470,3 +1185,45 @@ impl Drop for Device {
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supported_except(
+        missing: impl FnOnce(&mut vk::PhysicalDeviceFeatures),
+    ) -> vk::PhysicalDeviceFeatures {
+        let mut supported = vk::PhysicalDeviceFeatures::default();
+        Device::feature_flags(&mut supported).fill(vk::TRUE);
+        missing(&mut supported);
+        supported
+    }
+
+    #[test]
+    fn missing_optional_feature_is_disabled() {
+        let supported = supported_except(|features| features.wide_lines = vk::FALSE);
+        let enabled = Device::negotiate_features(
+            Device::required_features(),
+            Device::optional_features(),
+            supported,
+        )
+        .unwrap();
+        assert_eq!(enabled.sampler_anisotropy, vk::TRUE);
+        assert_eq!(enabled.multi_draw_indirect, vk::TRUE);
+        assert_eq!(enabled.fill_mode_non_solid, vk::TRUE);
+        assert_eq!(enabled.wide_lines, vk::FALSE);
+        // Supported features nobody asked for stay disabled
+        assert_eq!(enabled.geometry_shader, vk::FALSE);
+    }
+
+    #[test]
+    fn missing_required_feature_rejects_device() {
+        let supported = supported_except(|features| features.sampler_anisotropy = vk::FALSE);
+        assert!(Device::negotiate_features(
+            Device::required_features(),
+            Device::optional_features(),
+            supported,
+        )
+        .is_none());
+    }
+}
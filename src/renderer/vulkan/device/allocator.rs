@@ -0,0 +1,424 @@
+use super::{Device, PhysicalDeviceConfig};
+use crate::log;
+use ash::{prelude::VkResult, vk};
+use std::{
+    ptr::{self, copy_nonoverlapping},
+    sync::{Arc, Mutex},
+};
+
+const MEMORY_BLOCK_SIZE: vk::DeviceSize = 32 * 1024 * 1024;
+
+// Linear (buffer) and optimal (image) resources never share a block, which
+// keeps suballocations clear of `bufferImageGranularity` conflicts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PoolKey {
+    memory_type: u32,
+    linear: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FreeRange {
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+}
+
+#[derive(Debug)]
+struct Block {
+    key: PoolKey,
+    memory: vk::DeviceMemory,
+    mapped: *mut u8,
+    dedicated: bool,
+    free: Vec<FreeRange>,
+    live: usize,
+}
+
+// Mapped pointers are only dereferenced by `Allocation::with_mapped`, which
+// holds the allocator lock for the whole access and stays within the range
+// owned by that allocation
+unsafe impl Send for Block {}
+
+#[derive(Debug, Default)]
+struct AllocatorState {
+    blocks: Vec<Option<Block>>,
+    released: Vec<vk::DeviceMemory>,
+    live: usize,
+}
+
+/// Suballocates buffer and image memory from large per memory type blocks.
+/// Cloning yields another handle to the same pool.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Allocator {
+    state: Arc<Mutex<AllocatorState>>,
+}
+
+/// Range of a memory block owned by a single resource, returned to the pool on drop.
+#[derive(Debug)]
+pub(super) struct Allocation {
+    state: Arc<Mutex<AllocatorState>>,
+    block: usize,
+    memory: vk::DeviceMemory,
+    offset: vk::DeviceSize,
+    size: vk::DeviceSize,
+}
+
+impl Block {
+    fn new(key: PoolKey, memory: vk::DeviceMemory, size: vk::DeviceSize, mapped: *mut u8) -> Self {
+        Self {
+            key,
+            memory,
+            mapped,
+            dedicated: size > MEMORY_BLOCK_SIZE,
+            free: vec![FreeRange { offset: 0, size }],
+            live: 0,
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<vk::DeviceSize> {
+        let alignment = alignment.max(1);
+        let (index, offset) = self.free.iter().enumerate().find_map(|(index, range)| {
+            let offset = range.offset.div_ceil(alignment) * alignment;
+            (offset + size <= range.offset + range.size).then_some((index, offset))
+        })?;
+        let range = self.free.remove(index);
+        let tail = FreeRange {
+            offset: offset + size,
+            size: range.offset + range.size - (offset + size),
+        };
+        if tail.size > 0 {
+            self.free.insert(index, tail);
+        }
+        // Alignment padding stays free, it is merged back once the range is released
+        if offset > range.offset {
+            self.free.insert(
+                index,
+                FreeRange {
+                    offset: range.offset,
+                    size: offset - range.offset,
+                },
+            );
+        }
+        self.live += 1;
+        Some(offset)
+    }
+
+    fn release(&mut self, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.offset > offset)
+            .unwrap_or(self.free.len());
+        self.free.insert(index, FreeRange { offset, size });
+        if index + 1 < self.free.len()
+            && self.free[index].offset + self.free[index].size == self.free[index + 1].offset
+        {
+            self.free[index].size += self.free.remove(index + 1).size;
+        }
+        if index > 0 && self.free[index - 1].offset + self.free[index - 1].size == offset {
+            self.free[index - 1].size += self.free.remove(index).size;
+        }
+        self.live -= 1;
+    }
+}
+
+impl AllocatorState {
+    /// `create_block` is only called when no existing block of the pool has
+    /// room, it receives the block size and returns the memory and its mapping.
+    fn allocate_with<F>(
+        &mut self,
+        key: PoolKey,
+        requirements: &vk::MemoryRequirements,
+        create_block: F,
+    ) -> VkResult<(usize, vk::DeviceMemory, vk::DeviceSize)>
+    where
+        F: FnOnce(vk::DeviceSize) -> VkResult<(vk::DeviceMemory, *mut u8)>,
+    {
+        let (size, alignment) = (requirements.size, requirements.alignment);
+        let found = self
+            .blocks
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, block)| Some((index, block.as_mut()?)))
+            .filter(|(_, block)| block.key == key && !block.dedicated)
+            .find_map(|(index, block)| {
+                Some((index, block.memory, block.allocate(size, alignment)?))
+            });
+        let allocation = match found {
+            Some(allocation) => allocation,
+            None => {
+                let block_size = vk::DeviceSize::max(size, MEMORY_BLOCK_SIZE);
+                let (memory, mapped) = create_block(block_size)?;
+                let mut block = Block::new(key, memory, block_size, mapped);
+                let offset = block.allocate(size, alignment).unwrap();
+                let index = match self.blocks.iter().position(Option::is_none) {
+                    Some(index) => index,
+                    None => {
+                        self.blocks.push(None);
+                        self.blocks.len() - 1
+                    }
+                };
+                self.blocks[index] = Some(block);
+                (index, memory, offset)
+            }
+        };
+        self.live += 1;
+        Ok(allocation)
+    }
+
+    fn release(&mut self, index: usize, offset: vk::DeviceSize, size: vk::DeviceSize) {
+        let slot = match self.blocks.get_mut(index) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if let Some(block) = slot {
+            block.release(offset, size);
+            self.live -= 1;
+            // Oversized blocks are never shared, hand their memory back to the driver
+            if block.dedicated && block.live == 0 {
+                self.released.push(block.memory);
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl Allocator {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    pub(super) fn allocate(
+        &self,
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        requirements: &vk::MemoryRequirements,
+        properties: vk::MemoryPropertyFlags,
+        linear: bool,
+    ) -> VkResult<Allocation> {
+        let memory_type =
+            Device::memory_type_index(config, requirements.memory_type_bits, properties)
+                .ok_or(vk::Result::ERROR_UNKNOWN)?;
+        let host_visible = config.memory_properties.memory_types[memory_type as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_VISIBLE);
+        let mut state = self.state.lock().unwrap();
+        for memory in state.released.drain(..) {
+            unsafe { device.free_memory(memory, None) };
+        }
+        let key = PoolKey {
+            memory_type,
+            linear,
+        };
+        let (block, memory, offset) = state.allocate_with(key, requirements, |size| unsafe {
+            let memory = device.allocate_memory(
+                &vk::MemoryAllocateInfo::builder()
+                    .allocation_size(size)
+                    .memory_type_index(memory_type),
+                None,
+            )?;
+            if !host_visible {
+                return Ok((memory, ptr::null_mut()));
+            }
+            match device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty()) {
+                Ok(mapped) => Ok((memory, mapped as *mut u8)),
+                Err(err) => {
+                    device.free_memory(memory, None);
+                    Err(err)
+                }
+            }
+        })?;
+        Ok(Allocation {
+            state: self.state.clone(),
+            block,
+            memory,
+            offset,
+            size: requirements.size,
+        })
+    }
+
+    pub(super) fn block_count(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.blocks.iter().flatten().count()
+    }
+
+    pub(super) fn allocation_count(&self) -> usize {
+        self.state.lock().unwrap().live
+    }
+
+    /// Frees every block, resources still bound to them must already be destroyed.
+    pub(super) fn destroy(&self, device: &ash::Device) {
+        let mut state = self.state.lock().unwrap();
+        let released = std::mem::take(&mut state.released);
+        for memory in released
+            .into_iter()
+            .chain(state.blocks.drain(..).flatten().map(|block| block.memory))
+        {
+            unsafe { device.free_memory(memory, None) };
+        }
+    }
+}
+
+impl Allocation {
+    #[inline]
+    pub(super) fn memory(&self) -> vk::DeviceMemory {
+        self.memory
+    }
+
+    #[inline]
+    pub(super) fn offset(&self) -> vk::DeviceSize {
+        self.offset
+    }
+
+    /// Runs `f` on the mapping of `len` bytes starting `offset` bytes into
+    /// the allocation. The allocator lock is held for the whole call, so the
+    /// block can not be unmapped or freed while `f` accesses it.
+    fn with_mapped<R>(&self, offset: usize, len: usize, f: impl FnOnce(*mut u8) -> R) -> Option<R> {
+        let state = self.state.lock().ok()?;
+        let block = state.blocks.get(self.block)?.as_ref()?;
+        if block.mapped.is_null() || (offset + len) as vk::DeviceSize > self.size {
+            return None;
+        }
+        Some(f(unsafe {
+            block.mapped.add(self.offset as usize + offset)
+        }))
+    }
+
+    pub(super) fn write(&self, bytes: &[u8]) {
+        self.write_at(0, bytes)
+    }
+
+    /// Writes `bytes` starting `offset` bytes into the allocation.
+    pub(super) fn write_at(&self, offset: usize, bytes: &[u8]) {
+        let written = self.with_mapped(offset, bytes.len(), |mapped| unsafe {
+            copy_nonoverlapping(bytes.as_ptr(), mapped, bytes.len())
+        });
+        if written.is_none() {
+            log::error("Write to allocation outside of host visible memory");
+        }
+    }
+
+    pub(super) fn read(&self, bytes: &mut [u8]) {
+        let len = bytes.len();
+        let read = self.with_mapped(0, len, |mapped| unsafe {
+            copy_nonoverlapping(mapped as *const u8, bytes.as_mut_ptr(), len)
+        });
+        if read.is_none() {
+            log::error("Read from allocation outside of host visible memory");
+        }
+    }
+}
+
+impl Drop for Allocation {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.release(self.block, self.offset, self.size);
+        }
+    }
+}
+
+impl Device {
+    pub(super) fn allocate_buffer_memory(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        buffer: vk::Buffer,
+        properties: vk::MemoryPropertyFlags,
+    ) -> VkResult<Allocation> {
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+        let allocation = allocator.allocate(device, config, &requirements, properties, true)?;
+        unsafe { device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())? };
+        Ok(allocation)
+    }
+
+    pub(super) fn allocate_image_memory(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        image: vk::Image,
+        properties: vk::MemoryPropertyFlags,
+    ) -> VkResult<Allocation> {
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = allocator.allocate(device, config, &requirements, properties, false)?;
+        unsafe { device.bind_image_memory(image, allocation.memory(), allocation.offset())? };
+        Ok(allocation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+
+    const BUFFER_KEY: PoolKey = PoolKey {
+        memory_type: 0,
+        linear: true,
+    };
+
+    // Same bookkeeping as `Allocator::allocate`, with fake device memory
+    fn allocate(allocator: &Allocator, size: vk::DeviceSize, created: &mut u64) -> Allocation {
+        let requirements = vk::MemoryRequirements {
+            size,
+            alignment: 256,
+            memory_type_bits: 1,
+        };
+        let mut state = allocator.state.lock().unwrap();
+        let (block, memory, offset) = state
+            .allocate_with(BUFFER_KEY, &requirements, |_| {
+                *created += 1;
+                Ok((vk::DeviceMemory::from_raw(*created), ptr::null_mut()))
+            })
+            .unwrap();
+        Allocation {
+            state: allocator.state.clone(),
+            block,
+            memory,
+            offset,
+            size,
+        }
+    }
+
+    #[test]
+    fn small_buffers_share_few_device_allocations() {
+        let allocator = Allocator::new();
+        let mut created = 0;
+        let allocations = (0..1000)
+            .map(|_| allocate(&allocator, 4096, &mut created))
+            .collect::<Vec<_>>();
+        assert_eq!(allocator.allocation_count(), 1000);
+        assert_eq!(allocator.block_count(), 1);
+        assert_eq!(created, 1);
+        let mut ranges = allocations
+            .iter()
+            .map(|allocation| (allocation.offset(), allocation.size))
+            .collect::<Vec<_>>();
+        ranges.sort_unstable();
+        assert!(ranges
+            .windows(2)
+            .all(|pair| pair[0].0 + pair[0].1 <= pair[1].0));
+
+        drop(allocations);
+        assert_eq!(allocator.allocation_count(), 0);
+        // Released ranges are reused instead of growing the pool
+        let _allocations = (0..1000)
+            .map(|_| allocate(&allocator, 4096, &mut created))
+            .collect::<Vec<_>>();
+        assert_eq!(allocator.block_count(), 1);
+        assert_eq!(created, 1);
+    }
+
+    #[test]
+    fn oversized_allocation_gets_dedicated_block() {
+        let allocator = Allocator::new();
+        let mut created = 0;
+        let small = allocate(&allocator, 4096, &mut created);
+        let large = allocate(&allocator, 2 * MEMORY_BLOCK_SIZE, &mut created);
+        assert_eq!(allocator.block_count(), 2);
+        assert_ne!(small.memory(), large.memory());
+        drop(large);
+        assert_eq!(allocator.block_count(), 1);
+        assert_eq!(allocator.state.lock().unwrap().released.len(), 1);
+    }
+}
@@ -1,56 +1,335 @@
-use super::{CommandPools, CommandType, Device, PhysicalDeviceConfig, Queues};
-use crate::renderer::{mesh::Vertex, Mesh};
+use super::{
+    Allocation, Allocator, CommandPools, CommandType, Device, PhysicalDeviceConfig, Queues,
+};
+use crate::{
+    math::types::Vector4,
+    renderer::{mesh::Vertex, ColorSpace, IndexType, Mesh, MeshHandle},
+    utils::{ResultExt, StaticResult},
+};
 use ash::{prelude::VkResult, vk};
 use bytemuck::Pod;
-use std::{collections::HashSet, iter::FromIterator, mem::size_of, ptr::copy_nonoverlapping};
+use std::{collections::HashSet, iter::FromIterator, mem::size_of};
 
+#[derive(Debug, Clone, Copy)]
 pub struct MeshOffset {
+    /// Byte offset of the mesh indices within the index region
     pub index_offset: usize,
     pub vertex_offset: usize,
     pub index_count: usize,
+    pub index_type: vk::IndexType,
 }
 
+#[derive(Debug)]
 pub struct MeshData {
-    memory: vk::DeviceMemory,
+    allocation: Allocation,
     buffer: vk::Buffer,
     index_offset: usize,
     vertex_offset: usize,
+    vertex_count: usize,
+    index_byte_size: usize,
     pub(super) mesh_offsets: Vec<MeshOffset>,
 }
 
-pub struct StagingBuffer<'a> {
-    memory: vk::DeviceMemory,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorTransfer {
+    Identity,
+    EncodeSrgb,
+    DecodeSrgb,
+}
+
+/// Host visible transfer buffer kept on the device and reused by every upload
+/// and readback, it only grows when a transfer does not fit.
+#[derive(Debug)]
+pub(super) struct StagingBuffer {
+    allocation: Allocation,
     buffer: vk::Buffer,
     fence: vk::Fence,
-    device: &'a ash::Device,
+    size: usize,
+}
+
+impl ColorTransfer {
+    pub fn new(surface_format: vk::Format, linear_colors: bool) -> Self {
+        let srgb_surface = matches!(
+            surface_format,
+            vk::Format::R8G8B8A8_SRGB
+                | vk::Format::B8G8R8A8_SRGB
+                | vk::Format::A8B8G8R8_SRGB_PACK32
+                | vk::Format::R8G8B8_SRGB
+                | vk::Format::B8G8R8_SRGB
+        );
+        match (linear_colors, srgb_surface) {
+            (true, false) => ColorTransfer::EncodeSrgb,
+            (false, true) => ColorTransfer::DecodeSrgb,
+            _ => ColorTransfer::Identity,
+        }
+    }
+
+    /// Transfer taking colors given in `space` to the encoding of
+    /// `surface_format`.
+    pub fn for_space(surface_format: vk::Format, space: ColorSpace) -> Self {
+        ColorTransfer::new(surface_format, space == ColorSpace::Linear)
+    }
+
+    pub fn apply(self, color: Vector4) -> Vector4 {
+        let transfer = |c: f32| match self {
+            ColorTransfer::Identity => c,
+            ColorTransfer::EncodeSrgb if c <= 0.0031308 => c * 12.92,
+            ColorTransfer::EncodeSrgb => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+            ColorTransfer::DecodeSrgb if c <= 0.04045 => c / 12.92,
+            ColorTransfer::DecodeSrgb => ((c + 0.055) / 1.055).powf(2.4),
+        };
+        Vector4::new(
+            transfer(color.x),
+            transfer(color.y),
+            transfer(color.z),
+            color.w,
+        )
+    }
+}
+
+impl From<IndexType> for vk::IndexType {
+    fn from(index_type: IndexType) -> Self {
+        match index_type {
+            IndexType::U16 => vk::IndexType::UINT16,
+            IndexType::U32 => vk::IndexType::UINT32,
+        }
+    }
+}
+
+impl MeshData {
+    #[inline]
+    pub(super) fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    /// Index of the first vertex of `mesh` counted from the start of the buffer
+    pub(super) fn first_vertex(&self, mesh: MeshHandle) -> Option<usize> {
+        let offsets = self.mesh_offsets.get(mesh.0)?;
+        Some(self.vertex_offset / size_of::<Vertex>() + offsets.vertex_offset)
+    }
 }
 
-impl<'a> Device {
+impl StagingBuffer {
+    #[inline]
+    pub(super) fn buffer(&self) -> vk::Buffer {
+        self.buffer
+    }
+
+    #[inline]
+    pub(super) fn fence(&self) -> vk::Fence {
+        self.fence
+    }
+
+    #[inline]
+    pub(super) fn allocation(&self) -> &Allocation {
+        &self.allocation
+    }
+
+    #[inline]
+    pub(super) fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Device {
     pub(super) fn load_mesh_data(
         device: &ash::Device,
         config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
         command_pools: &CommandPools,
         queues: &Queues,
         meshes: &[Mesh],
-    ) -> VkResult<MeshData> {
+        color_transfer: ColorTransfer,
+    ) -> StaticResult<(MeshData, StagingBuffer)> {
         let mut mesh_offsets = Vec::new();
         let mut vertices = Vec::<Vertex>::new();
-        let mut indices = Vec::<u32>::new();
+        let mut indices = Vec::<u8>::new();
         for mesh in meshes {
             mesh_offsets.push(MeshOffset {
                 index_offset: indices.len(),
                 vertex_offset: vertices.len(),
                 index_count: mesh.indices.len(),
+                index_type: mesh.index_type().into(),
             });
-            vertices.extend(mesh.vertices.iter());
-            indices.extend(mesh.indices.iter());
+            vertices.extend(mesh.vertices.iter().map(|&vertex| Vertex {
+                color: color_transfer.apply(vertex.color),
+                ..vertex
+            }));
+            indices.extend(mesh.index_bytes());
         }
 
         let vertex_byte_size = vertices.len() * size_of::<Vertex>();
-        let index_byte_size = indices.len() * size_of::<u32>();
+        let index_byte_size = indices.len();
         let buffer_byte_size = vertex_byte_size + index_byte_size;
         let staging_byte_size = usize::max(vertex_byte_size, index_byte_size);
 
+        let (buffer, allocation) =
+            Device::create_mesh_buffer(device, config, allocator, buffer_byte_size)?;
+
+        let staging_buffer =
+            Device::create_staging_buffer(device, config, allocator, staging_byte_size)
+                .with_context(|| {
+                    format!(
+                        "Failed to allocate {} byte staging buffer",
+                        staging_byte_size
+                    )
+                })?;
+        Device::copy_buffer_data(
+            device,
+            &staging_buffer,
+            config,
+            command_pools,
+            queues,
+            buffer,
+            0,
+            &vertices,
+        )?;
+        Device::copy_buffer_data(
+            device,
+            &staging_buffer,
+            config,
+            command_pools,
+            queues,
+            buffer,
+            vertex_byte_size,
+            &indices,
+        )?;
+
+        let mesh_data = MeshData {
+            allocation,
+            buffer,
+            vertex_offset: 0,
+            index_offset: vertex_byte_size,
+            vertex_count: vertices.len(),
+            index_byte_size,
+            mesh_offsets,
+        };
+        Ok((mesh_data, staging_buffer))
+    }
+
+    pub(super) fn append_mesh_data(&mut self, mesh: &Mesh) -> StaticResult<MeshHandle> {
+        let Device {
+            device,
+            config,
+            allocator,
+            command_pools,
+            queues,
+            mesh_data: data,
+            staging_buffer,
+            color_transfer,
+            ..
+        } = self;
+        let vertices: Vec<_> = mesh
+            .vertices
+            .iter()
+            .map(|&vertex| Vertex {
+                color: color_transfer.apply(vertex.color),
+                ..vertex
+            })
+            .collect();
+        let indices = mesh.index_bytes();
+        let old_vertex_byte_size = data.vertex_count * size_of::<Vertex>();
+        let old_index_byte_size = data.index_byte_size;
+        let vertex_byte_size = old_vertex_byte_size + vertices.len() * size_of::<Vertex>();
+        let index_byte_size = old_index_byte_size + indices.len();
+        let staging_byte_size = usize::max(vertices.len() * size_of::<Vertex>(), indices.len());
+
+        let (buffer, allocation) = Device::create_mesh_buffer(
+            device,
+            config,
+            allocator,
+            vertex_byte_size + index_byte_size,
+        )?;
+        Device::reserve_staging_buffer(
+            device,
+            config,
+            allocator,
+            staging_buffer,
+            staging_byte_size,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to allocate {} byte staging buffer",
+                staging_byte_size
+            )
+        })?;
+        let regions: Vec<_> = [
+            vk::BufferCopy {
+                src_offset: data.vertex_offset as vk::DeviceSize,
+                dst_offset: 0,
+                size: old_vertex_byte_size as vk::DeviceSize,
+            },
+            vk::BufferCopy {
+                src_offset: data.index_offset as vk::DeviceSize,
+                dst_offset: vertex_byte_size as vk::DeviceSize,
+                size: old_index_byte_size as vk::DeviceSize,
+            },
+        ]
+        .iter()
+        .copied()
+        .filter(|region| region.size > 0)
+        .collect();
+        if !regions.is_empty() {
+            let command = Device::begin_single_time_command(
+                device,
+                config,
+                command_pools,
+                queues,
+                CommandType::Transfer,
+            )?;
+            unsafe {
+                device.cmd_copy_buffer(command.buffer, data.buffer, buffer, &regions);
+            }
+            command.submit(device, Some(staging_buffer.fence))?;
+            unsafe {
+                device.wait_for_fences(&[staging_buffer.fence], true, u64::MAX)?;
+                device.reset_fences(&[staging_buffer.fence])?;
+            }
+            Device::destory_command(device, command);
+        }
+        Device::copy_buffer_data(
+            device,
+            staging_buffer,
+            config,
+            command_pools,
+            queues,
+            buffer,
+            old_vertex_byte_size,
+            &vertices,
+        )?;
+        Device::copy_buffer_data(
+            device,
+            staging_buffer,
+            config,
+            command_pools,
+            queues,
+            buffer,
+            vertex_byte_size + old_index_byte_size,
+            &indices,
+        )?;
+        Device::destory_mesh_data(device, data);
+
+        data.buffer = buffer;
+        data.allocation = allocation;
+        data.vertex_offset = 0;
+        data.index_offset = vertex_byte_size;
+        data.mesh_offsets.push(MeshOffset {
+            index_offset: old_index_byte_size,
+            vertex_offset: data.vertex_count,
+            index_count: mesh.indices.len(),
+            index_type: mesh.index_type().into(),
+        });
+        data.vertex_count += vertices.len();
+        data.index_byte_size = index_byte_size;
+        Ok(MeshHandle(data.mesh_offsets.len() - 1))
+    }
+
+    pub(super) fn create_mesh_buffer(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        buffer_byte_size: usize,
+    ) -> StaticResult<(vk::Buffer, Allocation)> {
         let queue_indices: Vec<_> = HashSet::<u32>::from_iter([
             config.queue_families.graphics,
             config.queue_families.transfer,
@@ -64,6 +343,8 @@ impl<'a> Device {
                     .usage(
                         vk::BufferUsageFlags::VERTEX_BUFFER
                             | vk::BufferUsageFlags::INDEX_BUFFER
+                            | vk::BufferUsageFlags::STORAGE_BUFFER
+                            | vk::BufferUsageFlags::TRANSFER_SRC
                             | vk::BufferUsageFlags::TRANSFER_DST,
                     )
                     .size(buffer_byte_size as u64)
@@ -74,62 +355,30 @@ impl<'a> Device {
                         vk::SharingMode::CONCURRENT
                     }),
                 None,
-            )?
-        };
-        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
-        let mem_index = Device::memory_type_index(
-            &config,
-            requirements.memory_type_bits,
-            vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        )
-        .ok_or(vk::Result::ERROR_UNKNOWN)?;
-        let memory = unsafe {
-            device.allocate_memory(
-                &vk::MemoryAllocateInfo::builder()
-                    .allocation_size(requirements.size)
-                    .memory_type_index(mem_index),
-                None,
-            )?
-        };
-        unsafe { device.bind_buffer_memory(buffer, memory, 0)? };
-
-        {
-            let staging_buffer = Device::create_staging_buffer(device, config, staging_byte_size)?;
-            Device::copy_buffer_data(
-                device,
-                &staging_buffer,
-                config,
-                command_pools,
-                queues,
-                buffer,
-                0,
-                &vertices,
-            )?;
-            Device::copy_buffer_data(
-                device,
-                &staging_buffer,
-                config,
-                command_pools,
-                queues,
-                buffer,
-                vertex_byte_size,
-                &indices,
-            )?;
+            )
         }
-
-        Ok(MeshData {
-            memory,
+        .with_context(|| format!("Failed to create {} byte mesh buffer", buffer_byte_size))?;
+        let allocation = Device::allocate_buffer_memory(
+            device,
+            config,
+            allocator,
             buffer,
-            vertex_offset: 0,
-            index_offset: vertex_byte_size,
-            mesh_offsets,
-        })
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to allocate {} bytes of mesh buffer memory",
+                buffer_byte_size
+            )
+        })?;
+        Ok((buffer, allocation))
     }
 
     pub(super) fn bind_buffers(
         device: &ash::Device,
         command_buffer: vk::CommandBuffer,
         data: &MeshData,
+        mesh: &MeshOffset,
     ) {
         unsafe {
             device.cmd_bind_vertex_buffers(
@@ -141,53 +390,77 @@ impl<'a> Device {
             device.cmd_bind_index_buffer(
                 command_buffer,
                 data.buffer,
-                data.index_offset as vk::DeviceSize,
-                vk::IndexType::UINT32,
+                (data.index_offset + mesh.index_offset) as vk::DeviceSize,
+                mesh.index_type,
             )
         }
     }
 
     pub(super) fn create_staging_buffer(
-        device: &'a ash::Device,
+        device: &ash::Device,
         config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
         size: usize,
-    ) -> VkResult<StagingBuffer<'a>> {
+    ) -> VkResult<StagingBuffer> {
+        // Readbacks record their copies on the graphics queue
+        let queue_indices: Vec<_> = HashSet::<u32>::from_iter([
+            config.queue_families.graphics,
+            config.queue_families.transfer,
+        ])
+        .into_iter()
+        .collect();
+        let size = usize::max(size, 1);
         let buffer = unsafe {
             device.create_buffer(
                 &vk::BufferCreateInfo::builder()
-                    .usage(vk::BufferUsageFlags::TRANSFER_SRC)
+                    .usage(vk::BufferUsageFlags::TRANSFER_SRC | vk::BufferUsageFlags::TRANSFER_DST)
                     .size(size as vk::DeviceSize)
-                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
-                    .queue_family_indices(&[config.queue_families.transfer]),
+                    .sharing_mode(if queue_indices.len() == 1 {
+                        vk::SharingMode::EXCLUSIVE
+                    } else {
+                        vk::SharingMode::CONCURRENT
+                    })
+                    .queue_family_indices(&queue_indices),
                 None,
             )?
         };
-        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
-        let mem_index = Device::memory_type_index(
-            &config,
-            requirements.memory_type_bits,
+        let allocation = Device::allocate_buffer_memory(
+            device,
+            config,
+            allocator,
+            buffer,
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
-        )
-        .ok_or(vk::Result::ERROR_UNKNOWN)?;
-        let memory = unsafe {
-            device.allocate_memory(
-                &vk::MemoryAllocateInfo::builder()
-                    .allocation_size(requirements.size)
-                    .memory_type_index(mem_index),
-                None,
-            )?
-        };
-        unsafe { device.bind_buffer_memory(buffer, memory, 0)? };
+        )?;
         let fence = unsafe { device.create_fence(&vk::FenceCreateInfo::default(), None)? };
         Ok(StagingBuffer {
             buffer,
-            memory,
-            device,
+            allocation,
             fence,
+            size,
         })
     }
 
-    fn copy_buffer_data<T: Pod>(
+    /// Replaces `staging_buffer` with one of at least `size` bytes, the
+    /// existing buffer is kept when it is already large enough.
+    pub(super) fn reserve_staging_buffer(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        staging_buffer: &mut StagingBuffer,
+        size: usize,
+    ) -> VkResult<()> {
+        if size <= staging_buffer.size {
+            return Ok(());
+        }
+        let mut old = std::mem::replace(
+            staging_buffer,
+            Device::create_staging_buffer(device, config, allocator, size)?,
+        );
+        Device::destroy_staging_buffer(device, &mut old);
+        Ok(())
+    }
+
+    pub(super) fn copy_buffer_data<T: Pod>(
         device: &ash::Device,
         staging_buffer: &StagingBuffer,
         config: &PhysicalDeviceConfig,
@@ -198,16 +471,10 @@ impl<'a> Device {
         src: &[T],
     ) -> VkResult<()> {
         let src = bytemuck::cast_slice::<T, u8>(src);
-        unsafe {
-            let mem = device.map_memory(
-                staging_buffer.memory,
-                0,
-                src.len() as vk::DeviceSize,
-                vk::MemoryMapFlags::empty(),
-            )?;
-            copy_nonoverlapping(src.as_ptr(), mem as *mut u8, src.len());
-            device.unmap_memory(staging_buffer.memory);
-        };
+        if src.is_empty() {
+            return Ok(());
+        }
+        staging_buffer.allocation.write(src);
         let command = Device::begin_single_time_command(
             device,
             config,
@@ -239,17 +506,73 @@ impl<'a> Device {
     pub(super) fn destory_mesh_data(device: &ash::Device, data: &mut MeshData) {
         unsafe {
             device.destroy_buffer(data.buffer, None);
-            device.free_memory(data.memory, None);
         }
     }
-}
 
-impl<'a> Drop for StagingBuffer<'a> {
-    fn drop(&mut self) {
+    pub(super) fn destroy_staging_buffer(device: &ash::Device, staging_buffer: &mut StagingBuffer) {
         unsafe {
-            self.device.destroy_buffer(self.buffer, None);
-            self.device.free_memory(self.memory, None);
-            self.device.destroy_fence(self.fence, None);
+            device.destroy_buffer(staging_buffer.buffer, None);
+            device.destroy_fence(staging_buffer.fence, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_for_space_and_surface() {
+        let cases = [
+            (
+                vk::Format::B8G8R8A8_SRGB,
+                ColorSpace::Linear,
+                ColorTransfer::Identity,
+            ),
+            (
+                vk::Format::B8G8R8A8_SRGB,
+                ColorSpace::Srgb,
+                ColorTransfer::DecodeSrgb,
+            ),
+            (
+                vk::Format::B8G8R8A8_UNORM,
+                ColorSpace::Linear,
+                ColorTransfer::EncodeSrgb,
+            ),
+            (
+                vk::Format::B8G8R8A8_UNORM,
+                ColorSpace::Srgb,
+                ColorTransfer::Identity,
+            ),
+            (
+                vk::Format::R16G16B16A16_SFLOAT,
+                ColorSpace::Linear,
+                ColorTransfer::EncodeSrgb,
+            ),
+        ];
+        for &(format, space, transfer) in &cases {
+            assert_eq!(
+                ColorTransfer::for_space(format, space),
+                transfer,
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn srgb_transfers_round_trip() {
+        for step in 0..=20 {
+            let c = step as f32 / 20.0;
+            let color = Vector4::new(c, 1.0 - c, c * c, 0.5);
+            let encoded = ColorTransfer::EncodeSrgb.apply(color);
+            let decoded = ColorTransfer::DecodeSrgb.apply(encoded);
+            assert!((decoded - color).mag() < 1e-5, "{:?}", decoded);
+            // Alpha is never converted
+            assert_eq!(encoded.w, 0.5);
         }
+        // Linear mid gray is brighter once encoded
+        let gray = ColorTransfer::EncodeSrgb.apply(Vector4::new(0.5, 0.5, 0.5, 1.0));
+        assert!(f32::abs(gray.x - 0.7354) < 1e-3);
     }
 }
@@ -1,12 +1,14 @@
 use super::{CommandPools, Device, PhysicalDeviceConfig, Queues};
 use ash::{prelude::VkResult, vk};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) enum CommandType {
     Graphics,
     Transfer,
     Compute,
 }
 
+#[derive(Debug)]
 pub(super) struct Command {
     pub(super) buffer: vk::CommandBuffer,
     pub(super) queue: vk::Queue,
@@ -0,0 +1,324 @@
+use super::{
+    command::CommandType,
+    partial::{PartialObject, PartialObjects},
+    Allocation, Device,
+};
+use crate::renderer::{TextureFormat, TextureHandle};
+use ash::{prelude::VkResult, vk};
+
+#[derive(Debug)]
+pub(super) struct Texture {
+    _allocation: Allocation,
+    image: vk::Image,
+    view: vk::ImageView,
+    sampler: vk::Sampler,
+    extent: vk::Extent3D,
+}
+
+impl From<TextureFormat> for vk::Format {
+    fn from(format: TextureFormat) -> Self {
+        match format {
+            TextureFormat::R32Float => vk::Format::R32_SFLOAT,
+            TextureFormat::Rg32Float => vk::Format::R32G32_SFLOAT,
+            TextureFormat::Rgba32Float => vk::Format::R32G32B32A32_SFLOAT,
+        }
+    }
+}
+
+impl Texture {
+    #[inline]
+    pub(super) fn extent(&self) -> vk::Extent3D {
+        self.extent
+    }
+
+    #[inline]
+    pub(super) fn view(&self) -> vk::ImageView {
+        self.view
+    }
+
+    #[inline]
+    pub(super) fn sampler(&self) -> vk::Sampler {
+        self.sampler
+    }
+
+    /// Handles in creation order, for constructions that take ownership of
+    /// the texture and have to release it when they fail.
+    pub(super) fn partial_objects(&self) -> [PartialObject; 3] {
+        [self.image.into(), self.view.into(), self.sampler.into()]
+    }
+}
+
+impl Device {
+    fn float_format_channels(format: vk::Format) -> Option<usize> {
+        match format {
+            vk::Format::R32_SFLOAT => Some(1),
+            vk::Format::R32G32_SFLOAT => Some(2),
+            vk::Format::R32G32B32A32_SFLOAT => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Extent of a volume with `channels` floats per texel, rejecting empty
+    /// or oversized dimensions and data that does not fill the volume.
+    fn volume_extent(
+        len: usize,
+        channels: usize,
+        [width, height, depth]: [u32; 3],
+        limit: u32,
+    ) -> VkResult<vk::Extent3D> {
+        if width == 0 || height == 0 || depth == 0 || width.max(height).max(depth) > limit {
+            return Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+        let texel_count = width as usize * height as usize * depth as usize;
+        if len != texel_count * channels {
+            return Err(vk::Result::ERROR_INITIALIZATION_FAILED);
+        }
+        Ok(vk::Extent3D {
+            width,
+            height,
+            depth,
+        })
+    }
+
+    /// Uploads `data` as a `width` x `height` x `depth` volume, texels are laid
+    /// out row by row and slice by slice with one f32 per channel of `format`.
+    /// Only 32 bit float formats are accepted and, since linear filtering of
+    /// those is optional, the texture is sampled with nearest filtering.
+    pub fn load_texture_3d(
+        &mut self,
+        data: &[f32],
+        width: u32,
+        height: u32,
+        depth: u32,
+        format: vk::Format,
+    ) -> VkResult<TextureHandle> {
+        let channels =
+            Device::float_format_channels(format).ok_or(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)?;
+        let extent = Device::volume_extent(
+            data.len(),
+            channels,
+            [width, height, depth],
+            self.config.properties.limits.max_image_dimension3_d,
+        )?;
+        let texture = self.create_texture(
+            bytemuck::cast_slice(data),
+            extent,
+            format,
+            vk::ImageType::TYPE_3D,
+            vk::ImageViewType::TYPE_3D,
+            vk::Filter::NEAREST,
+        )?;
+        self.textures.push(texture);
+        Ok(TextureHandle(self.textures.len() - 1))
+    }
+
+    /// Creates a sampled texture from tightly packed `bytes`, waits for the
+    /// device to go idle before reusing the staging buffer.
+    pub(super) fn create_texture(
+        &mut self,
+        bytes: &[u8],
+        extent: vk::Extent3D,
+        format: vk::Format,
+        image_type: vk::ImageType,
+        view_type: vk::ImageViewType,
+        filter: vk::Filter,
+    ) -> VkResult<Texture> {
+        unsafe { self.device.device_wait_idle()? };
+        let byte_size = bytes.len();
+        Device::reserve_staging_buffer(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            &mut self.staging_buffer,
+            byte_size,
+        )?;
+        self.staging_buffer.allocation().write(bytes);
+
+        let mut partial = PartialObjects::new(&self.device);
+        let image = partial.add(unsafe {
+            self.device.create_image(
+                &vk::ImageCreateInfo::builder()
+                    .image_type(image_type)
+                    .format(format)
+                    .extent(extent)
+                    .mip_levels(1)
+                    .array_layers(1)
+                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .tiling(vk::ImageTiling::OPTIMAL)
+                    .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .queue_family_indices(&[self.config.queue_families.graphics])
+                    .initial_layout(vk::ImageLayout::UNDEFINED),
+                None,
+            )?
+        });
+        let allocation = Device::allocate_image_memory(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            image,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+        )?;
+        self.copy_staging_to_image(image, extent)?;
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let view = partial.add(unsafe {
+            self.device.create_image_view(
+                &vk::ImageViewCreateInfo::builder()
+                    .image(image)
+                    .view_type(view_type)
+                    .format(format)
+                    .components(vk::ComponentMapping::default())
+                    .subresource_range(subresource_range),
+                None,
+            )?
+        });
+        let sampler = partial.add(unsafe {
+            self.device.create_sampler(
+                &vk::SamplerCreateInfo::builder()
+                    .mag_filter(filter)
+                    .min_filter(filter)
+                    .mipmap_mode(vk::SamplerMipmapMode::NEAREST)
+                    .address_mode_u(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_v(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .address_mode_w(vk::SamplerAddressMode::CLAMP_TO_EDGE)
+                    .max_lod(0.0),
+                None,
+            )?
+        });
+        partial.finish();
+        Ok(Texture {
+            _allocation: allocation,
+            image,
+            view,
+            sampler,
+            extent,
+        })
+    }
+
+    fn copy_staging_to_image(&self, image: vk::Image, extent: vk::Extent3D) -> VkResult<()> {
+        let command = Device::begin_single_time_command(
+            &self.device,
+            &self.config,
+            &self.command_pools,
+            &self.queues,
+            CommandType::Graphics,
+        )?;
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .image(image)
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                command.buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::UNDEFINED,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::AccessFlags::empty(),
+                    vk::AccessFlags::TRANSFER_WRITE,
+                )],
+            );
+            self.device.cmd_copy_buffer_to_image(
+                command.buffer,
+                self.staging_buffer.buffer(),
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[vk::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::COLOR,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: vk::Offset3D::default(),
+                    image_extent: extent,
+                }],
+            );
+            self.device.cmd_pipeline_barrier(
+                command.buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                )],
+            );
+        }
+        let fence = self.staging_buffer.fence();
+        let result = command
+            .submit(&self.device, Some(fence))
+            .and_then(|_| unsafe {
+                self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+                self.device.reset_fences(&[fence])
+            });
+        Device::destory_command(&self.device, command);
+        result
+    }
+
+    pub(super) fn destroy_texture(device: &ash::Device, texture: &mut Texture) {
+        unsafe {
+            device.destroy_sampler(texture.sampler, None);
+            device.destroy_image_view(texture.view, None);
+            device.destroy_image(texture.image, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_extent_matches_dimensions() {
+        let extent = Device::volume_extent(2 * 3 * 4 * 2, 2, [2, 3, 4], 256).unwrap();
+        assert_eq!((extent.width, extent.height, extent.depth), (2, 3, 4));
+    }
+
+    #[test]
+    fn volume_extent_rejects_invalid_volumes() {
+        assert_eq!(
+            Device::volume_extent(0, 1, [0, 4, 4], 256),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        );
+        assert_eq!(
+            Device::volume_extent(512, 1, [512, 1, 1], 256),
+            Err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED)
+        );
+        assert_eq!(
+            Device::volume_extent(63, 1, [4, 4, 4], 256),
+            Err(vk::Result::ERROR_INITIALIZATION_FAILED)
+        );
+    }
+}
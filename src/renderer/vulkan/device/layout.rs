@@ -1,20 +1,49 @@
-use super::Device;
-use crate::{math::types::Matrix4, renderer::mesh::Vertex};
-use ash::{self, prelude::VkResult, vk};
+use super::{partial::PartialObjects, Device};
+use crate::{
+    math::types::Matrix4,
+    renderer::mesh::Vertex,
+    utils::{const_assert, StaticResult},
+};
+use ash::{self, vk};
 use bytemuck;
 use std::mem::size_of;
 
 pub const CAMERA_PUSH_OFFSET: u32 = 0 * size_of::<Matrix4>() as u32;
 pub const WORLD_PUSH_OFFSET: u32 = 1 * size_of::<Matrix4>() as u32;
+pub const PUSH_CONSTANT_SIZE: u32 = 2 * size_of::<Matrix4>() as u32;
+pub const PUSH_CONSTANT_STAGES: vk::ShaderStageFlags = vk::ShaderStageFlags::from_raw(
+    vk::ShaderStageFlags::VERTEX.as_raw() | vk::ShaderStageFlags::FRAGMENT.as_raw(),
+);
+pub const GUARANTEED_PUSH_CONSTANT_SIZE: u32 = 128;
 
+// Material data is read from a uniform buffer, see `material.rs`
+const_assert!(PUSH_CONSTANT_SIZE <= GUARANTEED_PUSH_CONSTANT_SIZE);
+
+#[derive(Debug)]
 pub(super) struct Layout {
+    pub material_set_layout: vk::DescriptorSetLayout,
     pub pipeline_layout: vk::PipelineLayout,
     pub vertex_bindings: [vk::VertexInputBindingDescription; 1],
     pub vertex_attribs: [vk::VertexInputAttributeDescription; 5],
 }
 
 impl Device {
-    pub(super) fn create_layout(device: &ash::Device) -> VkResult<Layout> {
+    pub(super) fn check_push_constant_size(size: u32, budget: u32) -> StaticResult<()> {
+        if size > budget {
+            Err(format!(
+                "Push constant size of {} bytes exceeds device limit of {} bytes",
+                size, budget
+            ))?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn create_layout(
+        device: &ash::Device,
+        push_constant_size: u32,
+        push_constant_budget: u32,
+    ) -> StaticResult<Layout> {
+        Device::check_push_constant_size(push_constant_size, push_constant_budget)?;
         let vertex = Vertex::default();
 
         let vertex_bindings = [vk::VertexInputBindingDescription {
@@ -57,19 +86,37 @@ impl Device {
         ];
 
         let push_ranges = [vk::PushConstantRange {
-            stage_flags: vk::ShaderStageFlags::VERTEX,
-            size: 2 * size_of::<Matrix4>() as u32,
+            stage_flags: PUSH_CONSTANT_STAGES,
+            size: push_constant_size,
             offset: 0,
         }];
 
+        let mut partial = PartialObjects::new(device);
+        let material_set_layout = partial.add(unsafe {
+            device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                    vk::DescriptorSetLayoutBinding::builder()
+                        .binding(0)
+                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                        .descriptor_count(1)
+                        .stage_flags(PUSH_CONSTANT_STAGES)
+                        .build(),
+                ]),
+                None,
+            )?
+        });
         let pipeline_layout = unsafe {
             device.create_pipeline_layout(
-                &vk::PipelineLayoutCreateInfo::builder().push_constant_ranges(&push_ranges),
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&[material_set_layout])
+                    .push_constant_ranges(&push_ranges),
                 None,
             )?
         };
+        partial.finish();
 
         Ok(Layout {
+            material_set_layout,
             pipeline_layout,
             vertex_attribs,
             vertex_bindings,
@@ -79,6 +126,25 @@ impl Device {
     pub(super) fn destory_layout(device: &ash::Device, layout: &mut Layout) {
         unsafe {
             device.destroy_pipeline_layout(layout.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(layout.material_set_layout, None);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_push_constants_are_rejected() {
+        assert!(Device::check_push_constant_size(
+            PUSH_CONSTANT_SIZE,
+            GUARANTEED_PUSH_CONSTANT_SIZE
+        )
+        .is_ok());
+        let err = Device::check_push_constant_size(256, GUARANTEED_PUSH_CONSTANT_SIZE)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("256") && err.contains("128"), "{}", err);
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Allocation, Allocator, Device, PhysicalDeviceConfig};
+use crate::renderer::mesh::Vertex;
+use ash::{prelude::VkResult, vk};
+use std::mem::size_of;
+
+const MIN_LINE_VERTICES: usize = 1024;
+
+#[derive(Debug)]
+pub(super) struct LineBuffer {
+    allocation: Allocation,
+    buffer: vk::Buffer,
+    capacity: usize,
+}
+
+impl Device {
+    fn create_line_buffer(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        capacity: usize,
+    ) -> VkResult<LineBuffer> {
+        let buffer = unsafe {
+            device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
+                    .size((capacity * size_of::<Vertex>()) as vk::DeviceSize)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .queue_family_indices(&[config.queue_families.graphics]),
+                None,
+            )?
+        };
+        let allocation = Device::allocate_buffer_memory(
+            device,
+            config,
+            allocator,
+            buffer,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        Ok(LineBuffer {
+            allocation,
+            buffer,
+            capacity,
+        })
+    }
+
+    pub(super) fn upload_lines(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        buffers: &mut Vec<Option<LineBuffer>>,
+        image_index: usize,
+        vertices: &[Vertex],
+    ) -> VkResult<vk::Buffer> {
+        if buffers.len() <= image_index {
+            buffers.resize_with(image_index + 1, || None);
+        }
+        let slot = &mut buffers[image_index];
+        if let Some(buffer) = slot
+            .as_mut()
+            .filter(|buffer| buffer.capacity < vertices.len())
+        {
+            Device::destroy_line_buffer(device, buffer);
+            *slot = None;
+        }
+        let buffer = match slot {
+            Some(buffer) => buffer,
+            None => slot.insert(Device::create_line_buffer(
+                device,
+                config,
+                allocator,
+                usize::max(vertices.len().next_power_of_two(), MIN_LINE_VERTICES),
+            )?),
+        };
+        buffer
+            .allocation
+            .write(bytemuck::cast_slice::<Vertex, u8>(vertices));
+        Ok(buffer.buffer)
+    }
+
+    pub(super) fn destroy_line_buffer(device: &ash::Device, buffer: &mut LineBuffer) {
+        unsafe {
+            device.destroy_buffer(buffer.buffer, None);
+        }
+    }
+}
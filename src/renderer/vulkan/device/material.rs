@@ -0,0 +1,210 @@
+use super::{partial::PartialObjects, Allocation, Device, Frame};
+use crate::{math::types::Vector4, utils::const_assert};
+use ash::{prelude::VkResult, vk};
+use bytemuck::{Pod, Zeroable};
+use std::mem::size_of;
+
+const MIN_MATERIAL_SLOTS: usize = 64;
+
+/// Layout of the `Material` uniform block of `shaders/src/shader.frag`,
+/// padded to the std140 size of the block.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(super) struct MaterialUniform {
+    pub albedo: Vector4,
+    pub color_mode: u32,
+    pub alpha: f32,
+    _padding: [u32; 2],
+}
+
+unsafe impl Zeroable for MaterialUniform {}
+unsafe impl Pod for MaterialUniform {}
+
+// std140 rounds the block up to the 16 byte alignment of its vec4 member
+const_assert!(size_of::<MaterialUniform>() == 32);
+
+#[derive(Debug)]
+struct MaterialBuffer {
+    allocation: Allocation,
+    buffer: vk::Buffer,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    capacity: usize,
+}
+
+/// Materials referenced by the command buffer of one swapchain image, every
+/// change takes the next slot and is bound through a dynamic offset. Buffers
+/// outgrown mid frame are kept until the image is acquired again.
+#[derive(Debug, Default)]
+pub(super) struct MaterialRing {
+    current: Option<MaterialBuffer>,
+    retired: Vec<MaterialBuffer>,
+    len: usize,
+    bound: Option<MaterialUniform>,
+}
+
+impl MaterialUniform {
+    pub(super) fn new(albedo: Vector4, color_mode: u32, alpha: f32) -> Self {
+        Self {
+            albedo,
+            color_mode,
+            alpha,
+            ..Default::default()
+        }
+    }
+}
+
+impl Device {
+    /// Distance between material slots, uniform buffer offsets have to be
+    /// multiples of `minUniformBufferOffsetAlignment`.
+    fn material_stride(&self) -> usize {
+        let alignment = self
+            .config
+            .properties
+            .limits
+            .min_uniform_buffer_offset_alignment
+            .max(1) as usize;
+        size_of::<MaterialUniform>().div_ceil(alignment) * alignment
+    }
+
+    fn create_material_buffer(&self, capacity: usize) -> VkResult<MaterialBuffer> {
+        let mut partial = PartialObjects::new(&self.device);
+        let buffer = partial.add(unsafe {
+            self.device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .usage(vk::BufferUsageFlags::UNIFORM_BUFFER)
+                    .size((capacity * self.material_stride()) as vk::DeviceSize)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .queue_family_indices(&[self.config.queue_families.graphics]),
+                None,
+            )?
+        });
+        let allocation = Device::allocate_buffer_memory(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            buffer,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        let descriptor_pool = partial.add(unsafe {
+            self.device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(1)
+                    .pool_sizes(&[vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+                        descriptor_count: 1,
+                    }]),
+                None,
+            )?
+        });
+        let descriptor_set = unsafe {
+            self.device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&[self.layout.material_set_layout]),
+            )?[0]
+        };
+        unsafe {
+            self.device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .buffer_info(&[vk::DescriptorBufferInfo {
+                        buffer,
+                        offset: 0,
+                        range: size_of::<MaterialUniform>() as vk::DeviceSize,
+                    }])
+                    .build()],
+                &[],
+            );
+        }
+        partial.finish();
+        Ok(MaterialBuffer {
+            allocation,
+            buffer,
+            descriptor_pool,
+            descriptor_set,
+            capacity,
+        })
+    }
+
+    /// Called once the fence of `image_index` has been waited on, the
+    /// previous use of its materials has finished by then.
+    pub(super) fn reset_materials(&mut self, image_index: usize) {
+        if self.materials.len() <= image_index {
+            self.materials
+                .resize_with(image_index + 1, MaterialRing::default);
+        }
+        let ring = &mut self.materials[image_index];
+        for mut buffer in ring.retired.drain(..) {
+            Device::destroy_material_buffer(&self.device, &mut buffer);
+        }
+        ring.len = 0;
+        ring.bound = None;
+    }
+
+    /// Writes the current albedo and color mode with the opacity of the next
+    /// draws to a new slot and binds it, unless the bound slot already holds
+    /// the same values. Opacity only matters to the blending pipeline.
+    pub(super) fn bind_material(&mut self, frame: &Frame, alpha: f32) -> VkResult<()> {
+        let material = MaterialUniform::new(self.albedo, self.color_mode as u32, alpha);
+        let image_index = frame.image_index as usize;
+        if self.materials[image_index].bound == Some(material) {
+            return Ok(());
+        }
+        let full = match &self.materials[image_index].current {
+            Some(buffer) => self.materials[image_index].len == buffer.capacity,
+            None => true,
+        };
+        if full {
+            let capacity = usize::max(
+                MIN_MATERIAL_SLOTS,
+                2 * self.materials[image_index]
+                    .current
+                    .as_ref()
+                    .map_or(0, |buffer| buffer.capacity),
+            );
+            let buffer = self.create_material_buffer(capacity)?;
+            let ring = &mut self.materials[image_index];
+            // Slots written earlier this frame are still read by recorded draws
+            if let Some(old) = ring.current.replace(buffer) {
+                ring.retired.push(old);
+            }
+            ring.len = 0;
+        }
+        let stride = self.material_stride();
+        let ring = &mut self.materials[image_index];
+        let buffer = ring.current.as_ref().unwrap();
+        let offset = ring.len * stride;
+        buffer
+            .allocation
+            .write_at(offset, bytemuck::bytes_of(&material));
+        unsafe {
+            self.device.cmd_bind_descriptor_sets(
+                frame.command,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.layout.pipeline_layout,
+                0,
+                &[buffer.descriptor_set],
+                &[offset as u32],
+            );
+        }
+        ring.len += 1;
+        ring.bound = Some(material);
+        Ok(())
+    }
+
+    fn destroy_material_buffer(device: &ash::Device, buffer: &mut MaterialBuffer) {
+        unsafe {
+            device.destroy_descriptor_pool(buffer.descriptor_pool, None);
+            device.destroy_buffer(buffer.buffer, None);
+        }
+    }
+
+    pub(super) fn destroy_material_ring(device: &ash::Device, ring: &mut MaterialRing) {
+        for buffer in ring.retired.iter_mut().chain(ring.current.as_mut()) {
+            Device::destroy_material_buffer(device, buffer);
+        }
+    }
+}
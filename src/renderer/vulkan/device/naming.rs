@@ -0,0 +1,100 @@
+use super::{pipeline::PipelineState, Device};
+use ash::{extensions::ext, prelude::VkResult, vk};
+#[cfg(debug_assertions)]
+use {crate::log, std::ffi::CString};
+
+impl Device {
+    #[cfg(debug_assertions)]
+    pub(super) fn name_object<T: vk::Handle>(
+        device: &ash::Device,
+        debug_utils: Option<&ext::DebugUtils>,
+        object: T,
+        object_type: vk::ObjectType,
+        name: &str,
+    ) {
+        let debug_utils = match debug_utils {
+            Some(debug_utils) => debug_utils,
+            None => return,
+        };
+        let object_name = match CString::new(name) {
+            Ok(object_name) => object_name,
+            Err(_) => {
+                log::warning(&format!(
+                    "Vulkan object name [{}] contains a nul byte",
+                    name
+                ));
+                return;
+            }
+        };
+        let result = unsafe {
+            debug_utils.debug_utils_set_object_name(
+                device.handle(),
+                &vk::DebugUtilsObjectNameInfoEXT::builder()
+                    .object_type(object_type)
+                    .object_handle(object.as_raw())
+                    .object_name(&object_name),
+            )
+        };
+        if let Err(err) = result {
+            log::warning(&format!("Failed to name Vulkan object [{}]: {}", name, err));
+        }
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[inline]
+    pub(super) fn name_object<T: vk::Handle>(
+        _device: &ash::Device,
+        _debug_utils: Option<&ext::DebugUtils>,
+        _object: T,
+        _object_type: vk::ObjectType,
+        _name: &str,
+    ) {
+    }
+
+    pub(super) fn name_swapchain_objects(&self) {
+        let debug_utils = self.debug_utils.as_ref();
+        Device::name_object(
+            &self.device,
+            debug_utils,
+            self.swapchain.handle(),
+            vk::ObjectType::SWAPCHAIN_KHR,
+            "Swapchain",
+        );
+        for (index, &framebuffer) in self.swapchain.framebuffers().iter().enumerate() {
+            Device::name_object(
+                &self.device,
+                debug_utils,
+                framebuffer,
+                vk::ObjectType::FRAMEBUFFER,
+                &format!("Swapchain::framebuffer[{}]", index),
+            );
+        }
+    }
+
+    pub(super) fn name_mesh_buffer(&self) {
+        Device::name_object(
+            &self.device,
+            self.debug_utils.as_ref(),
+            self.mesh_data.buffer(),
+            vk::ObjectType::BUFFER,
+            "MeshData::buffer",
+        );
+    }
+
+    pub(super) fn pipeline_variant(&mut self, state: PipelineState) -> VkResult<vk::Pipeline> {
+        let created = !self.pipeline.has_variant(&state);
+        let pipeline =
+            self.pipeline
+                .variant(&self.device, &self.layout, self.render_pass, state)?;
+        if created {
+            Device::name_object(
+                &self.device,
+                self.debug_utils.as_ref(),
+                pipeline,
+                vk::ObjectType::PIPELINE,
+                &format!("Pipeline::{:?}", state),
+            );
+        }
+        Ok(pipeline)
+    }
+}
@@ -0,0 +1,149 @@
+use ash::vk;
+
+/// Vulkan object created by a construction that may still fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PartialObject {
+    DescriptorSetLayout(vk::DescriptorSetLayout),
+    DescriptorPool(vk::DescriptorPool),
+    PipelineLayout(vk::PipelineLayout),
+    ShaderModule(vk::ShaderModule),
+    Pipeline(vk::Pipeline),
+    Image(vk::Image),
+    ImageView(vk::ImageView),
+    Sampler(vk::Sampler),
+    Buffer(vk::Buffer),
+}
+
+pub(super) trait DestroyPartial {
+    fn destroy_partial(&self, object: PartialObject);
+}
+
+/// Same role as `PartialDevice` for objects built in several fallible
+/// steps: everything added is destroyed in reverse creation order when the
+/// guard is dropped, unless `finish` hands the objects over first.
+pub(super) struct PartialObjects<'a, D: DestroyPartial = ash::Device> {
+    device: &'a D,
+    objects: Vec<PartialObject>,
+}
+
+impl DestroyPartial for ash::Device {
+    fn destroy_partial(&self, object: PartialObject) {
+        unsafe {
+            match object {
+                PartialObject::DescriptorSetLayout(layout) => {
+                    self.destroy_descriptor_set_layout(layout, None)
+                }
+                PartialObject::DescriptorPool(pool) => self.destroy_descriptor_pool(pool, None),
+                PartialObject::PipelineLayout(layout) => self.destroy_pipeline_layout(layout, None),
+                PartialObject::ShaderModule(module) => self.destroy_shader_module(module, None),
+                PartialObject::Pipeline(pipeline) => self.destroy_pipeline(pipeline, None),
+                PartialObject::Image(image) => self.destroy_image(image, None),
+                PartialObject::ImageView(view) => self.destroy_image_view(view, None),
+                PartialObject::Sampler(sampler) => self.destroy_sampler(sampler, None),
+                PartialObject::Buffer(buffer) => self.destroy_buffer(buffer, None),
+            }
+        }
+    }
+}
+
+impl<'a, D: DestroyPartial> PartialObjects<'a, D> {
+    pub(super) fn new(device: &'a D) -> Self {
+        Self {
+            device,
+            objects: Vec::new(),
+        }
+    }
+
+    /// Registers `object` for cleanup and passes it through.
+    pub(super) fn add<T: Copy + Into<PartialObject>>(&mut self, object: T) -> T {
+        self.objects.push(object.into());
+        object
+    }
+
+    pub(super) fn finish(mut self) {
+        self.objects.clear();
+    }
+}
+
+impl<'a, D: DestroyPartial> Drop for PartialObjects<'a, D> {
+    fn drop(&mut self) {
+        for object in self.objects.drain(..).rev() {
+            self.device.destroy_partial(object);
+        }
+    }
+}
+
+macro_rules! partial_object_from {
+    ($($variant:ident),+ $(,)?) => {
+        $(impl From<vk::$variant> for PartialObject {
+            fn from(object: vk::$variant) -> Self {
+                PartialObject::$variant(object)
+            }
+        })+
+    };
+}
+
+partial_object_from!(
+    DescriptorSetLayout,
+    DescriptorPool,
+    PipelineLayout,
+    ShaderModule,
+    Pipeline,
+    Image,
+    ImageView,
+    Sampler,
+    Buffer,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ash::vk::Handle;
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct Recorder {
+        destroyed: RefCell<Vec<PartialObject>>,
+    }
+
+    impl DestroyPartial for Recorder {
+        fn destroy_partial(&self, object: PartialObject) {
+            self.destroyed.borrow_mut().push(object);
+        }
+    }
+
+    // Mirrors the pipeline constructions: three objects, then a step that
+    // fails when `fail` is set
+    fn build(device: &Recorder, fail: bool) -> Result<[PartialObject; 3], &'static str> {
+        let mut partial = PartialObjects::new(device);
+        let layout = partial.add(vk::DescriptorSetLayout::from_raw(1));
+        let pool = partial.add(vk::DescriptorPool::from_raw(2));
+        let pipeline_layout = partial.add(vk::PipelineLayout::from_raw(3));
+        if fail {
+            Err("shader module missing")?;
+        }
+        partial.finish();
+        Ok([layout.into(), pool.into(), pipeline_layout.into()])
+    }
+
+    #[test]
+    fn failure_halfway_destroys_created_objects_in_reverse() {
+        let device = Recorder::default();
+        assert!(build(&device, true).is_err());
+        assert_eq!(
+            *device.destroyed.borrow(),
+            vec![
+                PartialObject::PipelineLayout(vk::PipelineLayout::from_raw(3)),
+                PartialObject::DescriptorPool(vk::DescriptorPool::from_raw(2)),
+                PartialObject::DescriptorSetLayout(vk::DescriptorSetLayout::from_raw(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_keeps_objects_alive() {
+        let device = Recorder::default();
+        assert!(build(&device, false).is_ok());
+        assert!(device.destroyed.borrow().is_empty());
+    }
+}
@@ -1,21 +1,129 @@
-use super::{Device, Layout, Swapchain};
-use ash::{self, prelude::VkResult, vk};
-use std::{ffi::CStr, fs::File, io::Read, path::Path};
+use super::{Device, Layout};
+use crate::{
+    renderer::{CullMode, ShaderSource, Shaders, StencilCompare},
+    utils::{ResultExt, StaticResult},
+};
+use ash::{self, prelude::VkResult, util::read_spv, vk};
+use std::{collections::HashMap, ffi::CStr, fs::File, io::Cursor, path::Path};
 
 const VERTEX_SHADER_PATH: &'static str = "shaders/spv/vert.spv";
 const FRAGMENT_SHADER_PATH: &'static str = "shaders/spv/frag.spv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct PipelineState {
+    pub cull_mode: CullMode,
+    pub depth_bias: bool,
+    pub stencil_compare: Option<StencilCompare>,
+    pub lines: bool,
+    pub blend: bool,
+    pub depth_test: bool,
+    pub depth_write: bool,
+}
+
+#[derive(Debug)]
 pub(super) struct Pipeline {
-    pub pipeline: vk::Pipeline,
+    shaders: Vec<vk::PipelineShaderStageCreateInfo>,
+    sample_count: vk::SampleCountFlags,
+    variants: HashMap<PipelineState, vk::Pipeline>,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        Self {
+            cull_mode: CullMode::Back,
+            depth_bias: false,
+            stencil_compare: None,
+            lines: false,
+            blend: false,
+            depth_test: true,
+            depth_write: true,
+        }
+    }
+}
+
+impl Pipeline {
+    pub(super) fn has_variant(&self, state: &PipelineState) -> bool {
+        self.variants.contains_key(state)
+    }
+
+    pub(super) fn variant(
+        &mut self,
+        device: &ash::Device,
+        layout: &Layout,
+        render_pass: vk::RenderPass,
+        state: PipelineState,
+    ) -> VkResult<vk::Pipeline> {
+        if let Some(&pipeline) = self.variants.get(&state) {
+            return Ok(pipeline);
+        }
+        let pipeline = Device::create_pipeline_variant(
+            device,
+            &self.shaders,
+            layout,
+            render_pass,
+            self.sample_count,
+            state,
+        )?;
+        self.variants.insert(state, pipeline);
+        Ok(pipeline)
+    }
 }
 
 impl Device {
     pub(super) fn create_pipeline(
         device: &ash::Device,
+        sample_count: vk::SampleCountFlags,
+        shaders: Option<&Shaders>,
+    ) -> StaticResult<Pipeline> {
+        Ok(Pipeline {
+            shaders: Device::load_shaders(device, shaders)?,
+            sample_count,
+            variants: HashMap::new(),
+        })
+    }
+
+    fn create_pipeline_variant(
+        device: &ash::Device,
+        shaders: &[vk::PipelineShaderStageCreateInfo],
         layout: &Layout,
-        swapchain: &Swapchain,
         render_pass: vk::RenderPass,
-    ) -> VkResult<Pipeline> {
-        let shaders = Device::load_shaders(device)?;
+        sample_count: vk::SampleCountFlags,
+        state: PipelineState,
+    ) -> VkResult<vk::Pipeline> {
+        let cull_mode = match state.cull_mode {
+            CullMode::None => vk::CullModeFlags::NONE,
+            CullMode::Front => vk::CullModeFlags::FRONT,
+            CullMode::Back => vk::CullModeFlags::BACK,
+        };
+        let stencil_compare = match state.stencil_compare {
+            None | Some(StencilCompare::Always) => vk::CompareOp::ALWAYS,
+            Some(StencilCompare::Never) => vk::CompareOp::NEVER,
+            Some(StencilCompare::Less) => vk::CompareOp::LESS,
+            Some(StencilCompare::Equal) => vk::CompareOp::EQUAL,
+            Some(StencilCompare::LessOrEqual) => vk::CompareOp::LESS_OR_EQUAL,
+            Some(StencilCompare::Greater) => vk::CompareOp::GREATER,
+            Some(StencilCompare::NotEqual) => vk::CompareOp::NOT_EQUAL,
+            Some(StencilCompare::GreaterOrEqual) => vk::CompareOp::GREATER_OR_EQUAL,
+        };
+
+        let mut dynamic_states = vec![vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        if state.depth_bias {
+            dynamic_states.push(vk::DynamicState::DEPTH_BIAS);
+        }
+        if state.stencil_compare.is_some() {
+            dynamic_states.extend_from_slice(&[
+                vk::DynamicState::STENCIL_COMPARE_MASK,
+                vk::DynamicState::STENCIL_WRITE_MASK,
+                vk::DynamicState::STENCIL_REFERENCE,
+            ]);
+        }
+        let stencil_op = vk::StencilOpState {
+            fail_op: vk::StencilOp::KEEP,
+            pass_op: vk::StencilOp::REPLACE,
+            depth_fail_op: vk::StencilOp::KEEP,
+            compare_op: stencil_compare,
+            ..Default::default()
+        };
 
         let pipeline = unsafe {
             device
@@ -25,7 +133,7 @@ impl Device {
                         .color_blend_state(
                             &vk::PipelineColorBlendStateCreateInfo::builder().attachments(&[
                                 vk::PipelineColorBlendAttachmentState::builder()
-                                    .blend_enable(false)
+                                    .blend_enable(state.blend)
                                     .color_blend_op(vk::BlendOp::ADD)
                                     .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
                                     .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
@@ -38,17 +146,25 @@ impl Device {
                         )
                         .depth_stencil_state(
                             &vk::PipelineDepthStencilStateCreateInfo::builder()
-                                .depth_write_enable(true) //TODO: ENABLE
-                                .depth_test_enable(true) //TODO: ENABLE
-                                .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL),
+                                .depth_write_enable(state.depth_write && !state.blend)
+                                .depth_test_enable(state.depth_test)
+                                .depth_compare_op(vk::CompareOp::LESS_OR_EQUAL)
+                                .stencil_test_enable(state.stencil_compare.is_some())
+                                .front(stencil_op)
+                                .back(stencil_op),
                         )
                         .input_assembly_state(
-                            &vk::PipelineInputAssemblyStateCreateInfo::builder()
-                                .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                            &vk::PipelineInputAssemblyStateCreateInfo::builder().topology(
+                                if state.lines {
+                                    vk::PrimitiveTopology::LINE_LIST
+                                } else {
+                                    vk::PrimitiveTopology::TRIANGLE_LIST
+                                },
+                            ),
                         )
                         .multisample_state(
                             &vk::PipelineMultisampleStateCreateInfo::builder()
-                                .rasterization_samples(vk::SampleCountFlags::TYPE_1),
+                                .rasterization_samples(sample_count),
                         )
                         .rasterization_state(
                             &vk::PipelineRasterizationStateCreateInfo::builder()
@@ -56,10 +172,11 @@ impl Device {
                                 .polygon_mode(vk::PolygonMode::FILL)
                                 .line_width(1.0f32)
                                 .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-                                .cull_mode(vk::CullModeFlags::BACK), //TODO: ENABLE
+                                .depth_bias_enable(state.depth_bias)
+                                .cull_mode(cull_mode),
                         )
                         .render_pass(render_pass)
-                        .stages(&shaders)
+                        .stages(shaders)
                         .subpass(0)
                         .vertex_input_state(
                             &vk::PipelineVertexInputStateCreateInfo::builder()
@@ -68,18 +185,12 @@ impl Device {
                         )
                         .viewport_state(
                             &vk::PipelineViewportStateCreateInfo::builder()
-                                .viewports(&[vk::Viewport {
-                                    width: swapchain.extent.width as f32,
-                                    height: -(swapchain.extent.height as f32),
-                                    x: 0.0 as f32,
-                                    y: swapchain.extent.height as f32,
-                                    min_depth: 0.0f32,
-                                    max_depth: 1.0f32,
-                                }])
-                                .scissors(&[vk::Rect2D {
-                                    offset: vk::Offset2D { x: 0, y: 0 },
-                                    extent: swapchain.extent,
-                                }]),
+                                .viewport_count(1)
+                                .scissor_count(1),
+                        )
+                        .dynamic_state(
+                            &vk::PipelineDynamicStateCreateInfo::builder()
+                                .dynamic_states(&dynamic_states),
                         )
                         .layout(layout.pipeline_layout)
                         .build()],
@@ -88,36 +199,38 @@ impl Device {
                 .map_err(|(_, err)| err)?[0]
         };
 
-        for shader in shaders {
-            unsafe {
-                device.destroy_shader_module(shader.module, None);
-            }
-        }
-
-        Ok(Pipeline { pipeline })
+        Ok(pipeline)
     }
 
     fn shader_entry_point() -> &'static CStr {
         unsafe { CStr::from_bytes_with_nul_unchecked(b"main\0") }
     }
 
-    fn load_shader_module(
+    /// SPIR-V words of `source`, reading the file for path sources.
+    fn shader_code(source: &ShaderSource) -> StaticResult<Vec<u32>> {
+        match source {
+            ShaderSource::Path(path) => {
+                let mut file = File::open(path)
+                    .with_context(|| format!("Failed to open shader {}", path.display()))?;
+                read_spv(&mut file)
+                    .with_context(|| format!("Failed to read shader {}", path.display()))
+            }
+            ShaderSource::SpirV(code) => {
+                read_spv(&mut Cursor::new(code)).context("Failed to read shader code")
+            }
+        }
+    }
+
+    fn create_shader_stage(
         device: &ash::Device,
         stage: vk::ShaderStageFlags,
-        path: &Path,
-    ) -> VkResult<vk::PipelineShaderStageCreateInfo> {
-        let reader = File::open(path).unwrap();
-        let bytes: Vec<_> = reader.bytes().filter_map(|b| b.ok()).collect();
+        source: &ShaderSource,
+    ) -> StaticResult<vk::PipelineShaderStageCreateInfo> {
+        let code = Device::shader_code(source)?;
         let module = unsafe {
-            device.create_shader_module(
-                &vk::ShaderModuleCreateInfo {
-                    p_code: bytes.as_ptr() as *const u32,
-                    code_size: bytes.len(),
-                    ..Default::default()
-                },
-                None,
-            )?
-        };
+            device.create_shader_module(&vk::ShaderModuleCreateInfo::builder().code(&code), None)
+        }
+        .with_context(|| format!("Failed to create {:?} shader module", stage))?;
         Ok(vk::PipelineShaderStageCreateInfo::builder()
             .module(module)
             .stage(stage)
@@ -125,23 +238,42 @@ impl Device {
             .build())
     }
 
-    fn load_shaders(device: &ash::Device) -> VkResult<Vec<vk::PipelineShaderStageCreateInfo>> {
-        let vertex = Device::load_shader_module(
-            device,
-            vk::ShaderStageFlags::VERTEX,
-            Path::new(VERTEX_SHADER_PATH),
-        )?;
-        let framgnet = Device::load_shader_module(
-            device,
-            vk::ShaderStageFlags::FRAGMENT,
-            Path::new(FRAGMENT_SHADER_PATH),
-        )?;
-        Ok(vec![vertex, framgnet])
+    pub(super) fn load_shader_module(
+        device: &ash::Device,
+        stage: vk::ShaderStageFlags,
+        path: &Path,
+    ) -> StaticResult<vk::PipelineShaderStageCreateInfo> {
+        Device::create_shader_stage(device, stage, &ShaderSource::Path(path.to_owned()))
+    }
+
+    /// Built-in shaders are read from `VERTEX_SHADER_PATH` and
+    /// `FRAGMENT_SHADER_PATH` unless `shaders` replaces them.
+    fn load_shaders(
+        device: &ash::Device,
+        shaders: Option<&Shaders>,
+    ) -> StaticResult<Vec<vk::PipelineShaderStageCreateInfo>> {
+        let shaders = match shaders {
+            Some(shaders) => shaders.clone(),
+            None => Shaders {
+                vertex: Path::new(VERTEX_SHADER_PATH).into(),
+                fragment: Path::new(FRAGMENT_SHADER_PATH).into(),
+            },
+        };
+        let vertex =
+            Device::create_shader_stage(device, vk::ShaderStageFlags::VERTEX, &shaders.vertex)?;
+        let fragment =
+            Device::create_shader_stage(device, vk::ShaderStageFlags::FRAGMENT, &shaders.fragment)?;
+        Ok(vec![vertex, fragment])
     }
 
     pub(super) fn destory_pipeline(device: &ash::Device, pipeline: &mut Pipeline) {
         unsafe {
-            device.destroy_pipeline(pipeline.pipeline, None);
+            for (_, variant) in pipeline.variants.drain() {
+                device.destroy_pipeline(variant, None);
+            }
+            for shader in &pipeline.shaders {
+                device.destroy_shader_module(shader.module, None);
+            }
         }
     }
 }
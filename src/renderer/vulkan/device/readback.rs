@@ -0,0 +1,165 @@
+use super::{command::CommandType, Device};
+use crate::utils::StaticResult;
+use ash::{prelude::VkResult, vk};
+use std::convert::TryInto;
+
+const DEPTH_TEXEL_SIZE: usize = 4;
+
+impl Device {
+    fn copy_depth_texel(&self, x: u32, y: u32) -> VkResult<()> {
+        let command = Device::begin_single_time_command(
+            &self.device,
+            &self.config,
+            &self.command_pools,
+            &self.queues,
+            CommandType::Graphics,
+        )?;
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| {
+            vk::ImageMemoryBarrier::builder()
+                .image(self.swapchain.depth_image())
+                .old_layout(old_layout)
+                .new_layout(new_layout)
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: self.config.depth_aspect(),
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                })
+                .build()
+        };
+        unsafe {
+            self.device.cmd_pipeline_barrier(
+                command.buffer,
+                vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                    vk::AccessFlags::TRANSFER_READ,
+                )],
+            );
+            self.device.cmd_copy_image_to_buffer(
+                command.buffer,
+                self.swapchain.depth_image(),
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                self.staging_buffer.buffer(),
+                &[vk::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_row_length: 0,
+                    buffer_image_height: 0,
+                    image_subresource: vk::ImageSubresourceLayers {
+                        aspect_mask: vk::ImageAspectFlags::DEPTH,
+                        mip_level: 0,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    },
+                    image_offset: vk::Offset3D {
+                        x: x as i32,
+                        y: y as i32,
+                        z: 0,
+                    },
+                    image_extent: vk::Extent3D {
+                        width: 1,
+                        height: 1,
+                        depth: 1,
+                    },
+                }],
+            );
+            // The render pass expects the depth attachment in this layout
+            self.device.cmd_pipeline_barrier(
+                command.buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                    vk::AccessFlags::TRANSFER_READ,
+                    vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                        | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                )],
+            );
+        }
+        let fence = self.staging_buffer.fence();
+        let result = command
+            .submit(&self.device, Some(fence))
+            .and_then(|_| unsafe {
+                self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+                self.device.reset_fences(&[fence])
+            });
+        Device::destory_command(&self.device, command);
+        result
+    }
+
+    fn decode_depth(format: vk::Format, texel: [u8; DEPTH_TEXEL_SIZE]) -> StaticResult<f32> {
+        let depth = match format {
+            vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT => f32::from_ne_bytes(texel),
+            vk::Format::D24_UNORM_S8_UINT | vk::Format::X8_D24_UNORM_PACK32 => {
+                (u32::from_ne_bytes(texel) & 0x00ff_ffff) as f32 / 0x00ff_ffff as f32
+            }
+            vk::Format::D16_UNORM | vk::Format::D16_UNORM_S8_UINT => {
+                u16::from_ne_bytes(texel[..2].try_into().unwrap()) as f32 / u16::MAX as f32
+            }
+            _ => Err("Unsupported depth format for readback")?,
+        };
+        Ok(depth)
+    }
+
+    pub fn read_depth(&mut self, x: u32, y: u32) -> StaticResult<f32> {
+        if self.config.sample_count != vk::SampleCountFlags::TYPE_1 {
+            Err("Depth readback is not supported with multisampling enabled")?;
+        }
+        let extent = self.swapchain.extent;
+        if x >= extent.width || y >= extent.height {
+            Err(format!(
+                "Depth readback pixel ({}, {}) outside of {}x{} framebuffer",
+                x, y, extent.width, extent.height
+            ))?;
+        }
+        unsafe { self.device.device_wait_idle()? };
+        Device::reserve_staging_buffer(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            &mut self.staging_buffer,
+            DEPTH_TEXEL_SIZE,
+        )?;
+        self.copy_depth_texel(x, y)?;
+        let mut texel = [0u8; DEPTH_TEXEL_SIZE];
+        self.staging_buffer.allocation().read(&mut texel);
+        Device::decode_depth(self.config.depth_format, texel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_depth_formats() {
+        let depth = |format, texel| Device::decode_depth(format, texel).unwrap();
+        assert_eq!(depth(vk::Format::D32_SFLOAT, 0.25f32.to_ne_bytes()), 0.25);
+        assert_eq!(
+            depth(vk::Format::D24_UNORM_S8_UINT, 0xab00_0000u32.to_ne_bytes()),
+            0.0
+        );
+        assert_eq!(
+            depth(vk::Format::D24_UNORM_S8_UINT, 0x00ff_ffffu32.to_ne_bytes()),
+            1.0
+        );
+        let max = u16::MAX.to_ne_bytes();
+        assert_eq!(depth(vk::Format::D16_UNORM, [max[0], max[1], 0, 0]), 1.0);
+        assert!(Device::decode_depth(vk::Format::R8_UNORM, [0; 4]).is_err());
+    }
+}
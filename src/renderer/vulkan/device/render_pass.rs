@@ -9,49 +9,85 @@ impl Device {
     pub(super) fn create_render_pass(
         device: &ash::Device,
         config: &PhysicalDeviceConfig,
+        sample_count: vk::SampleCountFlags,
     ) -> VkResult<vk::RenderPass> {
-        let attachments = [
+        let multisampled = sample_count != vk::SampleCountFlags::TYPE_1;
+        let mut attachments = vec![
             vk::AttachmentDescription {
-                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                final_layout: if multisampled {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                } else {
+                    vk::ImageLayout::PRESENT_SRC_KHR
+                },
                 initial_layout: vk::ImageLayout::UNDEFINED,
                 load_op: vk::AttachmentLoadOp::CLEAR,
-                store_op: vk::AttachmentStoreOp::DONT_CARE,
+                store_op: if multisampled {
+                    vk::AttachmentStoreOp::DONT_CARE
+                } else {
+                    vk::AttachmentStoreOp::STORE
+                },
                 stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
                 stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-                format: config.depth_format,
-                samples: vk::SampleCountFlags::TYPE_1,
+                format: config.surface_format.format,
+                samples: sample_count,
                 ..Default::default()
             },
             vk::AttachmentDescription {
-                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                final_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
                 initial_layout: vk::ImageLayout::UNDEFINED,
                 load_op: vk::AttachmentLoadOp::CLEAR,
                 store_op: vk::AttachmentStoreOp::STORE,
+                stencil_load_op: if config.has_stencil() {
+                    vk::AttachmentLoadOp::CLEAR
+                } else {
+                    vk::AttachmentLoadOp::DONT_CARE
+                },
+                stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
+                format: config.depth_format,
+                samples: sample_count,
+                ..Default::default()
+            },
+        ];
+        if multisampled {
+            attachments.push(vk::AttachmentDescription {
+                final_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                initial_layout: vk::ImageLayout::UNDEFINED,
+                load_op: vk::AttachmentLoadOp::DONT_CARE,
+                store_op: vk::AttachmentStoreOp::STORE,
                 stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
                 stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
                 format: config.surface_format.format,
                 samples: vk::SampleCountFlags::TYPE_1,
                 ..Default::default()
-            },
-        ];
+            });
+        }
 
-        let depth_reference = vk::AttachmentReference {
+        let color_reference = [vk::AttachmentReference {
             attachment: 0,
+            layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            ..Default::default()
+        }];
+
+        let depth_reference = vk::AttachmentReference {
+            attachment: 1,
             layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
             ..Default::default()
         };
 
-        let color_reference = [vk::AttachmentReference {
-            attachment: 1,
+        let resolve_reference = [vk::AttachmentReference {
+            attachment: 2,
             layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
-            ..Default::default()
         }];
 
-        let subpasses = [vk::SubpassDescription::builder()
+        let subpass = vk::SubpassDescription::builder()
             .color_attachments(&color_reference)
             .depth_stencil_attachment(&depth_reference)
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .build()];
+            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS);
+        let subpasses = [if multisampled {
+            subpass.resolve_attachments(&resolve_reference).build()
+        } else {
+            subpass.build()
+        }];
 
         let dependencies = [
             vk::SubpassDependency {
@@ -0,0 +1,393 @@
+use super::{
+    command::CommandType, partial::PartialObjects, Allocation, Allocator, Device,
+    PhysicalDeviceConfig,
+};
+use crate::{
+    log,
+    math::types::Matrix4,
+    renderer::{mesh::Vertex, Mesh, MeshHandle, Skeleton, SkinnedVertex},
+    utils::{ResultExt, StaticResult},
+};
+use ash::{prelude::VkResult, vk};
+use std::{mem::size_of, path::Path};
+
+const SKIN_SHADER_PATH: &str = "shaders/spv/skin_comp.spv";
+const SKIN_LOCAL_SIZE: u32 = 64;
+const SKIN_PUSH_CONSTANT_SIZE: u32 = 3 * size_of::<u32>() as u32;
+
+#[derive(Debug)]
+pub(super) struct SkinningPipeline {
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    shader: vk::ShaderModule,
+    joint_buffer: Option<(vk::Buffer, Allocation)>,
+    joint_capacity: usize,
+}
+
+/// Mesh uploaded with `Device::upload_skinned_mesh`, its vertices in the
+/// shared mesh buffer are overwritten by `Device::skin_uploaded_mesh`.
+#[derive(Debug)]
+pub(super) struct SkinnedMesh {
+    mesh: MeshHandle,
+    bind_pose: vk::Buffer,
+    _allocation: Allocation,
+    vertex_count: usize,
+}
+
+impl Device {
+    fn create_skinning_pipeline(device: &ash::Device) -> StaticResult<SkinningPipeline> {
+        let bindings: Vec<_> = (0..3)
+            .map(|binding| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .build()
+            })
+            .collect();
+        let mut partial = PartialObjects::new(device);
+        let descriptor_set_layout = partial.add(unsafe {
+            device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings),
+                None,
+            )?
+        });
+        let descriptor_pool = partial.add(unsafe {
+            device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(1)
+                    .pool_sizes(&[vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::STORAGE_BUFFER,
+                        descriptor_count: bindings.len() as u32,
+                    }]),
+                None,
+            )?
+        });
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&[descriptor_set_layout]),
+            )?[0]
+        };
+        let pipeline_layout = partial.add(unsafe {
+            device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&[descriptor_set_layout])
+                    .push_constant_ranges(&[vk::PushConstantRange {
+                        stage_flags: vk::ShaderStageFlags::COMPUTE,
+                        offset: 0,
+                        size: SKIN_PUSH_CONSTANT_SIZE,
+                    }]),
+                None,
+            )?
+        });
+        let stage = Device::load_shader_module(
+            device,
+            vk::ShaderStageFlags::COMPUTE,
+            Path::new(SKIN_SHADER_PATH),
+        )?;
+        partial.add(stage.module);
+        let pipeline = unsafe {
+            device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    &[vk::ComputePipelineCreateInfo::builder()
+                        .stage(stage)
+                        .layout(pipeline_layout)
+                        .build()],
+                    None,
+                )
+                .map_err(|(_, err)| err)
+                .context("Failed to create skinning pipeline")?[0]
+        };
+        partial.finish();
+        Ok(SkinningPipeline {
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            shader: stage.module,
+            joint_buffer: None,
+            joint_capacity: 0,
+        })
+    }
+
+    fn reserve_joint_buffer(
+        device: &ash::Device,
+        config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
+        skinning: &mut SkinningPipeline,
+        joint_count: usize,
+    ) -> VkResult<()> {
+        if joint_count <= skinning.joint_capacity && skinning.joint_buffer.is_some() {
+            return Ok(());
+        }
+        let capacity = joint_count.max(1).next_power_of_two();
+        let buffer = unsafe {
+            device.create_buffer(
+                &vk::BufferCreateInfo::builder()
+                    .usage(vk::BufferUsageFlags::STORAGE_BUFFER)
+                    .size((capacity * size_of::<Matrix4>()) as vk::DeviceSize)
+                    .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                    .queue_family_indices(&[config.queue_families.graphics]),
+                None,
+            )?
+        };
+        let allocation = Device::allocate_buffer_memory(
+            device,
+            config,
+            allocator,
+            buffer,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+        )?;
+        if let Some((old, _)) = skinning.joint_buffer.replace((buffer, allocation)) {
+            unsafe {
+                device.device_wait_idle()?;
+                device.destroy_buffer(old, None);
+            }
+        }
+        skinning.joint_capacity = capacity;
+        Ok(())
+    }
+
+    /// Records a compute dispatch into `command` that skins `vertex_count`
+    /// `SkinnedVertex` entries of `bind_pose_buffer` into `Vertex` entries of
+    /// `output_buffer` starting at `first_vertex`, followed by a barrier making
+    /// the output visible as vertex input. `command` must be recorded outside
+    /// of a render pass and executed before the next `skin_mesh` call, the
+    /// joint buffer and descriptor set are shared between calls.
+    pub fn skin_mesh(
+        &mut self,
+        command: vk::CommandBuffer,
+        skeleton: &Skeleton,
+        bind_pose_buffer: vk::Buffer,
+        output_buffer: vk::Buffer,
+        vertex_count: u32,
+        first_vertex: u32,
+    ) -> StaticResult<()> {
+        if self.skinning.is_none() {
+            self.skinning = Some(Device::create_skinning_pipeline(&self.device)?);
+        }
+        let skinning = self.skinning.as_mut().unwrap();
+        Device::reserve_joint_buffer(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            skinning,
+            skeleton.len(),
+        )?;
+        let (joint_buffer, joint_allocation) = skinning.joint_buffer.as_ref().unwrap();
+        joint_allocation.write(bytemuck::cast_slice(skeleton.joints()));
+
+        let buffer_infos = [
+            [vk::DescriptorBufferInfo {
+                buffer: *joint_buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }],
+            [vk::DescriptorBufferInfo {
+                buffer: bind_pose_buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }],
+            [vk::DescriptorBufferInfo {
+                buffer: output_buffer,
+                offset: 0,
+                range: vk::WHOLE_SIZE,
+            }],
+        ];
+        let writes: Vec<_> = buffer_infos
+            .iter()
+            .enumerate()
+            .map(|(binding, info)| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(skinning.descriptor_set)
+                    .dst_binding(binding as u32)
+                    .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                    .buffer_info(info)
+                    .build()
+            })
+            .collect();
+        let push_constants = [vertex_count, skeleton.len() as u32, first_vertex];
+        unsafe {
+            self.device.update_descriptor_sets(&writes, &[]);
+            self.device.cmd_bind_pipeline(
+                command,
+                vk::PipelineBindPoint::COMPUTE,
+                skinning.pipeline,
+            );
+            self.device.cmd_bind_descriptor_sets(
+                command,
+                vk::PipelineBindPoint::COMPUTE,
+                skinning.pipeline_layout,
+                0,
+                &[skinning.descriptor_set],
+                &[],
+            );
+            self.device.cmd_push_constants(
+                command,
+                skinning.pipeline_layout,
+                vk::ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::cast_slice(&push_constants),
+            );
+            self.device
+                .cmd_dispatch(command, vertex_count.div_ceil(SKIN_LOCAL_SIZE), 1, 1);
+            self.device.cmd_pipeline_barrier(
+                command,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::VERTEX_INPUT,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[vk::BufferMemoryBarrier::builder()
+                    .buffer(output_buffer)
+                    .offset(0)
+                    .size(vk::WHOLE_SIZE)
+                    .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                    .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .build()],
+                &[],
+            );
+        }
+        Ok(())
+    }
+
+    /// Uploads the bind pose as a regular mesh, so it can be drawn like any
+    /// other, and keeps a copy with joint influences for skinning.
+    pub fn upload_skinned_mesh(
+        &mut self,
+        vertices: &[SkinnedVertex],
+        indices: &[u32],
+    ) -> StaticResult<MeshHandle> {
+        let mesh = Mesh {
+            vertices: vertices.iter().map(|skinned| skinned.vertex).collect(),
+            indices: indices.to_vec(),
+        };
+        let handle = self.upload_mesh(&mesh)?;
+        let bind_pose: Vec<_> = vertices
+            .iter()
+            .map(|&skinned| SkinnedVertex {
+                vertex: Vertex {
+                    color: self.color_transfer.apply(skinned.vertex.color),
+                    ..skinned.vertex
+                },
+                ..skinned
+            })
+            .collect();
+        let byte_size = bind_pose.len() * size_of::<SkinnedVertex>();
+        let (buffer, allocation) = Device::create_mesh_buffer(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            byte_size.max(1),
+        )?;
+        Device::reserve_staging_buffer(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            &mut self.staging_buffer,
+            byte_size,
+        )?;
+        Device::copy_buffer_data(
+            &self.device,
+            &self.staging_buffer,
+            &self.config,
+            &self.command_pools,
+            &self.queues,
+            buffer,
+            0,
+            &bind_pose,
+        )?;
+        self.skinned_meshes.push(SkinnedMesh {
+            mesh: handle,
+            bind_pose: buffer,
+            _allocation: allocation,
+            vertex_count: vertices.len(),
+        });
+        Ok(handle)
+    }
+
+    /// Poses a mesh from `upload_skinned_mesh` with `skeleton` and waits for
+    /// the result, later draws of `mesh` use the skinned vertices.
+    pub fn skin_uploaded_mesh(
+        &mut self,
+        mesh: MeshHandle,
+        skeleton: &Skeleton,
+    ) -> StaticResult<()> {
+        let (bind_pose, vertex_count) = match self
+            .skinned_meshes
+            .iter()
+            .find(|skinned| skinned.mesh.0 == mesh.0)
+        {
+            Some(skinned) => (skinned.bind_pose, skinned.vertex_count),
+            None => Err(format!(
+                "Mesh handle {} was not uploaded as skinned",
+                mesh.0
+            ))?,
+        };
+        let first_vertex = self
+            .mesh_data
+            .first_vertex(mesh)
+            .ok_or_else(|| format!("Unknown mesh handle {}", mesh.0))?;
+        if vertex_count == 0 {
+            log::warning("Skipping skinning of empty mesh");
+            return Ok(());
+        }
+        unsafe { self.device.device_wait_idle()? };
+        let command = Device::begin_single_time_command(
+            &self.device,
+            &self.config,
+            &self.command_pools,
+            &self.queues,
+            CommandType::Graphics,
+        )?;
+        let output = self.mesh_data.buffer();
+        let result = self
+            .skin_mesh(
+                command.buffer,
+                skeleton,
+                bind_pose,
+                output,
+                vertex_count as u32,
+                first_vertex as u32,
+            )
+            .and_then(|_| {
+                let fence = self.staging_buffer.fence();
+                command.submit(&self.device, Some(fence))?;
+                unsafe {
+                    self.device.wait_for_fences(&[fence], true, u64::MAX)?;
+                    self.device.reset_fences(&[fence])?;
+                }
+                Ok(())
+            });
+        Device::destory_command(&self.device, command);
+        result
+    }
+
+    pub(super) fn destroy_skinned_mesh(device: &ash::Device, skinned: &mut SkinnedMesh) {
+        unsafe {
+            device.destroy_buffer(skinned.bind_pose, None);
+        }
+    }
+
+    pub(super) fn destroy_skinning_pipeline(device: &ash::Device, skinning: &mut SkinningPipeline) {
+        unsafe {
+            if let Some((buffer, _)) = skinning.joint_buffer.take() {
+                device.destroy_buffer(buffer, None);
+            }
+            device.destroy_pipeline(skinning.pipeline, None);
+            device.destroy_shader_module(skinning.shader, None);
+            device.destroy_pipeline_layout(skinning.pipeline_layout, None);
+            device.destroy_descriptor_pool(skinning.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(skinning.descriptor_set_layout, None);
+        }
+    }
+}
@@ -4,10 +4,15 @@ use ash::{extensions::khr, prelude::VkResult, vk};
 
 use crate::renderer::vulkan::device;
 
-use super::{Device, PhysicalDeviceConfig};
+use super::{Allocation, Allocator, Device, PhysicalDeviceConfig};
 
-struct DepthBuffer {
-    memory: vk::DeviceMemory,
+const PRESENT_FALLBACK_WINDOW: u32 = 8;
+const PRESENT_FALLBACK_THRESHOLD: u32 = 3;
+
+#[derive(Debug)]
+struct AttachmentImage {
+    // Returns the image memory to the pool once the swapchain is replaced
+    _allocation: Allocation,
     image: vk::Image,
     view: vk::ImageView,
 }
@@ -16,7 +21,8 @@ pub(super) struct Swapchain {
     pub(super) extent: vk::Extent2D,
     images: Vec<vk::Image>,
     views: Vec<vk::ImageView>,
-    depth_buffer: DepthBuffer,
+    depth_buffer: AttachmentImage,
+    color_buffer: Option<AttachmentImage>,
     framebuffers: Vec<vk::Framebuffer>,
     image_available: Vec<vk::Fence>,
     image_draw_ready: Vec<vk::Semaphore>,
@@ -28,6 +34,19 @@ pub(super) struct Swapchain {
     frame: usize,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum PresentStatus {
+    Optimal,
+    Suboptimal,
+    OutOfDate,
+}
+
+#[derive(Debug, Default)]
+pub(super) struct PresentMonitor {
+    history: u32,
+}
+
+#[derive(Debug)]
 pub struct Frame {
     pub(super) command: vk::CommandBuffer,
     pub(super) framebuffer: vk::Framebuffer,
@@ -37,7 +56,47 @@ pub struct Frame {
     pub(super) image_index: u32,
 }
 
+impl Frame {
+    #[inline]
+    pub fn image_index(&self) -> u32 {
+        self.image_index
+    }
+
+    #[inline]
+    pub fn command(&self) -> vk::CommandBuffer {
+        self.command
+    }
+}
+
+impl PresentMonitor {
+    pub(super) fn record(&mut self, status: PresentStatus) -> bool {
+        let window = (1 << PRESENT_FALLBACK_WINDOW) - 1;
+        self.history = ((self.history << 1) | (status != PresentStatus::Optimal) as u32) & window;
+        if self.history.count_ones() >= PRESENT_FALLBACK_THRESHOLD {
+            self.history = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
 impl Swapchain {
+    #[inline]
+    pub(super) fn depth_image(&self) -> vk::Image {
+        self.depth_buffer.image
+    }
+
+    #[inline]
+    pub(super) fn handle(&self) -> vk::SwapchainKHR {
+        self.handle
+    }
+
+    #[inline]
+    pub(super) fn framebuffers(&self) -> &[vk::Framebuffer] {
+        &self.framebuffers
+    }
+
     pub(super) fn acquire_image(&mut self, device: &ash::Device) -> VkResult<Frame> {
         let mut state = Frame {
             command: self.command_buffers[self.frame],
@@ -72,18 +131,23 @@ impl Swapchain {
         &mut self,
         state: Frame,
         present_queue: vk::Queue,
-    ) -> VkResult<bool> {
-        let suboptimal = unsafe {
+    ) -> VkResult<PresentStatus> {
+        let result = unsafe {
             self.loader.queue_present(
                 present_queue,
                 &vk::PresentInfoKHR::builder()
                     .image_indices(&[state.image_index])
                     .swapchains(&[self.handle])
                     .wait_semaphores(&[state.draw_finished]),
-            )?
+            )
         };
         self.frame = (self.frame + 1) % self.images.len();
-        Ok(suboptimal)
+        match result {
+            Ok(false) => Ok(PresentStatus::Optimal),
+            Ok(true) => Ok(PresentStatus::Suboptimal),
+            Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(PresentStatus::OutOfDate),
+            Err(err) => Err(err),
+        }
     }
 }
 
@@ -92,19 +156,26 @@ impl Device {
         instance: &ash::Instance,
         device: &ash::Device,
         config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
         surface: vk::SurfaceKHR,
         render_pass: vk::RenderPass,
+        window_extent: vk::Extent2D,
     ) -> VkResult<Swapchain> {
         let loader = khr::Swapchain::new(instance, device);
         let capabilities = &config.surface_capabilities;
+        let current_extent = if capabilities.current_extent.width == u32::MAX {
+            window_extent
+        } else {
+            capabilities.current_extent
+        };
         let extent = vk::Extent2D {
             width: u32::clamp(
-                capabilities.current_extent.width,
+                current_extent.width,
                 capabilities.min_image_extent.width,
                 capabilities.max_image_extent.width,
             ),
             height: u32::clamp(
-                capabilities.current_extent.height,
+                current_extent.height,
                 capabilities.min_image_extent.height,
                 capabilities.max_image_extent.height,
             ),
@@ -153,11 +224,32 @@ impl Device {
 
         let views =
             Device::create_swapchain_image_views(device, &images, config.surface_format.format)?;
-        let depth_buffer =
-            Device::create_swapchain_depth_buffer(device, config, &extent, &queue_indices)?;
+        let depth_buffer = Device::create_swapchain_attachment(
+            device,
+            config,
+            allocator,
+            &extent,
+            &queue_indices,
+            config.depth_format,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        )?;
+        let color_buffer = if config.sample_count != vk::SampleCountFlags::TYPE_1 {
+            Some(Device::create_swapchain_attachment(
+                device,
+                config,
+                allocator,
+                &extent,
+                &queue_indices,
+                config.surface_format.format,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+            )?)
+        } else {
+            None
+        };
         let framebuffers = Device::create_swapchain_framebuffers(
             device,
             &depth_buffer,
+            color_buffer.as_ref(),
             &views,
             &extent,
             render_pass,
@@ -177,6 +269,7 @@ impl Device {
             images,
             views,
             depth_buffer,
+            color_buffer,
             pool,
             command_buffers,
             framebuffers,
@@ -214,12 +307,20 @@ impl Device {
             .collect()
     }
 
-    fn create_swapchain_depth_buffer(
+    fn create_swapchain_attachment(
         device: &ash::Device,
         config: &PhysicalDeviceConfig,
+        allocator: &Allocator,
         extent: &vk::Extent2D,
         queue_indices: &[u32],
-    ) -> VkResult<DepthBuffer> {
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+    ) -> VkResult<AttachmentImage> {
+        let aspect_mask = if usage.contains(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT) {
+            config.depth_aspect()
+        } else {
+            vk::ImageAspectFlags::COLOR
+        };
         let image = unsafe {
             device.create_image(
                 &vk::ImageCreateInfo::builder()
@@ -230,7 +331,7 @@ impl Device {
                         height: extent.height,
                         depth: 1,
                     })
-                    .format(config.depth_format)
+                    .format(format)
                     .initial_layout(vk::ImageLayout::UNDEFINED)
                     .queue_family_indices(queue_indices)
                     .sharing_mode(if queue_indices.len() == 1 {
@@ -238,38 +339,29 @@ impl Device {
                     } else {
                         vk::SharingMode::CONCURRENT
                     })
-                    .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-                    .samples(vk::SampleCountFlags::TYPE_1)
+                    .usage(usage)
+                    .samples(config.sample_count)
                     .tiling(vk::ImageTiling::OPTIMAL)
                     .image_type(vk::ImageType::TYPE_2D),
                 None,
             )?
         };
-        let requirements = unsafe { device.get_image_memory_requirements(image) };
-        let mem_index = Device::memory_type_index(
+        let allocation = Device::allocate_image_memory(
+            device,
             config,
-            requirements.memory_type_bits,
+            allocator,
+            image,
             vk::MemoryPropertyFlags::DEVICE_LOCAL,
-        )
-        .ok_or(vk::Result::ERROR_UNKNOWN)?;
-        let memory = unsafe {
-            device.allocate_memory(
-                &vk::MemoryAllocateInfo::builder()
-                    .allocation_size(requirements.size)
-                    .memory_type_index(mem_index),
-                None,
-            )?
-        };
-        unsafe { device.bind_image_memory(image, memory, 0)? };
+        )?;
         let view = unsafe {
             device.create_image_view(
                 &vk::ImageViewCreateInfo::builder()
                     .image(image)
-                    .format(config.depth_format)
+                    .format(format)
                     .components(vk::ComponentMapping::default())
                     .view_type(vk::ImageViewType::TYPE_2D)
                     .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::DEPTH,
+                        aspect_mask,
                         base_mip_level: 0,
                         level_count: 1,
                         base_array_layer: 0,
@@ -278,8 +370,8 @@ impl Device {
                 None,
             )?
         };
-        Ok(DepthBuffer {
-            memory,
+        Ok(AttachmentImage {
+            _allocation: allocation,
             image,
             view,
         })
@@ -287,7 +379,8 @@ impl Device {
 
     fn create_swapchain_framebuffers(
         device: &ash::Device,
-        depth_buffer: &DepthBuffer,
+        depth_buffer: &AttachmentImage,
+        color_buffer: Option<&AttachmentImage>,
         views: &[vk::ImageView],
         extent: &vk::Extent2D,
         render_pass: vk::RenderPass,
@@ -295,9 +388,13 @@ impl Device {
         views
             .iter()
             .map(|&view| unsafe {
+                let attachments = match color_buffer {
+                    Some(color_buffer) => vec![color_buffer.view, depth_buffer.view, view],
+                    None => vec![view, depth_buffer.view],
+                };
                 device.create_framebuffer(
                     &vk::FramebufferCreateInfo::builder()
-                        .attachments(&[depth_buffer.view, view])
+                        .attachments(&attachments)
                         .layers(1)
                         .render_pass(render_pass)
                         .width(extent.width)
@@ -364,7 +461,10 @@ impl Device {
             }
             device.destroy_image_view(swapchain.depth_buffer.view, None);
             device.destroy_image(swapchain.depth_buffer.image, None);
-            device.free_memory(swapchain.depth_buffer.memory, None);
+            if let Some(color_buffer) = &swapchain.color_buffer {
+                device.destroy_image_view(color_buffer.view, None);
+                device.destroy_image(color_buffer.image, None);
+            }
             for &semaphore in &swapchain.image_draw_finished {
                 device.destroy_semaphore(semaphore, None);
             }
@@ -379,3 +479,42 @@ impl Device {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PresentStatus::*;
+
+    fn first_fallback(statuses: &[PresentStatus]) -> Option<usize> {
+        let mut monitor = PresentMonitor::default();
+        statuses.iter().position(|&status| monitor.record(status))
+    }
+
+    #[test]
+    fn falls_back_after_threshold_within_window() {
+        assert_eq!(
+            first_fallback(&[Suboptimal, OutOfDate, Suboptimal]),
+            Some(2)
+        );
+        assert_eq!(
+            first_fallback(&[Suboptimal, Optimal, Optimal, OutOfDate, Optimal, Suboptimal]),
+            Some(5)
+        );
+        // The first failure has left the eight frame window by the third one
+        let mut spread = vec![Suboptimal, Optimal, Optimal, Optimal, Suboptimal];
+        spread.extend([Optimal; 4]);
+        spread.push(Suboptimal);
+        assert_eq!(first_fallback(&spread), None);
+        assert_eq!(first_fallback(&[Optimal; 32]), None);
+    }
+
+    #[test]
+    fn history_restarts_after_fallback() {
+        let mut monitor = PresentMonitor::default();
+        let results = [Suboptimal; 6]
+            .iter()
+            .map(|&status| monitor.record(status))
+            .collect::<Vec<_>>();
+        assert_eq!(results, vec![false, false, true, false, false, true]);
+    }
+}
@@ -0,0 +1,330 @@
+use super::{image::Texture, partial::PartialObjects, Device, Frame};
+use crate::{
+    renderer::mesh::Vertex,
+    utils::{ResultExt, StaticResult},
+};
+use ash::{prelude::VkResult, vk};
+use std::{mem::size_of, path::Path};
+
+const TEXT_VERTEX_SHADER_PATH: &str = "shaders/spv/text_vert.spv";
+const TEXT_FRAGMENT_SHADER_PATH: &str = "shaders/spv/text_frag.spv";
+const TEXT_PUSH_CONSTANT_SIZE: u32 = 2 * size_of::<f32>() as u32;
+
+/// Screen space pipeline drawing glyph quads from the font atlas, blended
+/// over the frame without depth testing.
+#[derive(Debug)]
+pub(super) struct TextPipeline {
+    atlas: Texture,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_set: vk::DescriptorSet,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    shaders: Vec<vk::ShaderModule>,
+}
+
+impl Device {
+    fn create_text_pipeline(
+        device: &ash::Device,
+        render_pass: vk::RenderPass,
+        sample_count: vk::SampleCountFlags,
+        atlas: Texture,
+    ) -> StaticResult<TextPipeline> {
+        // The atlas is released along with everything else on failure
+        let mut partial = PartialObjects::new(device);
+        for object in atlas.partial_objects().iter() {
+            partial.add(*object);
+        }
+        let descriptor_set_layout = partial.add(unsafe {
+            device.create_descriptor_set_layout(
+                &vk::DescriptorSetLayoutCreateInfo::builder().bindings(&[
+                    vk::DescriptorSetLayoutBinding::builder()
+                        .binding(0)
+                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                        .descriptor_count(1)
+                        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
+                        .build(),
+                ]),
+                None,
+            )?
+        });
+        let descriptor_pool = partial.add(unsafe {
+            device.create_descriptor_pool(
+                &vk::DescriptorPoolCreateInfo::builder()
+                    .max_sets(1)
+                    .pool_sizes(&[vk::DescriptorPoolSize {
+                        ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                        descriptor_count: 1,
+                    }]),
+                None,
+            )?
+        });
+        let descriptor_set = unsafe {
+            device.allocate_descriptor_sets(
+                &vk::DescriptorSetAllocateInfo::builder()
+                    .descriptor_pool(descriptor_pool)
+                    .set_layouts(&[descriptor_set_layout]),
+            )?[0]
+        };
+        unsafe {
+            device.update_descriptor_sets(
+                &[vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(0)
+                    .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .image_info(&[vk::DescriptorImageInfo {
+                        sampler: atlas.sampler(),
+                        image_view: atlas.view(),
+                        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    }])
+                    .build()],
+                &[],
+            );
+        }
+        let pipeline_layout = partial.add(unsafe {
+            device.create_pipeline_layout(
+                &vk::PipelineLayoutCreateInfo::builder()
+                    .set_layouts(&[descriptor_set_layout])
+                    .push_constant_ranges(&[vk::PushConstantRange {
+                        stage_flags: vk::ShaderStageFlags::VERTEX,
+                        offset: 0,
+                        size: TEXT_PUSH_CONSTANT_SIZE,
+                    }]),
+                None,
+            )?
+        });
+        let shaders = [
+            (vk::ShaderStageFlags::VERTEX, TEXT_VERTEX_SHADER_PATH),
+            (vk::ShaderStageFlags::FRAGMENT, TEXT_FRAGMENT_SHADER_PATH),
+        ]
+        .iter()
+        .map(|&(stage, path)| {
+            let stage = Device::load_shader_module(device, stage, Path::new(path))?;
+            partial.add(stage.module);
+            Ok(stage)
+        })
+        .collect::<StaticResult<Vec<_>>>()?;
+
+        let vertex = Vertex::default();
+        let vertex_attribs = [
+            (
+                0,
+                bytemuck::offset_of!(vertex, Vertex, pos),
+                vk::Format::R32G32B32_SFLOAT,
+            ),
+            (
+                1,
+                bytemuck::offset_of!(vertex, Vertex, color),
+                vk::Format::R32G32B32A32_SFLOAT,
+            ),
+            (
+                2,
+                bytemuck::offset_of!(vertex, Vertex, tex),
+                vk::Format::R32G32_SFLOAT,
+            ),
+        ]
+        .iter()
+        .map(
+            |&(location, offset, format)| vk::VertexInputAttributeDescription {
+                binding: 0,
+                location,
+                offset: offset as u32,
+                format,
+            },
+        )
+        .collect::<Vec<_>>();
+        let pipeline = unsafe {
+            device
+                .create_graphics_pipelines(
+                    vk::PipelineCache::null(),
+                    &[vk::GraphicsPipelineCreateInfo::builder()
+                        .color_blend_state(
+                            &vk::PipelineColorBlendStateCreateInfo::builder().attachments(&[
+                                vk::PipelineColorBlendAttachmentState::builder()
+                                    .blend_enable(true)
+                                    .color_blend_op(vk::BlendOp::ADD)
+                                    .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
+                                    .alpha_blend_op(vk::BlendOp::ADD)
+                                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                                    .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+                                    .color_write_mask(vk::ColorComponentFlags::all())
+                                    .build(),
+                            ]),
+                        )
+                        .depth_stencil_state(
+                            &vk::PipelineDepthStencilStateCreateInfo::builder()
+                                .depth_test_enable(false)
+                                .depth_write_enable(false),
+                        )
+                        .input_assembly_state(
+                            &vk::PipelineInputAssemblyStateCreateInfo::builder()
+                                .topology(vk::PrimitiveTopology::TRIANGLE_LIST),
+                        )
+                        .multisample_state(
+                            &vk::PipelineMultisampleStateCreateInfo::builder()
+                                .rasterization_samples(sample_count),
+                        )
+                        .rasterization_state(
+                            &vk::PipelineRasterizationStateCreateInfo::builder()
+                                .polygon_mode(vk::PolygonMode::FILL)
+                                .line_width(1.0)
+                                .cull_mode(vk::CullModeFlags::NONE),
+                        )
+                        .render_pass(render_pass)
+                        .stages(&shaders)
+                        .subpass(0)
+                        .vertex_input_state(
+                            &vk::PipelineVertexInputStateCreateInfo::builder()
+                                .vertex_binding_descriptions(&[vk::VertexInputBindingDescription {
+                                    input_rate: vk::VertexInputRate::VERTEX,
+                                    stride: size_of::<Vertex>() as u32,
+                                    binding: 0,
+                                }])
+                                .vertex_attribute_descriptions(&vertex_attribs),
+                        )
+                        .viewport_state(
+                            &vk::PipelineViewportStateCreateInfo::builder()
+                                .viewport_count(1)
+                                .scissor_count(1),
+                        )
+                        .dynamic_state(
+                            &vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(&[
+                                vk::DynamicState::VIEWPORT,
+                                vk::DynamicState::SCISSOR,
+                            ]),
+                        )
+                        .layout(pipeline_layout)
+                        .build()],
+                    None,
+                )
+                .map_err(|(_, err)| err)
+                .context("Failed to create text pipeline")?[0]
+        };
+        partial.finish();
+        Ok(TextPipeline {
+            atlas,
+            descriptor_set_layout,
+            descriptor_pool,
+            descriptor_set,
+            pipeline_layout,
+            pipeline,
+            shaders: shaders.iter().map(|stage| stage.module).collect(),
+        })
+    }
+
+    /// Uploads a single channel signed distance field atlas and sets up the
+    /// text pipeline around it, replacing a previously loaded atlas.
+    pub fn load_font_atlas(&mut self, pixels: &[u8], width: u32, height: u32) -> StaticResult<()> {
+        let limit = self.config.properties.limits.max_image_dimension2_d;
+        if width == 0 || height == 0 || width.max(height) > limit {
+            Err(format!(
+                "Unsupported font atlas extent {}x{}",
+                width, height
+            ))?;
+        }
+        if pixels.len() != (width * height) as usize {
+            Err("Font atlas size does not match its extent")?;
+        }
+        let atlas = self.create_texture(
+            pixels,
+            vk::Extent3D {
+                width,
+                height,
+                depth: 1,
+            },
+            vk::Format::R8_UNORM,
+            vk::ImageType::TYPE_2D,
+            vk::ImageViewType::TYPE_2D,
+            vk::Filter::LINEAR,
+        )?;
+        let text = Device::create_text_pipeline(
+            &self.device,
+            self.render_pass,
+            self.config.sample_count,
+            atlas,
+        )?;
+        if let Some(mut old) = self.text.replace(text) {
+            // Frames still in flight may reference the previous atlas
+            unsafe {
+                self.device.device_wait_idle()?;
+            }
+            Device::destroy_text_pipeline(&self.device, &mut old);
+        }
+        Ok(())
+    }
+
+    /// Queues glyph quads laid out in window pixels for the current frame,
+    /// they are drawn over everything else when the frame ends.
+    pub fn draw_text(&mut self, vertices: &[Vertex]) {
+        let color_transfer = self.color_transfer;
+        self.text_vertices
+            .extend(vertices.iter().map(|&vertex| Vertex {
+                color: color_transfer.apply(vertex.color),
+                ..vertex
+            }));
+    }
+
+    pub(super) fn flush_text(&mut self, frame: &Frame) -> VkResult<()> {
+        let text = match &self.text {
+            Some(text) if !self.text_vertices.is_empty() => text,
+            _ => {
+                self.text_vertices.clear();
+                return Ok(());
+            }
+        };
+        let buffer = Device::upload_lines(
+            &self.device,
+            &self.config,
+            &self.allocator,
+            &mut self.text_buffers,
+            frame.image_index as usize,
+            &self.text_vertices,
+        )?;
+        let extent = [
+            self.swapchain.extent.width as f32,
+            self.swapchain.extent.height as f32,
+        ];
+        unsafe {
+            self.device.cmd_bind_pipeline(
+                frame.command,
+                vk::PipelineBindPoint::GRAPHICS,
+                text.pipeline,
+            );
+            self.device.cmd_bind_descriptor_sets(
+                frame.command,
+                vk::PipelineBindPoint::GRAPHICS,
+                text.pipeline_layout,
+                0,
+                &[text.descriptor_set],
+                &[],
+            );
+            self.device.cmd_push_constants(
+                frame.command,
+                text.pipeline_layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                bytemuck::cast_slice(&extent),
+            );
+            self.device
+                .cmd_bind_vertex_buffers(frame.command, 0, &[buffer], &[0]);
+            self.device
+                .cmd_draw(frame.command, self.text_vertices.len() as u32, 1, 0, 0);
+        }
+        self.text_vertices.clear();
+        Ok(())
+    }
+
+    pub(super) fn destroy_text_pipeline(device: &ash::Device, text: &mut TextPipeline) {
+        unsafe {
+            device.destroy_pipeline(text.pipeline, None);
+            for &shader in &text.shaders {
+                device.destroy_shader_module(shader, None);
+            }
+            device.destroy_pipeline_layout(text.pipeline_layout, None);
+            device.destroy_descriptor_pool(text.descriptor_pool, None);
+            device.destroy_descriptor_set_layout(text.descriptor_set_layout, None);
+        }
+        Device::destroy_texture(device, &mut text.atlas);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{math::types::Vector2, utils::StaticResult};
+use std::{fs, path::Path};
+use winit::event::{ElementState, MouseButton, VirtualKeyCode, WindowEvent};
+
+macro_rules! key_names {
+    ($($key:ident),+ $(,)?) => {
+        &[$((VirtualKeyCode::$key, stringify!($key))),+]
+    };
+}
+
+// Keys are saved by variant name, which stays valid when winit reorders or
+// extends `VirtualKeyCode`
+const KEY_NAMES: &[(VirtualKeyCode, &str)] = key_names!(
+    Key1,
+    Key2,
+    Key3,
+    Key4,
+    Key5,
+    Key6,
+    Key7,
+    Key8,
+    Key9,
+    Key0,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+    Escape,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    F13,
+    F14,
+    F15,
+    F16,
+    F17,
+    F18,
+    F19,
+    F20,
+    F21,
+    F22,
+    F23,
+    F24,
+    Snapshot,
+    Scroll,
+    Pause,
+    Insert,
+    Home,
+    Delete,
+    End,
+    PageDown,
+    PageUp,
+    Left,
+    Up,
+    Right,
+    Down,
+    Back,
+    Return,
+    Space,
+    Compose,
+    Caret,
+    Numlock,
+    Numpad0,
+    Numpad1,
+    Numpad2,
+    Numpad3,
+    Numpad4,
+    Numpad5,
+    Numpad6,
+    Numpad7,
+    Numpad8,
+    Numpad9,
+    NumpadAdd,
+    NumpadDivide,
+    NumpadDecimal,
+    NumpadComma,
+    NumpadEnter,
+    NumpadEquals,
+    NumpadMultiply,
+    NumpadSubtract,
+    AbntC1,
+    AbntC2,
+    Apostrophe,
+    Apps,
+    Asterisk,
+    At,
+    Ax,
+    Backslash,
+    Calculator,
+    Capital,
+    Colon,
+    Comma,
+    Convert,
+    Equals,
+    Grave,
+    Kana,
+    Kanji,
+    LAlt,
+    LBracket,
+    LControl,
+    LShift,
+    LWin,
+    Mail,
+    MediaSelect,
+    MediaStop,
+    Minus,
+    Mute,
+    MyComputer,
+    NavigateForward,
+    NavigateBackward,
+    NextTrack,
+    NoConvert,
+    OEM102,
+    Period,
+    PlayPause,
+    Plus,
+    Power,
+    PrevTrack,
+    RAlt,
+    RBracket,
+    RControl,
+    RShift,
+    RWin,
+    Semicolon,
+    Slash,
+    Sleep,
+    Stop,
+    Sysrq,
+    Tab,
+    Underline,
+    Unlabeled,
+    VolumeDown,
+    VolumeUp,
+    Wake,
+    WebBack,
+    WebFavorites,
+    WebForward,
+    WebHome,
+    WebRefresh,
+    WebSearch,
+    WebStop,
+    Yen,
+    Copy,
+    Paste,
+    Cut,
+);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordedEvent {
+    KeyDown(VirtualKeyCode),
+    KeyUp(VirtualKeyCode),
+    MouseMove(Vector2),
+    MouseButton(MouseButton, bool),
+    Step(f32),
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct ReplayRecorder {
+    pub events: Vec<(u64, RecordedEvent)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayPlayer {
+    events: Vec<(u64, RecordedEvent)>,
+    cursor: usize,
+}
+
+fn key_name(key: VirtualKeyCode) -> &'static str {
+    KEY_NAMES
+        .iter()
+        .find(|&&(code, _)| code == key)
+        .map(|&(_, name)| name)
+        .expect("Key missing from the replay key table")
+}
+
+fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+    KEY_NAMES
+        .iter()
+        .find(|&&(_, key_name)| key_name == name)
+        .map(|&(key, _)| key)
+}
+
+impl RecordedEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match *event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                match (input.virtual_keycode, input.state) {
+                    (Some(key), ElementState::Pressed) => Some(RecordedEvent::KeyDown(key)),
+                    (Some(key), ElementState::Released) => Some(RecordedEvent::KeyUp(key)),
+                    (None, _) => None,
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => Some(RecordedEvent::MouseMove(
+                Vector2::new(position.x as f32, position.y as f32),
+            )),
+            WindowEvent::MouseInput { state, button, .. } => Some(RecordedEvent::MouseButton(
+                button,
+                state == ElementState::Pressed,
+            )),
+            _ => None,
+        }
+    }
+
+    fn encode(&self) -> String {
+        match *self {
+            RecordedEvent::KeyDown(key) => format!("key_down {}", key_name(key)),
+            RecordedEvent::KeyUp(key) => format!("key_up {}", key_name(key)),
+            RecordedEvent::MouseMove(position) => format!(
+                "mouse_move {:08x} {:08x}",
+                position.x.to_bits(),
+                position.y.to_bits()
+            ),
+            RecordedEvent::MouseButton(button, pressed) => {
+                let button = match button {
+                    MouseButton::Left => 0,
+                    MouseButton::Right => 1,
+                    MouseButton::Middle => 2,
+                    MouseButton::Other(other) => 3 + other as u32,
+                };
+                format!("mouse_button {} {}", button, pressed as u32)
+            }
+            RecordedEvent::Step(dt) => format!("step {:08x}", dt.to_bits()),
+        }
+    }
+
+    fn decode(kind: &str, args: &[&str]) -> Option<Self> {
+        let bits = |arg: &str| u32::from_str_radix(arg, 16).ok().map(f32::from_bits);
+        match (kind, args) {
+            ("key_down", [name]) => key_from_name(name).map(RecordedEvent::KeyDown),
+            ("key_up", [name]) => key_from_name(name).map(RecordedEvent::KeyUp),
+            ("mouse_move", [x, y]) => {
+                Some(RecordedEvent::MouseMove(Vector2::new(bits(x)?, bits(y)?)))
+            }
+            ("mouse_button", [button, pressed]) => {
+                let button = match button.parse::<u32>().ok()? {
+                    0 => MouseButton::Left,
+                    1 => MouseButton::Right,
+                    2 => MouseButton::Middle,
+                    other => MouseButton::Other((other - 3) as u16),
+                };
+                Some(RecordedEvent::MouseButton(button, *pressed == "1"))
+            }
+            ("step", [dt]) => bits(dt).map(RecordedEvent::Step),
+            _ => None,
+        }
+    }
+}
+
+impl ReplayRecorder {
+    pub fn new() -> Self {
+        Self { events: vec![] }
+    }
+
+    pub fn record(&mut self, frame: u64, event: RecordedEvent) {
+        self.events.push((frame, event));
+    }
+
+    pub fn save(&self, path: &Path) -> StaticResult<()> {
+        let mut contents = String::new();
+        for (frame, event) in &self.events {
+            contents.push_str(&format!("{} {}\n", frame, event.encode()));
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+impl ReplayPlayer {
+    pub fn new(events: Vec<(u64, RecordedEvent)>) -> Self {
+        Self { events, cursor: 0 }
+    }
+
+    pub fn load(path: &Path) -> StaticResult<Self> {
+        let contents = fs::read_to_string(path)?;
+        let mut events = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let event = match fields.as_slice() {
+                [] => continue,
+                [frame, kind, args @ ..] => frame
+                    .parse::<u64>()
+                    .ok()
+                    .zip(RecordedEvent::decode(kind, args)),
+                _ => None,
+            };
+            events.push(event.ok_or(format!(
+                "Invalid replay event at {}:{}",
+                path.display(),
+                number + 1
+            ))?);
+        }
+        Ok(Self::new(events))
+    }
+
+    pub fn next_events(&mut self, frame: u64) -> &[(u64, RecordedEvent)] {
+        let start = self.cursor;
+        while self
+            .events
+            .get(self.cursor)
+            .is_some_and(|&(event_frame, _)| event_frame <= frame)
+        {
+            self.cursor += 1;
+        }
+        &self.events[start..self.cursor]
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.cursor >= self.events.len()
+    }
+}
+
+impl From<ReplayRecorder> for ReplayPlayer {
+    fn from(recorder: ReplayRecorder) -> Self {
+        Self::new(recorder.events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn key_table_covers_every_key_once() {
+        assert_eq!(KEY_NAMES.len(), VirtualKeyCode::Cut as usize + 1);
+        for (index, &(key, name)) in KEY_NAMES.iter().enumerate() {
+            assert_eq!(key as usize, index);
+            assert_eq!(key_from_name(name), Some(key));
+        }
+        assert_eq!(RecordedEvent::decode("key_down", &["Cut1"]), None);
+        assert_eq!(RecordedEvent::decode("key_down", &["163"]), None);
+    }
+
+    #[test]
+    fn recorded_events_survive_save_and_load() {
+        let mut recorder = ReplayRecorder::new();
+        recorder.record(0, RecordedEvent::KeyDown(VirtualKeyCode::Escape));
+        recorder.record(0, RecordedEvent::MouseMove(Vector2::new(0.1, -240.5)));
+        recorder.record(1, RecordedEvent::MouseButton(MouseButton::Other(7), true));
+        recorder.record(1, RecordedEvent::Step(1.0 / 60.0));
+        recorder.record(3, RecordedEvent::KeyUp(VirtualKeyCode::NumpadSubtract));
+        let path = env::temp_dir().join(format!("replay_round_trip_{}.txt", std::process::id()));
+        recorder.save(&path).unwrap();
+        let loaded = ReplayPlayer::load(&path);
+        fs::remove_file(&path).unwrap();
+        let mut player = loaded.unwrap();
+
+        assert_eq!(player.next_events(0), &recorder.events[..2]);
+        assert_eq!(player.next_events(2), &recorder.events[2..4]);
+        assert!(!player.is_finished());
+        assert_eq!(player.next_events(3), &recorder.events[4..]);
+        assert!(player.is_finished());
+    }
+}
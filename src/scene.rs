@@ -1,4 +1,5 @@
 use crate::{
+    log,
     math::{
         transforms,
         types::{Matrix4, Vector3},
@@ -6,31 +7,72 @@ use crate::{
     physics, renderer,
 };
 
-use crate::utils::StaticResult;
+use crate::utils::{static_assert_send_sync, StaticResult};
 
+const STANDARD_GRAVITY: f32 = 9.81;
+
+#[derive(Debug, Clone)]
 pub struct Object {
     shape: physics::Shape,
-    pub(super) world: Matrix4,
-    pub(super) mesh: renderer::MeshHandle,
+    world: Matrix4,
+    mesh: renderer::MeshHandle,
+    body: Option<physics::BodyHandle>,
 }
 
+#[derive(Debug)]
 pub struct SceneBuilder {
     pub(super) shapes: Vec<physics::Shape>,
     pub(super) meshes: Vec<renderer::Mesh>,
     pub(super) camera: Option<renderer::CameraBuilder>,
     pub(super) objects: Vec<Object>,
+    gravity: Vector3,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ShapeHandle {
-    index: usize,
+    pub(super) index: usize,
+}
+
+/// Index of an object, stays valid in the `Scene` built from the builder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectHandle {
+    pub(super) index: usize,
 }
 
+/// Owns only plain data, so it is `Send + Sync` and can be handed to a
+/// physics thread or shared read-only with the render loop.
+#[derive(Debug)]
 pub struct Scene {
-    pub(super) objects: Vec<Object>,
+    objects: Vec<Object>,
     pub(super) camera: renderer::Camera,
 }
 
+static_assert_send_sync!(Scene, Object);
+
+impl ShapeHandle {
+    #[inline]
+    pub fn mesh_handle(&self) -> renderer::MeshHandle {
+        renderer::MeshHandle(self.index)
+    }
+}
+
+impl Object {
+    #[inline]
+    pub fn mesh_handle(&self) -> renderer::MeshHandle {
+        self.mesh
+    }
+
+    #[inline]
+    pub fn world_transform(&self) -> &Matrix4 {
+        &self.world
+    }
+
+    #[inline]
+    pub fn body(&self) -> Option<physics::BodyHandle> {
+        self.body
+    }
+}
+
 impl SceneBuilder {
     pub fn new() -> Self {
         Self {
@@ -38,25 +80,121 @@ impl SceneBuilder {
             shapes: vec![],
             objects: vec![],
             camera: None,
+            gravity: renderer::CAMERA_UP * -STANDARD_GRAVITY,
         }
     }
 
+    pub fn with_gravity(self, gravity: Vector3) -> Self {
+        Self { gravity, ..self }
+    }
+
+    #[inline]
+    pub fn gravity(&self) -> Vector3 {
+        self.gravity
+    }
+
+    pub fn create_world(&self) -> physics::PhysicsWorld {
+        let mut world = physics::PhysicsWorld::new();
+        world.set_gravity(self.gravity);
+        world
+    }
+
     pub fn add_shape(&mut self, shape: physics::Shape) -> ShapeHandle {
-        self.meshes.push(renderer::Mesh::from_shape(&shape));
+        self.add_shape_with_quality(shape, renderer::TessellationQuality::default())
+    }
+
+    pub fn add_shape_with_quality(
+        &mut self,
+        shape: physics::Shape,
+        quality: renderer::TessellationQuality,
+    ) -> ShapeHandle {
+        self.meshes
+            .push(renderer::Mesh::from_shape(&shape, quality));
         self.shapes.push(shape);
         ShapeHandle {
             index: self.shapes.len() - 1,
         }
     }
 
-    pub fn add_instance(&mut self, shape: ShapeHandle, location: Vector3) {
+    pub fn add_instance(&mut self, shape: ShapeHandle, location: Vector3) -> ObjectHandle {
         self.objects.push(Object {
-            shape: self.shapes[shape.index],
+            shape: self.shapes[shape.index].clone(),
             mesh: renderer::MeshHandle(shape.index),
             world: transforms::translate(location),
+            body: None,
+        });
+        ObjectHandle {
+            index: self.objects.len() - 1,
+        }
+    }
+
+    /// Adds `count.0 * count.1 * count.2` instances of `shape`, the one at
+    /// grid cell (i, j, k) placed at `origin + (i * spacing.x, j * spacing.y,
+    /// k * spacing.z)`. Handles are ordered with `i` varying slowest.
+    pub fn add_instance_grid(
+        &mut self,
+        shape: ShapeHandle,
+        origin: Vector3,
+        count: (u32, u32, u32),
+        spacing: Vector3,
+    ) -> Vec<ObjectHandle> {
+        let mut handles =
+            Vec::with_capacity(count.0 as usize * count.1 as usize * count.2 as usize);
+        for i in 0..count.0 {
+            for j in 0..count.1 {
+                for k in 0..count.2 {
+                    let offset = Vector3::new(
+                        i as f32 * spacing.x,
+                        j as f32 * spacing.y,
+                        k as f32 * spacing.z,
+                    );
+                    handles.push(self.add_instance(shape, origin + offset));
+                }
+            }
+        }
+        handles
+    }
+
+    #[inline]
+    pub fn object(&self, handle: ObjectHandle) -> Option<&Object> {
+        self.objects.get(handle.index)
+    }
+
+    pub fn add_body(
+        &mut self,
+        shape: ShapeHandle,
+        world: &physics::PhysicsWorld,
+        body: physics::BodyHandle,
+    ) {
+        let transform = match world.body(body) {
+            Some(rigid_body) => rigid_body.world_transform(),
+            None => {
+                log::warning(&format!("Skipping object of stale body handle {:?}", body));
+                return;
+            }
+        };
+        self.objects.push(Object {
+            shape: self.shapes[shape.index].clone(),
+            mesh: renderer::MeshHandle(shape.index),
+            world: transform,
+            body: Some(body),
         })
     }
 
+    pub fn add_world(&mut self, world: &physics::PhysicsWorld) {
+        self.add_world_with(world, |scene, body| scene.add_shape(body.shape.clone()))
+    }
+
+    pub fn add_world_with<F>(&mut self, world: &physics::PhysicsWorld, mut mesh_map: F)
+    where
+        F: FnMut(&mut Self, &physics::RigidBody) -> ShapeHandle,
+    {
+        for (handle, body) in world.bodies() {
+            let shape = mesh_map(self, body);
+            self.add_body(shape, world, handle);
+        }
+    }
+
     pub fn set_camera(&mut self, eye: Vector3, center: Vector3) {
         self.camera = Some(renderer::CameraBuilder::new(eye, center));
     }
@@ -72,3 +210,175 @@ impl SceneBuilder {
         })
     }
 }
+
+impl Scene {
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.objects.iter()
+    }
+
+    #[inline]
+    pub fn object(&self, handle: ObjectHandle) -> Option<&Object> {
+        self.objects.get(handle.index)
+    }
+
+    pub fn sync_with_world(&mut self, world: &physics::PhysicsWorld) {
+        for object in &mut self.objects {
+            if let Some(body) = object.body.and_then(|body| world.body(body)) {
+                object.world = body.world_transform();
+            }
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Scene {
+    type Item = &'a Object;
+    type IntoIter = std::slice::Iter<'a, Object>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.objects.iter()
+    }
+}
+
+impl Default for SceneBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_grid_places_every_cell() {
+        let mut builder = SceneBuilder::new();
+        let sphere = builder.add_shape(physics::Shape::new_sphere(0.5));
+        builder.add_instance(sphere, Vector3::ZERO);
+        let shape = builder.add_shape(physics::Shape::new_cuboid(Vector3::new(1.0, 1.0, 1.0)));
+        let origin = Vector3::new(1.0, -2.0, 0.5);
+        let spacing = Vector3::new(2.0, 3.0, 4.0);
+        let handles = builder.add_instance_grid(shape, origin, (2, 3, 4), spacing);
+        assert_eq!(handles.len(), 24);
+        let mut expected = handles.iter();
+        for i in 0..2 {
+            for j in 0..3 {
+                for k in 0..4 {
+                    let handle = *expected.next().unwrap();
+                    let object = builder.object(handle).unwrap();
+                    let position =
+                        origin + Vector3::new(i as f32 * 2.0, j as f32 * 3.0, k as f32 * 4.0);
+                    assert_eq!(object.world_transform().l.xyz(), position);
+                    assert_eq!(object.mesh_handle().0, shape.index);
+                }
+            }
+        }
+        // Handles follow the instance added before the grid
+        assert_eq!(handles[0].index, 1);
+        assert_eq!(handles[23].index, 24);
+    }
+
+    #[test]
+    fn empty_grid_adds_nothing() {
+        let mut builder = SceneBuilder::new();
+        let shape = builder.add_shape(physics::Shape::new_sphere(0.5));
+        let spacing = Vector3::new(1.0, 1.0, 1.0);
+        assert!(builder
+            .add_instance_grid(shape, Vector3::ZERO, (4, 0, 4), spacing)
+            .is_empty());
+        assert!(builder.objects.is_empty());
+    }
+
+    fn world_with_bodies() -> (physics::PhysicsWorld, Vec<physics::BodyHandle>) {
+        let mut world = physics::PhysicsWorld::new();
+        let handles = [
+            Vector3::new(0.0, 0.0, 5.0),
+            Vector3::new(3.0, -1.0, 2.0),
+            Vector3::new(-4.0, 2.0, 0.0),
+        ]
+        .map(|position| {
+            world.add_body(physics::RigidBody::new(
+                physics::Shape::new_sphere(0.5),
+                position,
+                1.0,
+            ))
+        })
+        .to_vec();
+        (world, handles)
+    }
+
+    #[test]
+    fn world_bodies_become_objects() {
+        let (world, handles) = world_with_bodies();
+        let mut builder = SceneBuilder::new();
+        builder.add_world(&world);
+        assert_eq!(builder.objects.len(), 3);
+        for handle in handles {
+            let object = builder
+                .objects
+                .iter()
+                .find(|object| object.body() == Some(handle))
+                .unwrap();
+            let transform = world.body(handle).unwrap().world_transform();
+            for c in 0..4 {
+                assert_eq!(object.world_transform()[c], transform[c]);
+            }
+        }
+    }
+
+    #[test]
+    fn stale_body_handle_adds_no_object() {
+        let (mut world, handles) = world_with_bodies();
+        world.remove_body(handles[1]);
+        let mut builder = SceneBuilder::new();
+        let shape = builder.add_shape(physics::Shape::new_sphere(0.5));
+        builder.add_body(shape, &world, handles[1]);
+        assert!(builder.objects.is_empty());
+        builder.add_body(shape, &world, handles[0]);
+        assert_eq!(builder.objects.len(), 1);
+    }
+
+    #[test]
+    fn gravity_defaults_to_negative_camera_up() {
+        let builder = SceneBuilder::new();
+        assert_eq!(builder.gravity(), Vector3::new(0.0, 0.0, -9.81));
+        assert_eq!(
+            builder.create_world().gravity(),
+            Vector3::new(0.0, 0.0, -9.81)
+        );
+        let gravity = Vector3::new(1.0, 2.0, 3.0);
+        let builder = builder.with_gravity(gravity);
+        assert_eq!(builder.gravity(), gravity);
+        assert_eq!(builder.create_world().gravity(), gravity);
+    }
+
+    #[test]
+    fn default_builder_matches_new() {
+        let (mut default, mut new) = (SceneBuilder::default(), SceneBuilder::new());
+        assert_eq!(format!("{:?}", default), format!("{:?}", new));
+        for builder in [&mut default, &mut new] {
+            let shape = builder.add_shape(physics::Shape::new_sphere(0.5));
+            builder.add_instance(shape, Vector3::new(1.0, 2.0, 3.0));
+            builder.set_camera(Vector3::new(5.0, 5.0, 5.0), Vector3::ZERO);
+        }
+        assert_eq!(format!("{:?}", default), format!("{:?}", new));
+    }
+
+    #[test]
+    fn scene_iterates_every_added_object() {
+        let mut builder = SceneBuilder::new();
+        let shape = builder.add_shape(physics::Shape::new_sphere(0.5));
+        let positions = [Vector3::ZERO, Vector3::X, Vector3::Y, Vector3::Z];
+        for &position in &positions {
+            builder.add_instance(shape, position);
+        }
+        builder.set_camera(Vector3::new(5.0, 5.0, 5.0), Vector3::ZERO);
+        let scene = builder.build(60.0, 1.0, 0.1, 100.0).unwrap();
+        assert_eq!(scene.objects().count(), positions.len());
+        let mut count = 0;
+        for (object, &position) in (&scene).into_iter().zip(&positions) {
+            assert_eq!(object.world_transform().l.xyz(), position);
+            count += 1;
+        }
+        assert_eq!(count, positions.len());
+    }
+}
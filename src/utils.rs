@@ -1,5 +1,70 @@
+#[cfg(feature = "profile")]
+pub mod profiler;
+
+use ash::vk;
 use std::error::Error;
+use std::fmt::{self, Display};
 use std::result::Result;
 
 pub(super) type StaticResult<T> = Result<T, Box<dyn Error>>;
 pub(super) type ScopedResult<'a, T> = Result<T, Box<dyn Error + 'a>>;
+
+#[derive(Debug)]
+struct ContextError {
+    message: String,
+    source: Box<dyn Error>,
+}
+
+impl Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Vulkan result descriptions omit the code, which is what gets searched for
+        match self.source.downcast_ref::<vk::Result>() {
+            Some(result) => write!(f, "{}: {:?} ({})", self.message, result, result),
+            None => write!(f, "{}: {}", self.message, self.source),
+        }
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+pub(super) trait ResultExt<T> {
+    fn context(self, message: impl Display) -> StaticResult<T>;
+    fn with_context(self, message: impl FnOnce() -> String) -> StaticResult<T>;
+}
+
+impl<T, E: Into<Box<dyn Error>>> ResultExt<T> for Result<T, E> {
+    fn context(self, message: impl Display) -> StaticResult<T> {
+        self.with_context(|| message.to_string())
+    }
+
+    fn with_context(self, message: impl FnOnce() -> String) -> StaticResult<T> {
+        self.map_err(|source| {
+            Box::new(ContextError {
+                message: message(),
+                source: source.into(),
+            }) as Box<dyn Error>
+        })
+    }
+}
+
+macro_rules! static_assert_send_sync {
+    ($($t:ty),+ $(,)?) => {
+        const _: fn() = || {
+            fn assert_send_sync<T: Send + Sync>() {}
+            $(assert_send_sync::<$t>();)+
+        };
+    };
+}
+
+macro_rules! const_assert {
+    ($cond:expr $(,)?) => {
+        const _: () = assert!($cond);
+    };
+}
+
+pub(super) use const_assert;
+pub(super) use static_assert_send_sync;
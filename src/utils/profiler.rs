@@ -0,0 +1,128 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const FRAME_HISTORY: usize = 120;
+
+type Frame = HashMap<&'static str, Duration>;
+
+#[derive(Debug)]
+pub struct Profiler {
+    scopes: Vec<&'static str>,
+    current: Option<Frame>,
+    history: VecDeque<Frame>,
+}
+
+#[derive(Debug)]
+pub struct ScopeGuard {
+    name: &'static str,
+    start: Instant,
+}
+
+static PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+    scopes: vec![],
+    current: None,
+    history: VecDeque::new(),
+});
+
+impl Profiler {
+    pub fn begin_scope(name: &'static str) -> ScopeGuard {
+        ScopeGuard {
+            name,
+            start: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, name: &'static str, elapsed: Duration) {
+        if !self.scopes.contains(&name) {
+            self.scopes.push(name);
+        }
+        *self
+            .current
+            .get_or_insert_with(HashMap::new)
+            .entry(name)
+            .or_default() += elapsed;
+    }
+
+    pub fn next_frame() {
+        if let Ok(mut profiler) = PROFILER.lock() {
+            let frame = profiler.current.take().unwrap_or_default();
+            profiler.history.push_back(frame);
+            if profiler.history.len() > FRAME_HISTORY {
+                profiler.history.pop_front();
+            }
+        }
+    }
+
+    pub fn report() -> Vec<(&'static str, f64, f64)> {
+        let profiler = match PROFILER.lock() {
+            Ok(profiler) => profiler,
+            Err(_) => return vec![],
+        };
+        profiler
+            .scopes
+            .iter()
+            .filter_map(|&name| {
+                let samples: Vec<_> = profiler
+                    .history
+                    .iter()
+                    .chain(profiler.current.iter())
+                    .filter_map(|frame| frame.get(name))
+                    .map(|elapsed| elapsed.as_secs_f64() * 1e6)
+                    .collect();
+                if samples.is_empty() {
+                    return None;
+                }
+                let avg_us = samples.iter().sum::<f64>() / samples.len() as f64;
+                let max_us = samples.iter().copied().fold(0.0, f64::max);
+                Some((name, avg_us, max_us))
+            })
+            .collect()
+    }
+
+    pub fn reset() {
+        if let Ok(mut profiler) = PROFILER.lock() {
+            profiler.scopes.clear();
+            profiler.current = None;
+            profiler.history.clear();
+        }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        if let Ok(mut profiler) = PROFILER.lock() {
+            profiler.record(self.name, elapsed);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "profile"))]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn report_lists_timed_scopes() {
+        for _ in 0..3 {
+            {
+                let _outer = Profiler::begin_scope("profiler_test_outer");
+                let _inner = Profiler::begin_scope("profiler_test_inner");
+                thread::sleep(Duration::from_millis(1));
+            }
+            Profiler::next_frame();
+        }
+        let report = Profiler::report();
+        for name in ["profiler_test_outer", "profiler_test_inner"] {
+            let &(_, avg_us, max_us) = report
+                .iter()
+                .find(|(scope, _, _)| *scope == name)
+                .unwrap_or_else(|| panic!("{} missing from {:?}", name, report));
+            assert!(avg_us > 0.0 && avg_us <= max_us, "{:?}", report);
+            assert!(max_us < 1e6, "{:?}", report);
+        }
+    }
+}